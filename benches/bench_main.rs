@@ -1,14 +1,15 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use svgtidy::parser;
 use svgtidy::plugins::{
-    CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues, CollapseGroups,
-    ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients, ConvertPathData,
-    ConvertShapeToPath, ConvertStyleToAttrs, ConvertTransform, MergePaths, MoveElemsAttrsToGroup,
-    MoveGroupAttrsToElems, Plugin, RemoveComments, RemoveDesc, RemoveDimensions, RemoveDoctype,
-    RemoveEditorsNSData, RemoveEmptyAttrs, RemoveEmptyContainers, RemoveEmptyText,
-    RemoveHiddenElems, RemoveMetadata, RemoveRasterImages, RemoveScriptElement, RemoveStyleElement,
-    RemoveTitle, RemoveUnknownsAndDefaults, RemoveUnusedNS, RemoveUselessDefs,
-    RemoveUselessStrokeAndFill, RemoveXMLProcInst, SortAttrs, SortDefsChildren,
+    ApplyTransforms, CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues,
+    CollapseGroups, ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients,
+    ConvertPathData, ConvertShapeToPath, ConvertStyleToAttrs, ConvertTransform, InlineUse,
+    MergePaths, MoveElemsAttrsToGroup, MoveGroupAttrsToElems, Plugin, RemoveComments, RemoveDesc,
+    RemoveDimensions, RemoveDoctype, RemoveDuplicateDefs, RemoveEditorsNSData, RemoveEmptyAttrs,
+    RemoveEmptyContainers, RemoveEmptyText, RemoveHiddenElems, RemoveMetadata, RemoveRasterImages,
+    RemoveScriptElement, RemoveStyleElement, RemoveTitle, RemoveUnknownsAndDefaults,
+    RemoveUnusedNS, RemoveUselessDefs, RemoveUselessStrokeAndFill, RemoveXMLProcInst, SortAttrs,
+    SortDefsChildren,
 };
 use svgtidy::printer;
 
@@ -57,7 +58,9 @@ fn run_pipeline(input: &str) -> String {
         Box::new(MoveGroupAttrsToElems),
         Box::new(MoveElemsAttrsToGroup),
         Box::new(ConvertOneStopGradients),
-        Box::new(CleanupIds),
+        Box::new(InlineUse),
+        Box::new(RemoveDuplicateDefs),
+        Box::new(CleanupIds::default()),
         Box::new(RemoveUselessDefs),
         Box::new(RemoveEmptyContainers),
         Box::new(RemoveHiddenElems),
@@ -66,6 +69,7 @@ fn run_pipeline(input: &str) -> String {
         Box::new(RemoveUselessStrokeAndFill),
         Box::new(ConvertEllipseToCircle),
         Box::new(ConvertShapeToPath),
+        Box::new(ApplyTransforms::default()),
         Box::new(ConvertPathData::default()),
         Box::new(ConvertTransform::default()),
         Box::new(MergePaths),
@@ -0,0 +1,797 @@
+//! Bounding boxes of paths, shapes and element subtrees.
+//!
+//! Boxes are exact for every path segment type: curve and arc extrema are
+//! solved for after transforming, so a rotated ellipse gets its real extent
+//! rather than the box around its rotated control points. Anything that can't
+//! be measured reliably (text, markers, CSS transforms, viewports that don't
+//! clip) yields `None`, and callers should treat that as "could be anywhere".
+
+use crate::path::{PathData, Point, Segment};
+use crate::references::ReferenceIndex;
+use crate::style::{self, ComputedStyle, Origin, StyledNode};
+use crate::transform::{self, Matrix};
+use crate::tree::{Document, Element, Node};
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            min_x: x,
+            min_y: y,
+            max_x: x + width,
+            max_y: y + height,
+        }
+    }
+
+    /// Parses a `viewBox` value. `None` unless it's four numbers with a
+    /// positive width and height.
+    pub(crate) fn from_view_box(value: &str) -> Option<Self> {
+        let nums: Vec<f64> = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().ok())
+            .collect::<Option<_>>()?;
+        match nums[..] {
+            [x, y, w, h] if w > 0.0 && h > 0.0 => Some(Self::new(x, y, w, h)),
+            _ => None,
+        }
+    }
+
+    pub fn from_point(p: Point) -> Self {
+        Self {
+            min_x: p.x,
+            min_y: p.y,
+            max_x: p.x,
+            max_y: p.y,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    pub fn include(&mut self, p: Point) {
+        self.min_x = self.min_x.min(p.x);
+        self.min_y = self.min_y.min(p.y);
+        self.max_x = self.max_x.max(p.x);
+        self.max_y = self.max_y.max(p.y);
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    /// Whether the boxes overlap. Touching edges count as overlapping.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    /// The overlap of the boxes, `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        self.intersects(other).then(|| Rect {
+            min_x: self.min_x.max(other.min_x),
+            min_y: self.min_y.max(other.min_y),
+            max_x: self.max_x.min(other.max_x),
+            max_y: self.max_y.min(other.max_y),
+        })
+    }
+
+    /// Grows the box by `d` on every side.
+    pub fn expand(&self, d: f64) -> Rect {
+        Rect {
+            min_x: self.min_x - d,
+            min_y: self.min_y - d,
+            max_x: self.max_x + d,
+            max_y: self.max_y + d,
+        }
+    }
+
+    /// The box around this box's corners after `m`.
+    pub fn transform(&self, m: &Matrix) -> Rect {
+        let mut r = Rect::from_point(m.apply(Point::new(self.min_x, self.min_y)));
+        r.include(m.apply(Point::new(self.max_x, self.min_y)));
+        r.include(m.apply(Point::new(self.min_x, self.max_y)));
+        r.include(m.apply(Point::new(self.max_x, self.max_y)));
+        r
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+/// The stroke parameters that affect how far paint reaches past the geometry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stroke {
+    pub width: f64,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f64,
+}
+
+impl Stroke {
+    /// How far the stroke can reach from the geometry, in user units. Miter
+    /// joins can reach `miter_limit` half-widths out and square caps reach
+    /// the corner of a square, so this errs on the large side.
+    pub fn extent(&self) -> f64 {
+        let half = self.width / 2.0;
+        let join = match self.join {
+            LineJoin::Miter => self.miter_limit.max(1.0),
+            LineJoin::Round | LineJoin::Bevel => 1.0,
+        };
+        let cap = match self.cap {
+            LineCap::Square => std::f64::consts::SQRT_2,
+            LineCap::Butt | LineCap::Round => 1.0,
+        };
+        half * join.max(cap)
+    }
+}
+
+/// The box around `path` after `m`, `None` if it draws nothing.
+pub fn path_bbox(path: &PathData, m: &Matrix) -> Option<Rect> {
+    let mut rect: Option<Rect> = None;
+    let mut include = |p: Point| match &mut rect {
+        Some(r) => r.include(p),
+        None => rect = Some(Rect::from_point(p)),
+    };
+
+    for step in path.iter() {
+        let segment = step.normalized();
+        if let Segment::MoveTo { .. } = segment {
+            continue;
+        }
+        let from = m.apply(step.from);
+        include(from);
+        include(m.apply(step.to));
+
+        match segment {
+            Segment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let p1 = m.apply(Point::new(x1, y1));
+                let p2 = m.apply(Point::new(x2, y2));
+                let p3 = m.apply(Point::new(x, y));
+                for t in cubic_extrema(from.x, p1.x, p2.x, p3.x)
+                    .into_iter()
+                    .chain(cubic_extrema(from.y, p1.y, p2.y, p3.y))
+                {
+                    include(cubic_at(from, p1, p2, p3, t));
+                }
+            }
+            Segment::QuadTo { x1, y1, x, y } => {
+                let p1 = m.apply(Point::new(x1, y1));
+                let p2 = m.apply(Point::new(x, y));
+                for t in quad_extremum(from.x, p1.x, p2.x)
+                    .into_iter()
+                    .chain(quad_extremum(from.y, p1.y, p2.y))
+                {
+                    include(quad_at(from, p1, p2, t));
+                }
+            }
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => {
+                for p in arc_extrema(
+                    step.from,
+                    rx,
+                    ry,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    Point::new(x, y),
+                    m,
+                ) {
+                    include(p);
+                }
+            }
+            _ => {}
+        }
+    }
+    rect
+}
+
+fn cubic_at(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let mt = 1.0 - t;
+    let a = mt * mt * mt;
+    let b = 3.0 * mt * mt * t;
+    let c = 3.0 * mt * t * t;
+    let d = t * t * t;
+    Point::new(
+        a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+    )
+}
+
+fn quad_at(p0: Point, p1: Point, p2: Point, t: f64) -> Point {
+    let mt = 1.0 - t;
+    Point::new(
+        mt * mt * p0.x + 2.0 * mt * t * p1.x + t * t * p2.x,
+        mt * mt * p0.y + 2.0 * mt * t * p1.y + t * t * p2.y,
+    )
+}
+
+// Parameters in (0, 1) where one coordinate of a cubic has zero derivative
+fn cubic_extrema(p0: f64, p1: f64, p2: f64, p3: f64) -> Vec<f64> {
+    // B'(t)/3 = a t^2 + b t + c
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+    let mut roots = Vec::new();
+    if a.abs() < 1e-12 {
+        if b.abs() > 1e-12 {
+            roots.push(-c / b);
+        }
+    } else {
+        let disc = b * b - 4.0 * a * c;
+        if disc >= 0.0 {
+            let sq = disc.sqrt();
+            roots.push((-b + sq) / (2.0 * a));
+            roots.push((-b - sq) / (2.0 * a));
+        }
+    }
+    roots.retain(|t| *t > 0.0 && *t < 1.0);
+    roots
+}
+
+fn quad_extremum(p0: f64, p1: f64, p2: f64) -> Option<f64> {
+    let denom = p0 - 2.0 * p1 + p2;
+    if denom.abs() < 1e-12 {
+        return None;
+    }
+    let t = (p0 - p1) / denom;
+    (t > 0.0 && t < 1.0).then_some(t)
+}
+
+// Points of an arc (endpoint parameterization, before `m`) where the
+// transformed arc is extremal in x or y
+#[allow(clippy::too_many_arguments)]
+fn arc_extrema(
+    from: Point,
+    rx: f64,
+    ry: f64,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+    m: &Matrix,
+) -> Vec<Point> {
+    let Some(arc) = CenterArc::new(from, rx, ry, rotation, large_arc, sweep, to) else {
+        // Degenerate arcs are straight lines, already covered by endpoints
+        return Vec::new();
+    };
+
+    // The transformed arc is p(θ) = m(c) + A (cos θ, sin θ)
+    let (sin_phi, cos_phi) = arc.rotation.sin_cos();
+    let a11 = (m.a * cos_phi + m.c * sin_phi) * arc.rx;
+    let a12 = (-m.a * sin_phi + m.c * cos_phi) * arc.ry;
+    let a21 = (m.b * cos_phi + m.d * sin_phi) * arc.rx;
+    let a22 = (-m.b * sin_phi + m.d * cos_phi) * arc.ry;
+    let center = m.apply(arc.center);
+
+    let x_theta = a12.atan2(a11);
+    let y_theta = a22.atan2(a21);
+    [x_theta, x_theta + PI, y_theta, y_theta + PI]
+        .into_iter()
+        .filter(|theta| arc.contains(*theta))
+        .map(|theta| {
+            let (s, c) = theta.sin_cos();
+            Point::new(center.x + a11 * c + a12 * s, center.y + a21 * c + a22 * s)
+        })
+        .collect()
+}
+
+/// An arc in center parameterization (SVG implementation notes F.6.5).
+struct CenterArc {
+    center: Point,
+    rx: f64,
+    ry: f64,
+    // In radians
+    rotation: f64,
+    start: f64,
+    sweep: f64,
+}
+
+impl CenterArc {
+    fn new(
+        from: Point,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        to: Point,
+    ) -> Option<CenterArc> {
+        let (mut rx, mut ry) = (rx.abs(), ry.abs());
+        if rx == 0.0 || ry == 0.0 || from == to {
+            return None;
+        }
+        let phi = rotation.to_radians();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let dx = (from.x - to.x) / 2.0;
+        let dy = (from.y - to.y) / 2.0;
+        let x1 = cos_phi * dx + sin_phi * dy;
+        let y1 = -sin_phi * dx + cos_phi * dy;
+
+        // Scale radii up if they can't span the endpoints
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            let s = lambda.sqrt();
+            rx *= s;
+            ry *= s;
+        }
+
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coef = (num / den).max(0.0).sqrt();
+        if large_arc == sweep {
+            coef = -coef;
+        }
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+        let center = Point::new(
+            cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) / 2.0,
+            sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) / 2.0,
+        );
+
+        let angle =
+            |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let ux = (x1 - cx1) / rx;
+        let uy = (y1 - cy1) / ry;
+        let vx = (-x1 - cx1) / rx;
+        let vy = (-y1 - cy1) / ry;
+        let start = angle(1.0, 0.0, ux, uy);
+        let mut delta = angle(ux, uy, vx, vy);
+        if !sweep && delta > 0.0 {
+            delta -= 2.0 * PI;
+        } else if sweep && delta < 0.0 {
+            delta += 2.0 * PI;
+        }
+
+        Some(CenterArc {
+            center,
+            rx,
+            ry,
+            rotation: phi,
+            start,
+            sweep: delta,
+        })
+    }
+
+    fn contains(&self, theta: f64) -> bool {
+        let offset = if self.sweep >= 0.0 {
+            theta - self.start
+        } else {
+            self.start - theta
+        };
+        offset.rem_euclid(2.0 * PI) <= self.sweep.abs()
+    }
+}
+
+/// Computes bounding boxes of elements in a document, in the coordinate
+/// system of their parent.
+pub struct BBoxes<'a> {
+    doc: &'a Document,
+    index: ReferenceIndex,
+    styles: Vec<StyledNode>,
+    include_stroke: bool,
+}
+
+// Deep enough for real documents, shallow enough to stop `<use>` cycles
+const MAX_DEPTH: usize = 32;
+
+impl<'a> BBoxes<'a> {
+    /// With `include_stroke`, boxes cover the painted stroke, not just the
+    /// geometry, and filtered elements have no known box.
+    pub fn new(doc: &'a Document, include_stroke: bool) -> Self {
+        Self {
+            doc,
+            index: ReferenceIndex::build(doc),
+            styles: style::resolve(doc),
+            include_stroke,
+        }
+    }
+
+    /// The box of the element at `path`, including its own `transform`,
+    /// mapped through `m`. `None` when it's unknown or nothing is drawn.
+    pub fn element(&self, path: &[usize], m: &Matrix) -> Option<Rect> {
+        self.element_at(path, m, None, 0)
+    }
+
+    /// The element's own `transform`, see [`transform::element_transform`].
+    pub fn local_transform(&self, path: &[usize]) -> Option<Matrix> {
+        let Some(Node::Element(elem)) = self.doc.node_at(path) else {
+            return None;
+        };
+        transform::element_transform(elem, self.style(path)?)
+    }
+
+    /// The computed style of the element at `path`.
+    pub fn style(&self, path: &[usize]) -> Option<&ComputedStyle> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.styles.get(*first)?;
+        for i in rest {
+            node = node.children.get(*i)?;
+        }
+        Some(&node.style)
+    }
+
+    fn element_at(
+        &self,
+        path: &[usize],
+        m: &Matrix,
+        use_style: Option<&ComputedStyle>,
+        depth: usize,
+    ) -> Option<Rect> {
+        let Some(Node::Element(elem)) = self.doc.node_at(path) else {
+            return None;
+        };
+        let style = self.style(path)?;
+        if depth > MAX_DEPTH || style.value("display")? == "none" {
+            return None;
+        }
+        // Filters can paint anywhere in their (configurable) region
+        if self.include_stroke && style.value("filter")? != "none" {
+            return None;
+        }
+        let m = m.multiply(&self.local_transform(path)?);
+
+        match elem.name.as_str() {
+            "g" | "a" | "switch" => {
+                let mut rect: Option<Rect> = None;
+                let mut child_path = path.to_vec();
+                for (i, child) in elem.children.iter().enumerate() {
+                    let Node::Element(child) = child else {
+                        continue;
+                    };
+                    if is_non_rendering(&child.name) {
+                        continue;
+                    }
+                    child_path.push(i);
+                    let child_rect = self.element_at(&child_path, &m, use_style, depth + 1)?;
+                    child_path.pop();
+                    rect = Some(match rect {
+                        Some(r) => r.union(&child_rect),
+                        None => child_rect,
+                    });
+                }
+                rect
+            }
+            "use" => self.use_bbox(elem, style, &m, depth),
+            "svg" | "image" | "foreignObject" => {
+                // Nested viewports clip to their own box unless overflow is
+                // visible, so that box bounds whatever they draw
+                if elem.name == "svg"
+                    && style.origin("overflow") != Origin::Initial
+                    && matches!(style.value("overflow")?, "visible" | "auto")
+                {
+                    return None;
+                }
+                let rect = Rect::new(
+                    number(elem, "x")?,
+                    number(elem, "y")?,
+                    length(elem, "width")?,
+                    length(elem, "height")?,
+                );
+                Some(rect.transform(&m))
+            }
+            _ => {
+                let data = shape_path(elem)?;
+                let rect = path_bbox(&data, &m)?;
+                if !self.include_stroke {
+                    return Some(rect);
+                }
+                // Markers draw arbitrary content
+                for marker in ["marker-start", "marker-mid", "marker-end"] {
+                    if style.value(marker)? != "none" {
+                        return None;
+                    }
+                }
+                let mut extent = stroke(style)?.map_or(0.0, |s| s.extent());
+                // Content of a <use> inherits from the <use>, so take the
+                // wider of the two strokes
+                if let Some(use_style) = use_style {
+                    extent = extent.max(stroke(use_style)?.map_or(0.0, |s| s.extent()));
+                }
+                Some(rect.expand(extent * m.max_scale()))
+            }
+        }
+    }
+
+    fn use_bbox(
+        &self,
+        elem: &Element,
+        style: &ComputedStyle,
+        m: &Matrix,
+        depth: usize,
+    ) -> Option<Rect> {
+        let id = elem
+            .attributes
+            .get("href")
+            .or_else(|| elem.attributes.get("xlink:href"))?
+            .strip_prefix('#')?;
+        let target_path = self.index.definition(id)?;
+        let Some(Node::Element(target)) = self.doc.node_at(target_path) else {
+            return None;
+        };
+        let (x, y) = (number(elem, "x")?, number(elem, "y")?);
+
+        match target.name.as_str() {
+            // Instantiated as a viewport of the <use>'s size
+            "symbol" | "svg" => {
+                let target_style = self.style(target_path)?;
+                if target_style.origin("overflow") != Origin::Initial
+                    && matches!(target_style.value("overflow")?, "visible" | "auto")
+                {
+                    return None;
+                }
+                let width = length(elem, "width").or_else(|| length(target, "width"))?;
+                let height = length(elem, "height").or_else(|| length(target, "height"))?;
+                Some(Rect::new(x, y, width, height).transform(m))
+            }
+            _ => {
+                let m = m.multiply(&Matrix::translate(x, y));
+                self.element_at(target_path, &m, Some(style), depth + 1)
+            }
+        }
+    }
+}
+
+// Elements that never draw where they are
+fn is_non_rendering(name: &str) -> bool {
+    matches!(
+        name,
+        "defs"
+            | "title"
+            | "desc"
+            | "metadata"
+            | "style"
+            | "script"
+            | "symbol"
+            | "linearGradient"
+            | "radialGradient"
+            | "pattern"
+            | "clipPath"
+            | "mask"
+            | "filter"
+            | "marker"
+            | "animate"
+            | "animateMotion"
+            | "animateTransform"
+            | "set"
+    )
+}
+
+// A coordinate attribute, 0 when missing, `None` for anything but plain
+// numbers or px
+fn number(elem: &Element, name: &str) -> Option<f64> {
+    match elem.attributes.get(name) {
+        Some(v) => v.trim().trim_end_matches("px").parse().ok(),
+        None => Some(0.0),
+    }
+}
+
+// A size attribute that must be present
+fn length(elem: &Element, name: &str) -> Option<f64> {
+    elem.attributes.get(name)?;
+    number(elem, name)
+}
+
+// The stroke of an element, `Some(None)` when it has none
+fn stroke(style: &ComputedStyle) -> Option<Option<Stroke>> {
+    if style.value("stroke")? == "none" {
+        return Some(None);
+    }
+    let width: f64 = style
+        .value("stroke-width")?
+        .trim()
+        .trim_end_matches("px")
+        .parse()
+        .ok()?;
+    let join = match style.value("stroke-linejoin")? {
+        "round" => LineJoin::Round,
+        "bevel" => LineJoin::Bevel,
+        _ => LineJoin::Miter,
+    };
+    let cap = match style.value("stroke-linecap")? {
+        "round" => LineCap::Round,
+        "square" => LineCap::Square,
+        _ => LineCap::Butt,
+    };
+    let miter_limit = style.value("stroke-miterlimit")?.trim().parse().ok()?;
+    Some(Some(Stroke {
+        width,
+        join,
+        cap,
+        miter_limit,
+    }))
+}
+
+/// The geometry of a basic shape or `<path>` as path data, `None` for other
+/// elements or shapes that draw nothing.
+pub fn shape_path(elem: &Element) -> Option<PathData> {
+    let num = |name: &str| number(elem, name);
+    let segments = match elem.name.as_str() {
+        "path" => return Some(PathData::parse(elem.attributes.get("d")?)),
+        "rect" => {
+            let (x, y) = (num("x")?, num("y")?);
+            let (w, h) = (length(elem, "width")?, length(elem, "height")?);
+            if w <= 0.0 || h <= 0.0 {
+                return None;
+            }
+            // Rounded corners stay inside the box
+            vec![
+                Segment::MoveTo { x, y },
+                Segment::HorizontalTo { x: x + w },
+                Segment::VerticalTo { y: y + h },
+                Segment::HorizontalTo { x },
+                Segment::Close,
+            ]
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (num("cx")?, num("cy")?);
+            let (rx, ry) = if elem.name == "circle" {
+                let r = length(elem, "r")?;
+                (r, r)
+            } else {
+                let rx = elem.attributes.get("rx").and(num("rx"));
+                let ry = elem.attributes.get("ry").and(num("ry"));
+                // Either radius defaults to the other ("auto")
+                (rx.or(ry)?, ry.or(rx)?)
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return None;
+            }
+            let arc = |x| Segment::ArcTo {
+                rx,
+                ry,
+                rotation: 0.0,
+                large_arc: true,
+                sweep: false,
+                x,
+                y: cy,
+            };
+            vec![
+                Segment::MoveTo { x: cx - rx, y: cy },
+                arc(cx + rx),
+                arc(cx - rx),
+                Segment::Close,
+            ]
+        }
+        "line" => vec![
+            Segment::MoveTo {
+                x: num("x1")?,
+                y: num("y1")?,
+            },
+            Segment::LineTo {
+                x: num("x2")?,
+                y: num("y2")?,
+            },
+        ],
+        "polyline" | "polygon" => {
+            let coords: Vec<f64> = elem
+                .attributes
+                .get("points")?
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map_while(|s| s.parse().ok())
+                .collect();
+            let mut segments: Vec<Segment> = coords
+                .chunks_exact(2)
+                .map(|p| Segment::LineTo { x: p[0], y: p[1] })
+                .collect();
+            let first = segments.first_mut()?;
+            if let Segment::LineTo { x, y } = *first {
+                *first = Segment::MoveTo { x, y };
+            }
+            if elem.name == "polygon" {
+                segments.push(Segment::Close);
+            }
+            segments
+        }
+        _ => return None,
+    };
+    Some(PathData { segments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn assert_rect(rect: Option<Rect>, expected: [f64; 4]) {
+        let rect = rect.expect("no bbox");
+        let actual = [rect.min_x, rect.min_y, rect.max_x, rect.max_y];
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_curve_extrema() {
+        let id = Matrix::identity();
+        // The curve bulges past its endpoints to y = 7.5
+        assert_rect(
+            path_bbox(&PathData::parse("M0 0C0 10 10 10 10 0"), &id),
+            [0.0, 0.0, 10.0, 7.5],
+        );
+        assert_rect(
+            path_bbox(&PathData::parse("M0 0Q5 10 10 0"), &id),
+            [0.0, 0.0, 10.0, 5.0],
+        );
+        // Half circle of radius 5 below the x axis
+        assert_rect(
+            path_bbox(&PathData::parse("M0 0A5 5 0 0 0 10 0"), &id),
+            [0.0, 0.0, 10.0, 5.0],
+        );
+    }
+
+    #[test]
+    fn test_transformed_arc() {
+        // A circle stays a circle under rotation
+        let path = PathData::parse("M-5 0A5 5 0 1 0 5 0A5 5 0 1 0-5 0z");
+        assert_rect(
+            path_bbox(&path, &transform::parse("translate(10 10) rotate(30)")),
+            [5.0, 5.0, 15.0, 15.0],
+        );
+        // A rotated ellipse is wider than its axes
+        let path = PathData::parse("M-10 0A10 5 0 1 0 10 0A10 5 0 1 0-10 0z");
+        let rect = path_bbox(&path, &transform::parse("rotate(90)")).unwrap();
+        assert!((rect.width() - 10.0).abs() < 1e-9 && (rect.height() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_element_bboxes() {
+        let doc = parser::parse("<svg><defs><circle id=\"c\" r=\"2\"/></defs><g transform=\"translate(10 0)\"><rect width=\"4\" height=\"4\" stroke=\"red\" stroke-width=\"2\" stroke-linejoin=\"round\"/><use href=\"#c\" x=\"20\" y=\"20\"/></g></svg>").unwrap();
+        let geometry = BBoxes::new(&doc, false);
+        assert_rect(
+            geometry.element(&[0, 1], &Matrix::identity()),
+            [10.0, 0.0, 32.0, 22.0],
+        );
+        let painted = BBoxes::new(&doc, true);
+        assert_rect(
+            painted.element(&[0, 1, 0], &Matrix::identity()),
+            [-1.0, -1.0, 5.0, 5.0],
+        );
+    }
+}
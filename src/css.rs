@@ -0,0 +1,862 @@
+use crate::tree::{Element, Node};
+
+// Minimal CSS model for <style> contents and style="" attributes.
+// It only understands what SVG documents use in practice: plain rules,
+// conditional at-rules (@media, @supports, ...) and declaration blocks.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub value: String,
+    pub important: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub selectors: Vec<String>,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtRuleBody {
+    None,
+    Rules(Vec<CssItem>),
+    Declarations(Vec<Declaration>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtRule {
+    pub name: String,
+    pub prelude: String,
+    pub body: AtRuleBody,
+}
+
+impl AtRule {
+    /// Conditional group rules whose nested rules apply to the document.
+    /// `@keyframes` also nests rules, but their selectors are not element selectors.
+    pub fn is_conditional(&self) -> bool {
+        matches!(
+            self.name.as_str(),
+            "media" | "supports" | "document" | "container" | "layer" | "scope"
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssItem {
+    Rule(Rule),
+    AtRule(AtRule),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stylesheet {
+    pub items: Vec<CssItem>,
+}
+
+impl Stylesheet {
+    /// All style rules that may apply to elements, paired with whether they
+    /// are nested in a conditional at-rule (`@media`, `@supports`, ...).
+    pub fn rules(&self) -> Vec<(&Rule, bool)> {
+        let mut out = Vec::new();
+        collect_rules(&self.items, false, &mut out);
+        out
+    }
+}
+
+fn collect_rules<'a>(items: &'a [CssItem], conditional: bool, out: &mut Vec<(&'a Rule, bool)>) {
+    for item in items {
+        match item {
+            CssItem::Rule(rule) => out.push((rule, conditional)),
+            CssItem::AtRule(at_rule) if at_rule.is_conditional() => {
+                if let AtRuleBody::Rules(rules) = &at_rule.body {
+                    collect_rules(rules, true, out);
+                }
+            }
+            CssItem::AtRule(_) => {}
+        }
+    }
+}
+
+/// Whether `elem` is a `<style>` element holding CSS.
+pub fn is_style_element(elem: &Element) -> bool {
+    elem.name == "style"
+        && elem
+            .attributes
+            .get("type")
+            .is_none_or(|t| t.is_empty() || t == "text/css")
+}
+
+/// The text content of a CSS `<style>` element.
+pub fn style_text(elem: &Element) -> Option<String> {
+    if !is_style_element(elem) {
+        return None;
+    }
+    let mut text = String::new();
+    for child in &elem.children {
+        if let Node::Text(t) | Node::Cdata(t) = child {
+            text.push_str(t);
+        }
+    }
+    Some(text)
+}
+
+/// Parses a stylesheet. Returns `None` when the structure isn't understood
+/// (unbalanced braces, nested rules inside declaration blocks, ...), in which
+/// case callers should leave the CSS untouched.
+pub fn parse_stylesheet(css: &str) -> Option<Stylesheet> {
+    let css = strip_comments(css);
+    let items = parse_items(&css)?;
+    Some(Stylesheet { items })
+}
+
+fn parse_items(s: &str) -> Option<Vec<CssItem>> {
+    let mut items = Vec::new();
+    let bytes = s.as_bytes();
+    let mut pos = 0;
+
+    loop {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b';') {
+            pos += 1;
+        }
+        if pos >= bytes.len() {
+            break;
+        }
+
+        if bytes[pos] == b'@' {
+            let name_start = pos + 1;
+            let mut name_end = name_start;
+            while name_end < bytes.len()
+                && (bytes[name_end].is_ascii_alphanumeric() || bytes[name_end] == b'-')
+            {
+                name_end += 1;
+            }
+            let name = s[name_start..name_end].to_ascii_lowercase();
+            let (prelude_end, terminator) = find_top_level(s, name_end, b";{")?;
+            let prelude = collapse_whitespace(&s[name_end..prelude_end]);
+
+            if terminator == b';' {
+                items.push(CssItem::AtRule(AtRule {
+                    name,
+                    prelude,
+                    body: AtRuleBody::None,
+                }));
+                pos = prelude_end + 1;
+                continue;
+            }
+
+            let block_end = find_block_end(s, prelude_end)?;
+            let inner = &s[prelude_end + 1..block_end];
+            let mut at_rule = AtRule {
+                name,
+                prelude,
+                body: AtRuleBody::None,
+            };
+            at_rule.body = if at_rule.is_conditional() || at_rule.name.ends_with("keyframes") {
+                AtRuleBody::Rules(parse_items(inner)?)
+            } else {
+                AtRuleBody::Declarations(parse_block_declarations(inner)?)
+            };
+            items.push(CssItem::AtRule(at_rule));
+            pos = block_end + 1;
+        } else {
+            let (prelude_end, _) = find_top_level(s, pos, b"{")?;
+            let block_end = find_block_end(s, prelude_end)?;
+            let selectors = split_top_level(&s[pos..prelude_end], b',')
+                .into_iter()
+                .map(|sel| minify_selector(sel.trim()))
+                .filter(|sel| !sel.is_empty())
+                .collect::<Vec<_>>();
+            if selectors.is_empty() {
+                return None;
+            }
+            let declarations = parse_block_declarations(&s[prelude_end + 1..block_end])?;
+            items.push(CssItem::Rule(Rule {
+                selectors,
+                declarations,
+            }));
+            pos = block_end + 1;
+        }
+    }
+
+    Some(items)
+}
+
+fn parse_block_declarations(s: &str) -> Option<Vec<Declaration>> {
+    // Nested rules (CSS nesting) are not supported.
+    if find_top_level(s, 0, b"{}").is_some() {
+        return None;
+    }
+    Some(parse_declarations(s))
+}
+
+/// Parses a declaration list such as the value of a `style` attribute.
+///
+/// Semicolons and colons inside strings, `url(...)` and other functions don't
+/// split declarations, comments are dropped and `!important` is recognized.
+/// XML entity references (`&quot;`) are kept intact.
+pub fn parse_declarations(s: &str) -> Vec<Declaration> {
+    let s = strip_comments(s);
+    let mut declarations = Vec::new();
+
+    for part in split_top_level(&s, b';') {
+        let Some((colon, _)) = find_top_level(part, 0, b":") else {
+            continue;
+        };
+        let raw_name = part[..colon].trim();
+        if raw_name.is_empty() {
+            continue;
+        }
+        // Custom properties are case-sensitive, everything else is not.
+        let name = if raw_name.starts_with("--") {
+            raw_name.to_string()
+        } else {
+            raw_name.to_ascii_lowercase()
+        };
+
+        let mut value = part[colon + 1..].trim();
+        let mut important = false;
+        if let Some(bang) = value.rfind('!') {
+            if value[bang + 1..].trim().eq_ignore_ascii_case("important") {
+                important = true;
+                value = value[..bang].trim_end();
+            }
+        }
+        if value.is_empty() {
+            continue;
+        }
+
+        declarations.push(Declaration {
+            name,
+            value: value.to_string(),
+            important,
+        });
+    }
+
+    declarations
+}
+
+pub fn stringify_declarations(declarations: &[Declaration]) -> String {
+    let mut out = String::new();
+    for (i, decl) in declarations.iter().enumerate() {
+        if i > 0 {
+            out.push(';');
+        }
+        out.push_str(&decl.name);
+        out.push(':');
+        out.push_str(&decl.value);
+        if decl.important {
+            out.push_str("!important");
+        }
+    }
+    out
+}
+
+pub fn stringify(sheet: &Stylesheet) -> String {
+    let mut out = String::new();
+    stringify_items(&sheet.items, &mut out);
+    out
+}
+
+fn stringify_items(items: &[CssItem], out: &mut String) {
+    for item in items {
+        match item {
+            CssItem::Rule(rule) => {
+                out.push_str(&rule.selectors.join(","));
+                out.push('{');
+                out.push_str(&stringify_declarations(&rule.declarations));
+                out.push('}');
+            }
+            CssItem::AtRule(at_rule) => {
+                out.push('@');
+                out.push_str(&at_rule.name);
+                if !at_rule.prelude.is_empty() {
+                    out.push(' ');
+                    out.push_str(&at_rule.prelude);
+                }
+                match &at_rule.body {
+                    AtRuleBody::None => out.push(';'),
+                    AtRuleBody::Rules(rules) => {
+                        out.push('{');
+                        stringify_items(rules, out);
+                        out.push('}');
+                    }
+                    AtRuleBody::Declarations(decls) => {
+                        out.push('{');
+                        out.push_str(&stringify_declarations(decls));
+                        out.push('}');
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Collapses whitespace in a value outside of strings and drops it where CSS
+/// doesn't need it (around commas, inside parentheses).
+pub fn minify_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.trim().chars().peekable();
+    let mut pending_space = false;
+
+    while let Some(c) = chars.next() {
+        if c == '"' || c == '\'' {
+            if pending_space {
+                out.push(' ');
+                pending_space = false;
+            }
+            out.push(c);
+            for sc in chars.by_ref() {
+                out.push(sc);
+                if sc == c {
+                    break;
+                }
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space {
+            let prev = out.chars().last();
+            if !matches!(c, ',' | ')') && !matches!(prev, Some(',') | Some('(')) {
+                out.push(' ');
+            }
+            pending_space = false;
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn minify_selector(selector: &str) -> String {
+    let mut out = String::with_capacity(selector.len());
+    let mut pending_space = false;
+    let mut in_brackets = 0usize;
+    let mut chars = selector.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '"' || c == '\'' {
+            out.push(c);
+            for sc in chars.by_ref() {
+                out.push(sc);
+                if sc == c {
+                    break;
+                }
+            }
+            continue;
+        }
+        if c.is_whitespace() && in_brackets == 0 {
+            pending_space = true;
+            continue;
+        }
+        match c {
+            '[' => in_brackets += 1,
+            ']' => in_brackets = in_brackets.saturating_sub(1),
+            _ => {}
+        }
+        let is_combinator = in_brackets == 0 && matches!(c, '>' | '+' | '~');
+        if pending_space {
+            let prev = out.chars().last();
+            if !is_combinator && !matches!(prev, Some('>') | Some('+') | Some('~') | None) {
+                out.push(' ');
+            }
+            pending_space = false;
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn strip_comments(s: &str) -> String {
+    if !s.contains("/*") {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '"' || c == '\'' {
+            out.push(c);
+            for sc in chars.by_ref() {
+                out.push(sc);
+                if sc == c {
+                    break;
+                }
+            }
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            let mut prev = ' ';
+            for cc in chars.by_ref() {
+                if prev == '*' && cc == '/' {
+                    break;
+                }
+                prev = cc;
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Finds the first of `targets` at nesting depth zero, skipping strings,
+/// parenthesized groups, brackets and XML entity references.
+fn find_top_level(s: &str, from: usize, targets: &[u8]) -> Option<(usize, u8)> {
+    let bytes = s.as_bytes();
+    let mut depth = 0usize;
+    let mut i = from;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'"' | b'\'' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            // Escaped quotes delimit strings just like real ones
+            b'&' if bytes[i..].starts_with(b"&quot;") || bytes[i..].starts_with(b"&apos;") => {
+                let entity = &s[i..i + 6];
+                match s[i + 6..].find(entity) {
+                    Some(end) => i += 6 + end + 5,
+                    None => i = bytes.len(),
+                }
+            }
+            b'&' => {
+                let rest = &bytes[i + 1..];
+                let len = rest
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == b'#')
+                    .count();
+                if len > 0 && rest.get(len) == Some(&b';') {
+                    i += len + 1;
+                }
+            }
+            _ if depth == 0 && targets.contains(&b) => return Some((i, b)),
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+fn find_block_end(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut pos = open;
+    loop {
+        let (i, b) = find_top_level(s, pos, b"{}")?;
+        if b == b'{' {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        pos = i + 1;
+    }
+}
+
+fn split_top_level(s: &str, sep: u8) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while let Some((i, _)) = find_top_level(s, start, &[sep]) {
+        parts.push(&s[start..i]);
+        start = i + 1;
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum AttrOp {
+    Exists,
+    Equals,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AttrSelector {
+    name: String,
+    op: AttrOp,
+    value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attrs: Vec<AttrSelector>,
+    pseudos: Vec<String>,
+}
+
+/// A parsed complex selector such as `g > .icon:hover`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    // The combinator of the first part is unused.
+    parts: Vec<(Combinator, Compound)>,
+}
+
+impl Selector {
+    /// Parses a single selector (not a list). Returns `None` for syntax that
+    /// isn't supported, e.g. namespaces or nesting.
+    pub fn parse(s: &str) -> Option<Selector> {
+        let bytes = s.trim().as_bytes();
+        let mut parts = Vec::new();
+        let mut compound = Compound::default();
+        let mut combinator = Combinator::Descendant;
+        let mut empty = true;
+        let mut i = 0;
+
+        let read_ident = |i: &mut usize| -> String {
+            let start = *i;
+            while *i < bytes.len()
+                && (bytes[*i].is_ascii_alphanumeric()
+                    || matches!(bytes[*i], b'-' | b'_' | b'\\')
+                    || bytes[*i] >= 0x80)
+            {
+                *i += 1;
+            }
+            String::from_utf8_lossy(&bytes[start..*i]).into_owned()
+        };
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b' ' | b'\t' | b'\n' | b'\r' | b'>' | b'+' | b'~' => {
+                    let mut next = Combinator::Descendant;
+                    while i < bytes.len() {
+                        match bytes[i] {
+                            b'>' => next = Combinator::Child,
+                            b'+' => next = Combinator::NextSibling,
+                            b'~' => next = Combinator::SubsequentSibling,
+                            c if c.is_ascii_whitespace() => {}
+                            _ => break,
+                        }
+                        i += 1;
+                    }
+                    if empty {
+                        return None;
+                    }
+                    parts.push((combinator, std::mem::take(&mut compound)));
+                    combinator = next;
+                    empty = true;
+                    continue;
+                }
+                b'*' => {
+                    i += 1;
+                }
+                b'#' => {
+                    i += 1;
+                    let id = read_ident(&mut i);
+                    if id.is_empty() {
+                        return None;
+                    }
+                    compound.ids.push(id);
+                }
+                b'.' => {
+                    i += 1;
+                    let class = read_ident(&mut i);
+                    if class.is_empty() {
+                        return None;
+                    }
+                    compound.classes.push(class);
+                }
+                b'[' => {
+                    let end = i + bytes[i..].iter().position(|b| *b == b']')?;
+                    compound
+                        .attrs
+                        .push(parse_attr_selector(&s.trim()[i + 1..end])?);
+                    i = end + 1;
+                }
+                b':' => {
+                    let start = i;
+                    i += 1;
+                    if bytes.get(i) == Some(&b':') {
+                        i += 1;
+                    }
+                    if read_ident(&mut i).is_empty() {
+                        return None;
+                    }
+                    if bytes.get(i) == Some(&b'(') {
+                        let mut depth = 0;
+                        while i < bytes.len() {
+                            match bytes[i] {
+                                b'(' => depth += 1,
+                                b')' => {
+                                    depth -= 1;
+                                    if depth == 0 {
+                                        break;
+                                    }
+                                }
+                                _ => {}
+                            }
+                            i += 1;
+                        }
+                        if i >= bytes.len() {
+                            return None;
+                        }
+                        i += 1;
+                    }
+                    compound
+                        .pseudos
+                        .push(String::from_utf8_lossy(&bytes[start..i]).into_owned());
+                }
+                c if c.is_ascii_alphabetic() || c == b'_' || c >= 0x80 => {
+                    if !empty {
+                        return None;
+                    }
+                    compound.tag = Some(read_ident(&mut i));
+                }
+                _ => return None,
+            }
+            empty = false;
+        }
+
+        if empty {
+            return None;
+        }
+        parts.push((combinator, compound));
+        Some(Selector { parts })
+    }
+
+    /// Specificity as (ids, classes/attributes/pseudo-classes, types/pseudo-elements).
+    pub fn specificity(&self) -> (u32, u32, u32) {
+        let mut spec = (0, 0, 0);
+        for (_, compound) in &self.parts {
+            spec.0 += compound.ids.len() as u32;
+            spec.1 += (compound.classes.len() + compound.attrs.len()) as u32;
+            spec.2 += compound.tag.is_some() as u32;
+            for pseudo in &compound.pseudos {
+                if pseudo.starts_with("::") {
+                    spec.2 += 1;
+                } else {
+                    spec.1 += 1;
+                }
+            }
+        }
+        spec
+    }
+
+    /// Whether the selector uses pseudo-classes or pseudo-elements, whose
+    /// matching depends on state that isn't visible in the document.
+    pub fn has_pseudo(&self) -> bool {
+        self.parts.iter().any(|(_, c)| !c.pseudos.is_empty())
+    }
+
+    /// Matches the selector against `element`, whose ancestors are given from
+    /// the root down to its parent. Pseudo-classes and pseudo-elements are
+    /// assumed to match.
+    pub fn matches(&self, element: &Element, ancestors: &[&Element]) -> bool {
+        self.match_part(self.parts.len() - 1, element, ancestors)
+    }
+
+    fn match_part(&self, idx: usize, element: &Element, ancestors: &[&Element]) -> bool {
+        let (combinator, compound) = &self.parts[idx];
+        if !compound.matches(element) {
+            return false;
+        }
+        if idx == 0 {
+            return true;
+        }
+
+        match combinator {
+            Combinator::Descendant => (0..ancestors.len())
+                .rev()
+                .any(|i| self.match_part(idx - 1, ancestors[i], &ancestors[..i])),
+            Combinator::Child => match ancestors.split_last() {
+                Some((parent, rest)) => self.match_part(idx - 1, parent, rest),
+                None => false,
+            },
+            Combinator::NextSibling => previous_siblings(element, ancestors)
+                .last()
+                .is_some_and(|sibling| self.match_part(idx - 1, sibling, ancestors)),
+            Combinator::SubsequentSibling => previous_siblings(element, ancestors)
+                .iter()
+                .any(|sibling| self.match_part(idx - 1, sibling, ancestors)),
+        }
+    }
+}
+
+fn previous_siblings<'a>(element: &Element, ancestors: &[&'a Element]) -> Vec<&'a Element> {
+    let Some(parent) = ancestors.last() else {
+        return Vec::new();
+    };
+    let mut siblings = Vec::new();
+    for child in &parent.children {
+        if let Node::Element(child_elem) = child {
+            if std::ptr::eq(child_elem, element) {
+                break;
+            }
+            siblings.push(child_elem);
+        }
+    }
+    siblings
+}
+
+fn parse_attr_selector(s: &str) -> Option<AttrSelector> {
+    let ops = [
+        ("~=", AttrOp::Includes),
+        ("|=", AttrOp::DashMatch),
+        ("^=", AttrOp::Prefix),
+        ("$=", AttrOp::Suffix),
+        ("*=", AttrOp::Substring),
+        ("=", AttrOp::Equals),
+    ];
+    for (token, op) in ops {
+        if let Some((name, value)) = s.split_once(token) {
+            let value = value.trim();
+            // Case-insensitivity flags (`[a=b i]`) aren't supported.
+            let value = if (value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\''))
+            {
+                &value[1..value.len().max(2) - 1]
+            } else if value.contains(char::is_whitespace) {
+                return None;
+            } else {
+                value
+            };
+            return Some(AttrSelector {
+                name: name.trim().to_string(),
+                op,
+                value: value.to_string(),
+            });
+        }
+    }
+    let name = s.trim();
+    if name.is_empty() || name.contains('|') {
+        return None;
+    }
+    Some(AttrSelector {
+        name: name.to_string(),
+        op: AttrOp::Exists,
+        value: String::new(),
+    })
+}
+
+impl Compound {
+    fn matches(&self, element: &Element) -> bool {
+        if let Some(tag) = &self.tag {
+            if *tag != element.name {
+                return false;
+            }
+        }
+        if !self.ids.is_empty() {
+            let id = element.attributes.get("id");
+            if !self.ids.iter().all(|i| id == Some(i)) {
+                return false;
+            }
+        }
+        if !self.classes.is_empty() {
+            let class = element.attributes.get("class").map(String::as_str);
+            let classes = class.unwrap_or("").split_whitespace().collect::<Vec<_>>();
+            if !self.classes.iter().all(|c| classes.contains(&c.as_str())) {
+                return false;
+            }
+        }
+        self.attrs.iter().all(|attr| {
+            let Some(actual) = element.attributes.get(&attr.name) else {
+                return false;
+            };
+            match attr.op {
+                AttrOp::Exists => true,
+                AttrOp::Equals => *actual == attr.value,
+                AttrOp::Includes => actual.split_whitespace().any(|v| v == attr.value),
+                AttrOp::DashMatch => {
+                    *actual == attr.value || actual.starts_with(&format!("{}-", attr.value))
+                }
+                AttrOp::Prefix => !attr.value.is_empty() && actual.starts_with(&attr.value),
+                AttrOp::Suffix => !attr.value.is_empty() && actual.ends_with(&attr.value),
+                AttrOp::Substring => !attr.value.is_empty() && actual.contains(&attr.value),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_parse_declarations_tricky_values() {
+        let decls = parse_declarations(
+            "fill: url(\"data:image/png;base64,AA==\") ; /* c; */ stroke:red !important;font-family:&quot;A;B&quot;",
+        );
+        assert_eq!(decls.len(), 3);
+        assert_eq!(decls[0].value, "url(\"data:image/png;base64,AA==\")");
+        assert!(decls[1].important);
+        assert_eq!(decls[1].value, "red");
+        assert_eq!(decls[2].value, "&quot;A;B&quot;");
+    }
+
+    #[test]
+    fn test_stylesheet_roundtrip() {
+        let css =
+            "/* x */ @media (max-width: 10px) { .a > g { fill : red } } .b, .c { stroke: blue; }";
+        let sheet = parse_stylesheet(css).unwrap();
+        assert_eq!(
+            stringify(&sheet),
+            "@media (max-width: 10px){.a>g{fill:red}}.b,.c{stroke:blue}"
+        );
+        assert!(parse_stylesheet(".a { fill: red").is_none());
+    }
+
+    #[test]
+    fn test_selector_matching() {
+        let doc =
+            parser::parse("<svg><g class=\"a b\"><rect id=\"r\"/><circle/></g></svg>").unwrap();
+        let Node::Element(svg) = &doc.root[0] else {
+            panic!()
+        };
+        let Node::Element(g) = &svg.children[0] else {
+            panic!()
+        };
+        let Node::Element(circle) = &g.children[1] else {
+            panic!()
+        };
+
+        let ancestors = [svg, g];
+        assert!(Selector::parse("svg .b circle")
+            .unwrap()
+            .matches(circle, &ancestors));
+        assert!(Selector::parse("g.a > circle")
+            .unwrap()
+            .matches(circle, &ancestors));
+        assert!(Selector::parse("#r + circle")
+            .unwrap()
+            .matches(circle, &ancestors));
+        assert!(Selector::parse("circle:hover")
+            .unwrap()
+            .matches(circle, &ancestors));
+        assert!(!Selector::parse(".c circle")
+            .unwrap()
+            .matches(circle, &ancestors));
+        assert!(!Selector::parse("svg > circle")
+            .unwrap()
+            .matches(circle, &ancestors));
+    }
+}
@@ -1,18 +1,27 @@
+pub mod bbox;
+pub mod css;
 pub mod parser;
+pub mod path;
 pub mod plugins;
+pub mod precision;
 pub mod printer;
+pub mod references;
+pub mod sprite;
+pub mod style;
+pub mod transform;
 pub mod tree;
 pub mod visitor;
 
 use crate::plugins::{
-    CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues, CollapseGroups,
-    ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients, ConvertPathData,
-    ConvertShapeToPath, ConvertStyleToAttrs, ConvertTransform, MergePaths, MoveElemsAttrsToGroup,
-    MoveGroupAttrsToElems, Plugin, RemoveComments, RemoveDesc, RemoveDimensions, RemoveDoctype,
-    RemoveEditorsNSData, RemoveEmptyAttrs, RemoveEmptyContainers, RemoveEmptyText,
-    RemoveHiddenElems, RemoveMetadata, RemoveRasterImages, RemoveScriptElement, RemoveTitle,
-    RemoveUnknownsAndDefaults, RemoveUnusedNS, RemoveUselessDefs, RemoveUselessStrokeAndFill,
-    RemoveXMLProcInst, SortAttrs, SortDefsChildren,
+    ApplyTransforms, CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues,
+    CollapseGroups, ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients,
+    ConvertPathData, ConvertShapeToPath, ConvertStyleToAttrs, ConvertTransform, InlineUse,
+    MergePaths, MinifyStyles, MoveElemsAttrsToGroup, MoveGroupAttrsToElems, Plugin, RemoveComments,
+    RemoveDesc, RemoveDimensions, RemoveDoctype, RemoveDuplicateDefs, RemoveEditorsNSData,
+    RemoveEmptyAttrs, RemoveEmptyContainers, RemoveEmptyText, RemoveHiddenElems, RemoveMetadata,
+    RemoveRasterImages, RemoveScriptElement, RemoveTitle, RemoveUnknownsAndDefaults,
+    RemoveUnusedNS, RemoveUselessDefs, RemoveUselessStrokeAndFill, RemoveXMLProcInst, SortAttrs,
+    SortDefsChildren,
 };
 use wasm_bindgen::prelude::*;
 
@@ -39,6 +48,10 @@ pub fn optimize(svg: &str) -> String {
         Box::new(RemoveScriptElement),
         Box::new(RemoveRasterImages),
         // RemoveStyleElement false by default in main, let's keep it out or minimal
+        Box::new(MinifyStyles {
+            float_precision: precision,
+            remove_unused: true,
+        }),
         Box::new(ConvertStyleToAttrs),
         Box::new(CleanupAttrs),
         Box::new(RemoveUselessStrokeAndFill),
@@ -46,7 +59,9 @@ pub fn optimize(svg: &str) -> String {
         Box::new(MoveGroupAttrsToElems),
         Box::new(MoveElemsAttrsToGroup),
         Box::new(ConvertOneStopGradients),
-        Box::new(CleanupIds),
+        Box::new(InlineUse),
+        Box::new(RemoveDuplicateDefs),
+        Box::new(CleanupIds::default()),
         Box::new(RemoveUselessDefs),
         Box::new(RemoveEmptyContainers),
         Box::new(RemoveHiddenElems),
@@ -54,26 +69,32 @@ pub fn optimize(svg: &str) -> String {
         Box::new(CollapseGroups),
         Box::new(ConvertEllipseToCircle),
         Box::new(ConvertShapeToPath),
+        Box::new(ApplyTransforms {
+            float_precision: precision,
+            error_budget: None,
+        }),
         Box::new(ConvertPathData {
             float_precision: precision,
             leading_zero: true,
-            ..Default::default()
+            ..ConvertPathData::default()
         }),
         Box::new(ConvertTransform {
             float_precision: precision,
             deg_precision: precision,
-            ..Default::default()
+            error_budget: None,
         }),
         Box::new(CleanupNumericValues {
             float_precision: precision,
             remove_px: true,
             leading_zero: true,
+            error_budget: None,
         }),
         Box::new(CleanupListOfValues {
             float_precision: precision,
             default_px: true,
             convert_to_px: true,
             leading_zero: true,
+            error_budget: None,
         }),
         Box::new(RemoveUnknownsAndDefaults::default()),
         Box::new(MergePaths),
@@ -1,47 +1,163 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use svgtidy::parser;
 use svgtidy::plugins::{
-    CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues, CollapseGroups,
-    ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients, ConvertPathData,
-    ConvertShapeToPath, ConvertStyleToAttrs, ConvertTransform, MergePaths, MoveElemsAttrsToGroup,
-    MoveGroupAttrsToElems, Plugin, RemoveComments, RemoveDesc, RemoveDimensions, RemoveDoctype,
-    RemoveEditorsNSData, RemoveEmptyAttrs, RemoveEmptyContainers, RemoveEmptyText,
-    RemoveHiddenElems, RemoveMetadata, RemoveRasterImages, RemoveScriptElement, RemoveStyleElement,
-    RemoveTitle, RemoveUnknownsAndDefaults, RemoveUnusedNS, RemoveUselessDefs,
-    RemoveUselessStrokeAndFill, RemoveXMLProcInst, SortAttrs, SortDefsChildren,
+    ApplyTransforms, CleanupAttrs, CleanupIds, CleanupListOfValues, CleanupNumericValues,
+    CollapseGroups, ConvertColors, ConvertEllipseToCircle, ConvertOneStopGradients,
+    ConvertPathData, ConvertPathToShapes, ConvertShapeToPath, ConvertStyleToAttrs,
+    ConvertTransform, CropViewBox, InlineUse, MergePaths, MinifyStyles, MoveElemsAttrsToGroup,
+    MoveGroupAttrsToElems, Plugin, PrefixIds, RemoveComments, RemoveDesc, RemoveDimensions,
+    RemoveDoctype, RemoveDuplicateDefs, RemoveEditorsNSData, RemoveEmptyAttrs,
+    RemoveEmptyContainers, RemoveEmptyText, RemoveHiddenElems, RemoveMetadata,
+    RemoveOffCanvasPaths, RemoveRasterImages, RemoveScriptElement, RemoveStyleElement, RemoveTitle,
+    RemoveUnknownsAndDefaults, RemoveUnusedNS, RemoveUselessDefs, RemoveUselessStrokeAndFill,
+    RemoveXMLProcInst, ReusePaths, Simplification, SimplifyPaths, SortAttrs, SortDefsChildren,
+    Tolerance,
 };
-use svgtidy::printer;
+use svgtidy::precision::ErrorBudget;
+use svgtidy::tree::Document;
+use svgtidy::{printer, sprite};
 use walkdir::WalkDir;
 
 #[derive(Parser, Debug, Clone)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input file or directory
-    input: PathBuf,
+    #[arg(required = true)]
+    input: Option<PathBuf>,
 
     /// Output file or directory (optional)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     output: Option<PathBuf>,
 
     /// Set numeric precision
-    #[arg(short, long, default_value_t = 3)]
+    #[arg(short, long, default_value_t = 3, global = true)]
     precision: u8,
 
     /// Enable specific plugins (comma-separated list)
-    #[arg(long, value_delimiter = ',')]
+    #[arg(long, value_delimiter = ',', global = true)]
     enable: Vec<String>,
 
     /// Disable specific plugins (comma-separated list)
-    #[arg(long, value_delimiter = ',')]
+    #[arg(long, value_delimiter = ',', global = true)]
     disable: Vec<String>,
 
     /// Pretty print output (disable minification)
-    #[arg(long)]
+    #[arg(long, global = true)]
     pretty: bool,
+
+    /// Prefix used by prefixIds (defaults to a hash of the file name)
+    #[arg(long, global = true)]
+    id_prefix: Option<String>,
+
+    /// Padding cropViewBox leaves around the content
+    #[arg(long, default_value_t = 0.0, global = true)]
+    crop_padding: f64,
+
+    /// Make the viewBox cropViewBox computes square
+    #[arg(long, global = true)]
+    crop_square: bool,
+
+    /// viewBox cropViewBox fits the content onto, e.g. "0 0 24 24"
+    #[arg(long, value_parser = parse_view_box, global = true)]
+    crop_target: Option<[f64; 4]>,
+
+    /// How far convertPathData may move geometry (defaults to the precision)
+    #[arg(long, global = true)]
+    path_tolerance: Option<f64>,
+
+    /// How far simplifyPaths may move outlines, in user units or as a
+    /// percentage of the viewBox, e.g. "0.5" or "0.1%"
+    #[arg(long, value_parser = parse_tolerance, default_value = "0.1%", global = true)]
+    simplify_tolerance: Tolerance,
+
+    /// Points simplifyPaths drops: "rdp" (Ramer–Douglas–Peucker) or "visvalingam"
+    #[arg(long, value_parser = parse_simplification, default_value = "rdp", global = true)]
+    simplify_algorithm: Simplification,
+
+    /// Let simplifyPaths refit polylines with curves
+    #[arg(long, global = true)]
+    simplify_curves: bool,
+
+    /// Round so nothing moves more than this many pixels when rendered,
+    /// instead of to a fixed number of decimals
+    #[arg(long, global = true)]
+    pixel_error: Option<f64>,
+
+    /// Rendered size of the larger side of the viewBox for --pixel-error
+    /// (defaults to the root's width/height)
+    #[arg(long, requires = "pixel_error", global = true)]
+    render_size: Option<f64>,
+}
+
+fn parse_tolerance(s: &str) -> Result<Tolerance, String> {
+    let (s, percent) = match s.strip_suffix('%') {
+        Some(s) => (s, true),
+        None => (s, false),
+    };
+    let n: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid number: {}", s))?;
+    if n < 0.0 {
+        return Err("tolerance can't be negative".to_string());
+    }
+    Ok(if percent {
+        Tolerance::ViewBox(n / 100.0)
+    } else {
+        Tolerance::Units(n)
+    })
+}
+
+fn parse_simplification(s: &str) -> Result<Simplification, String> {
+    match s {
+        "rdp" => Ok(Simplification::RamerDouglasPeucker),
+        "visvalingam" => Ok(Simplification::Visvalingam),
+        _ => Err("expected \"rdp\" or \"visvalingam\"".to_string()),
+    }
+}
+
+fn parse_view_box(s: &str) -> Result<[f64; 4], String> {
+    let nums: Vec<f64> = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| format!("invalid number: {}", s)))
+        .collect::<Result<_, _>>()?;
+    match nums[..] {
+        [x, y, w, h] if w > 0.0 && h > 0.0 => Ok([x, y, w, h]),
+        _ => Err("expected \"x y width height\" with a positive size".to_string()),
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Combine a directory of icons into a <symbol> sprite
+    Sprite {
+        /// Directory of SVG icons
+        input: PathBuf,
+
+        /// Also write a JSON manifest of symbol IDs and viewBoxes
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+    },
+    /// Split a <symbol> sprite into one SVG file per symbol
+    Unsprite {
+        /// Sprite file
+        input: PathBuf,
+    },
 }
 
 struct PluginConfig {
@@ -64,7 +180,11 @@ impl PluginConfig {
     }
 }
 
-fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
+fn get_config(
+    args: &Args,
+    file_name: &str,
+    error_budget: Option<ErrorBudget>,
+) -> Vec<Box<dyn Plugin>> {
     let mut plugins: Vec<Box<dyn Plugin>> = Vec::new();
 
     // Helper to create config
@@ -74,6 +194,15 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
 
     // Capture precision for closures (cast to usize)
     let precision = args.precision as usize;
+    let id_prefix = args.id_prefix.clone();
+    let file_name = file_name.to_string();
+    let crop = (args.crop_padding, args.crop_square, args.crop_target);
+    let path_tolerance = args.path_tolerance;
+    let simplify = (
+        args.simplify_tolerance,
+        args.simplify_algorithm,
+        args.simplify_curves,
+    );
 
     // Define all available plugins
     // Note: Order matters for optimal processing!
@@ -116,6 +245,16 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             Box::new(|| Box::new(RemoveStyleElement)),
             false,
         ), // Optional
+        p(
+            "minifyStyles",
+            Box::new(move || {
+                Box::new(MinifyStyles {
+                    float_precision: precision,
+                    remove_unused: true,
+                })
+            }),
+            true,
+        ),
         p(
             "convertStyleToAttrs",
             Box::new(|| Box::new(ConvertStyleToAttrs)),
@@ -148,7 +287,17 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             Box::new(|| Box::new(ConvertOneStopGradients)),
             true,
         ),
-        p("cleanupIds", Box::new(|| Box::new(CleanupIds)), true),
+        p("inlineUse", Box::new(|| Box::new(InlineUse)), true),
+        p(
+            "removeDuplicateDefs",
+            Box::new(|| Box::new(RemoveDuplicateDefs)),
+            true,
+        ),
+        p(
+            "cleanupIds",
+            Box::new(|| Box::new(CleanupIds::default())),
+            true,
+        ),
         p(
             "removeUselessDefs",
             Box::new(|| Box::new(RemoveUselessDefs)),
@@ -164,6 +313,22 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             Box::new(|| Box::new(RemoveHiddenElems)),
             true,
         ),
+        p(
+            "removeOffCanvasPaths",
+            Box::new(|| Box::new(RemoveOffCanvasPaths)),
+            false,
+        ),
+        p(
+            "cropViewBox",
+            Box::new(move || {
+                Box::new(CropViewBox {
+                    padding: crop.0,
+                    square: crop.1,
+                    target: crop.2,
+                })
+            }),
+            false,
+        ),
         p(
             "removeEmptyText",
             Box::new(|| Box::new(RemoveEmptyText)),
@@ -185,25 +350,58 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             Box::new(|| Box::new(ConvertShapeToPath)),
             true,
         ),
+        p(
+            "applyTransforms",
+            Box::new(move || {
+                Box::new(ApplyTransforms {
+                    float_precision: precision,
+                    error_budget,
+                })
+            }),
+            true,
+        ),
         // Configurable Plugins
+        p(
+            "simplifyPaths",
+            Box::new(move || {
+                let mut plugin = SimplifyPaths::new(simplify.0);
+                plugin.algorithm = simplify.1;
+                plugin.fit_curves = simplify.2;
+                plugin.float_precision = precision;
+                plugin.error_budget = error_budget;
+                Box::new(plugin)
+            }),
+            false,
+        ),
         p(
             "convertPathData",
             Box::new(move || {
                 Box::new(ConvertPathData {
                     float_precision: precision,
                     leading_zero: true,
-                    ..Default::default()
+                    tolerance: path_tolerance,
+                    error_budget,
+                    ..ConvertPathData::default()
                 })
             }),
             true,
         ),
+        p(
+            "convertPathToShapes",
+            Box::new(move || {
+                Box::new(ConvertPathToShapes {
+                    float_precision: precision,
+                })
+            }),
+            false,
+        ),
         p(
             "convertTransform",
             Box::new(move || {
                 Box::new(ConvertTransform {
                     float_precision: precision,
                     deg_precision: precision,
-                    ..Default::default()
+                    error_budget,
                 })
             }),
             true,
@@ -215,6 +413,7 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
                     float_precision: precision,
                     remove_px: true,
                     leading_zero: true,
+                    error_budget,
                 })
             }),
             true,
@@ -227,6 +426,7 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
                     default_px: true,
                     convert_to_px: true,
                     leading_zero: true,
+                    error_budget,
                 })
             }),
             true,
@@ -237,6 +437,7 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             true,
         ),
         p("mergePaths", Box::new(|| Box::new(MergePaths)), true),
+        p("reusePaths", Box::new(|| Box::new(ReusePaths)), false),
         p("convertColors", Box::new(|| Box::new(ConvertColors)), true),
         p(
             "removeEmptyAttrs",
@@ -248,6 +449,16 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
             Box::new(|| Box::new(RemoveUnusedNS)),
             true,
         ),
+        p(
+            "prefixIds",
+            Box::new(move || {
+                Box::new(match &id_prefix {
+                    Some(prefix) => PrefixIds::new(prefix),
+                    None => PrefixIds::from_file_name(&file_name),
+                })
+            }),
+            false,
+        ),
         p("sortAttrs", Box::new(|| Box::new(SortAttrs)), true),
         p(
             "sortDefsChildren",
@@ -278,40 +489,145 @@ fn get_config(args: &Args) -> Vec<Box<dyn Plugin>> {
     plugins
 }
 
-fn process_string(text: &str, args: &Args) -> Result<String, String> {
+fn optimize_document(doc: &mut Document, args: &Args, file_name: &str) {
+    let error_budget = args.pixel_error.map(|error| {
+        ErrorBudget {
+            error,
+            size: args.render_size,
+        }
+        .pinned(doc)
+    });
+    let plugins = get_config(args, file_name, error_budget);
+    for plugin in plugins {
+        plugin.apply(doc);
+        if let Some(report) = plugin.report() {
+            eprintln!("{}: {}", file_name, report);
+        }
+    }
+}
+
+fn process_string(text: &str, args: &Args, file_name: &str) -> Result<String, String> {
     match parser::parse(text) {
         Ok(mut doc) => {
-            let plugins = get_config(args);
-            for plugin in plugins {
-                plugin.apply(&mut doc);
-            }
+            optimize_document(&mut doc, args, file_name);
             Ok(printer::print(&doc))
         }
         Err(e) => Err(format!("Parse error: {}", e)),
     }
 }
 
+fn svg_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "svg"))
+        .map(|e| e.path().to_owned())
+        .collect()
+}
+
+fn run_sprite(args: &Args, input: &Path, manifest: Option<&PathBuf>) {
+    let mut files = svg_files(input);
+    files.sort();
+
+    let icons: Vec<(String, Document)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("Could not read {:?}: {}", path, e);
+                    return None;
+                }
+            };
+            match parser::parse(&text) {
+                Ok(mut doc) => {
+                    optimize_document(&mut doc, args, &path.to_string_lossy());
+                    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                    Some((sprite::symbol_id(&stem), doc))
+                }
+                Err(e) => {
+                    eprintln!("Error processing {:?}: Parse error: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let (doc, entries) = sprite::build(icons);
+    let out = printer::print(&doc);
+    match &args.output {
+        Some(path) => fs::write(path, out).expect("Could not write output file"),
+        None => println!("{}", out),
+    }
+    if let Some(path) = manifest {
+        fs::write(path, sprite::manifest_json(&entries)).expect("Could not write manifest file");
+    }
+}
+
+fn run_unsprite(args: &Args, input: &Path) {
+    let Some(out_dir) = &args.output else {
+        eprintln!("Error: unsprite needs an output directory (-o)");
+        std::process::exit(1);
+    };
+    let text = match fs::read_to_string(input) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Could not read input file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let doc = match parser::parse(&text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            eprintln!("Error: Parse error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = fs::create_dir_all(out_dir) {
+        eprintln!("Could not create {:?}: {}", out_dir, e);
+        std::process::exit(1);
+    }
+
+    let symbols = sprite::split(&doc);
+    symbols.into_par_iter().for_each(|(id, mut doc)| {
+        let path = out_dir.join(format!("{}.svg", sprite::symbol_id(&id)));
+        optimize_document(&mut doc, args, &path.to_string_lossy());
+        if let Err(e) = fs::write(&path, printer::print(&doc)) {
+            eprintln!("Error writing {:?}: {}", path, e);
+        }
+    });
+}
+
 fn main() {
     let args = Args::parse();
 
-    if args.input.is_dir() {
-        // Batch Mode
-        let walker = WalkDir::new(&args.input).into_iter();
+    match &args.command {
+        Some(Command::Sprite { input, manifest }) => {
+            run_sprite(&args, input, manifest.as_ref());
+            return;
+        }
+        Some(Command::Unsprite { input }) => {
+            run_unsprite(&args, input);
+            return;
+        }
+        None => {}
+    }
+    let Some(input) = args.input.clone() else {
+        return;
+    };
 
+    if input.is_dir() {
+        // Batch Mode
         // Collect files first to parallelize
-        let files: Vec<PathBuf> = walker
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().extension().map_or(false, |ext| ext == "svg"))
-            .map(|e| e.path().to_owned())
-            .collect();
+        let files = svg_files(&input);
 
         println!("Found {} SVG files. Processing in parallel...", files.len());
 
         files.par_iter().for_each(|input_path| {
             // Calculate output path
             let output_path = if let Some(ref out_dir) = args.output {
-                // Mirror structure: out_dir + (input_path - args.input)
-                let relative = input_path.strip_prefix(&args.input).unwrap();
+                // Mirror structure: out_dir + (input_path - input)
+                let relative = input_path.strip_prefix(&input).unwrap();
                 Some(out_dir.join(relative))
             } else {
                 None // If no output dir, maybe print? Or overwrite? Let's safeguard and strictly require output dir for batch OR just print (too noisy).
@@ -319,7 +635,7 @@ fn main() {
             };
 
             if let Ok(text) = fs::read_to_string(input_path) {
-                match process_string(&text, &args) {
+                match process_string(&text, &args, &input_path.to_string_lossy()) {
                     Ok(out) => {
                         if let Some(path) = output_path {
                             // Ensure parent exists
@@ -343,8 +659,8 @@ fn main() {
         println!("Done.");
     } else {
         // Single File Mode
-        match fs::read_to_string(&args.input) {
-            Ok(text) => match process_string(&text, &args) {
+        match fs::read_to_string(&input) {
+            Ok(text) => match process_string(&text, &args, &input.to_string_lossy()) {
                 Ok(out) => {
                     if let Some(output_path) = args.output {
                         fs::write(output_path, out).expect("Could not write output file");
@@ -0,0 +1,853 @@
+//! Path data (the `d` attribute): parsing, geometry helpers and
+//! serialization, shared by every plugin that touches paths.
+
+use crate::transform::Matrix;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// One path command. Coordinates are always absolute; use
+/// [`Segment::to_relative`] to get the deltas a relative command would carry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    MoveTo {
+        x: f64,
+        y: f64,
+    },
+    LineTo {
+        x: f64,
+        y: f64,
+    },
+    HorizontalTo {
+        x: f64,
+    },
+    VerticalTo {
+        y: f64,
+    },
+    CubicTo {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        x: f64,
+        y: f64,
+    },
+    SmoothCubicTo {
+        x2: f64,
+        y2: f64,
+        x: f64,
+        y: f64,
+    },
+    QuadTo {
+        x1: f64,
+        y1: f64,
+        x: f64,
+        y: f64,
+    },
+    SmoothQuadTo {
+        x: f64,
+        y: f64,
+    },
+    ArcTo {
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        x: f64,
+        y: f64,
+    },
+    Close,
+}
+
+impl Segment {
+    /// The command letter of the absolute form.
+    pub fn letter(&self) -> char {
+        match self {
+            Segment::MoveTo { .. } => 'M',
+            Segment::LineTo { .. } => 'L',
+            Segment::HorizontalTo { .. } => 'H',
+            Segment::VerticalTo { .. } => 'V',
+            Segment::CubicTo { .. } => 'C',
+            Segment::SmoothCubicTo { .. } => 'S',
+            Segment::QuadTo { .. } => 'Q',
+            Segment::SmoothQuadTo { .. } => 'T',
+            Segment::ArcTo { .. } => 'A',
+            Segment::Close => 'Z',
+        }
+    }
+
+    /// Shifts every coordinate (but not arc radii or flags) by `dx`/`dy`.
+    pub fn translate(&self, dx: f64, dy: f64) -> Segment {
+        match *self {
+            Segment::MoveTo { x, y } => Segment::MoveTo {
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::LineTo { x, y } => Segment::LineTo {
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::HorizontalTo { x } => Segment::HorizontalTo { x: x + dx },
+            Segment::VerticalTo { y } => Segment::VerticalTo { y: y + dy },
+            Segment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => Segment::CubicTo {
+                x1: x1 + dx,
+                y1: y1 + dy,
+                x2: x2 + dx,
+                y2: y2 + dy,
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::SmoothCubicTo { x2, y2, x, y } => Segment::SmoothCubicTo {
+                x2: x2 + dx,
+                y2: y2 + dy,
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::QuadTo { x1, y1, x, y } => Segment::QuadTo {
+                x1: x1 + dx,
+                y1: y1 + dy,
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::SmoothQuadTo { x, y } => Segment::SmoothQuadTo {
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large_arc,
+                sweep,
+                x: x + dx,
+                y: y + dy,
+            },
+            Segment::Close => Segment::Close,
+        }
+    }
+
+    /// The same segment with coordinates relative to `from`.
+    pub fn to_relative(&self, from: Point) -> Segment {
+        self.translate(-from.x, -from.y)
+    }
+
+    /// Reads a segment whose coordinates are relative to `from`.
+    pub fn to_absolute(&self, from: Point) -> Segment {
+        self.translate(from.x, from.y)
+    }
+
+    /// Where the pen ends up after this segment. `Close` needs the start of
+    /// the subpath, so it returns `from` here; [`PathData::iter`] handles it.
+    pub fn end_point(&self, from: Point) -> Point {
+        match *self {
+            Segment::MoveTo { x, y }
+            | Segment::LineTo { x, y }
+            | Segment::CubicTo { x, y, .. }
+            | Segment::SmoothCubicTo { x, y, .. }
+            | Segment::QuadTo { x, y, .. }
+            | Segment::SmoothQuadTo { x, y }
+            | Segment::ArcTo { x, y, .. } => Point::new(x, y),
+            Segment::HorizontalTo { x } => Point::new(x, from.y),
+            Segment::VerticalTo { y } => Point::new(from.x, y),
+            Segment::Close => from,
+        }
+    }
+
+    fn args(&self) -> Vec<Arg> {
+        use Arg::Num;
+        match *self {
+            Segment::MoveTo { x, y }
+            | Segment::LineTo { x, y }
+            | Segment::SmoothQuadTo { x, y } => vec![Num(x), Num(y)],
+            Segment::HorizontalTo { x } => vec![Num(x)],
+            Segment::VerticalTo { y } => vec![Num(y)],
+            Segment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => vec![Num(x1), Num(y1), Num(x2), Num(y2), Num(x), Num(y)],
+            Segment::SmoothCubicTo { x2, y2, x, y } => vec![Num(x2), Num(y2), Num(x), Num(y)],
+            Segment::QuadTo { x1, y1, x, y } => vec![Num(x1), Num(y1), Num(x), Num(y)],
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            } => vec![
+                Num(rx),
+                Num(ry),
+                Num(rotation),
+                Arg::Flag(large_arc),
+                Arg::Flag(sweep),
+                Num(x),
+                Num(y),
+            ],
+            Segment::Close => vec![],
+        }
+    }
+}
+
+enum Arg {
+    Num(f64),
+    Flag(bool),
+}
+
+/// A segment together with the pen position it starts from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub segment: Segment,
+    pub from: Point,
+    pub to: Point,
+    /// Start of the current subpath, where `Close` returns to
+    pub subpath_start: Point,
+    // The reflected control point for this segment, if it's S/T
+    reflected: Point,
+}
+
+impl Step {
+    /// The segment with the shorthand forms spelled out: `H`/`V` become `L`,
+    /// `S` becomes `C`, `T` becomes `Q` and `Close` a line back to the start.
+    pub fn normalized(&self) -> Segment {
+        let Point { x: rx, y: ry } = self.reflected;
+        match self.segment {
+            Segment::HorizontalTo { .. } | Segment::VerticalTo { .. } | Segment::Close => {
+                Segment::LineTo {
+                    x: self.to.x,
+                    y: self.to.y,
+                }
+            }
+            Segment::SmoothCubicTo { x2, y2, x, y } => Segment::CubicTo {
+                x1: rx,
+                y1: ry,
+                x2,
+                y2,
+                x,
+                y,
+            },
+            Segment::SmoothQuadTo { x, y } => Segment::QuadTo {
+                x1: rx,
+                y1: ry,
+                x,
+                y,
+            },
+            segment => segment,
+        }
+    }
+
+    /// The segment with coordinates relative to where it starts.
+    pub fn relative(&self) -> Segment {
+        self.segment.to_relative(self.from)
+    }
+}
+
+/// Parsed path data.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathData {
+    pub segments: Vec<Segment>,
+}
+
+impl PathData {
+    /// Parses `d`. Like renderers do, everything up to the first error is
+    /// kept and the rest is dropped.
+    pub fn parse(d: &str) -> PathData {
+        let mut lexer = PathLexer::new(d);
+        let mut segments = Vec::new();
+        let mut cur = Point::default();
+        let mut start = cur;
+        let mut prev_cmd: Option<char> = None;
+
+        loop {
+            lexer.skip_ws_comma();
+            let Some(c) = lexer.peek_char() else {
+                break;
+            };
+            let cmd = if c.is_ascii_alphabetic() {
+                lexer.read_char();
+                c
+            } else {
+                // Repeated arguments continue the previous command, and
+                // pairs after a moveto are linetos
+                match prev_cmd {
+                    Some('M') => 'L',
+                    Some('m') => 'l',
+                    Some('Z' | 'z') | None => break,
+                    Some(prev) => prev,
+                }
+            };
+            prev_cmd = Some(cmd);
+
+            let Some(segment) = read_segment(&mut lexer, cmd.to_ascii_uppercase()) else {
+                break;
+            };
+            let segment = if cmd.is_ascii_lowercase() {
+                segment.to_absolute(cur)
+            } else {
+                segment
+            };
+            match segment {
+                Segment::MoveTo { x, y } => {
+                    start = Point::new(x, y);
+                    cur = start;
+                }
+                Segment::Close => cur = start,
+                _ => cur = segment.end_point(cur),
+            }
+            segments.push(segment);
+        }
+
+        PathData { segments }
+    }
+
+    /// The segments with the pen position before and after each one.
+    pub fn iter(&self) -> Steps<'_> {
+        Steps {
+            segments: self.segments.iter(),
+            cur: Point::default(),
+            start: Point::default(),
+            control: None,
+        }
+    }
+
+    /// The path after `m`. Arcs get new radii and rotation (and the sweep
+    /// flips for mirroring transforms); `H`/`V` become `L` when `m` rotates
+    /// or skews.
+    pub fn transform(&self, m: &Matrix) -> PathData {
+        let p = |x: f64, y: f64| m.apply(Point::new(x, y));
+        let axis_aligned = m.b == 0.0 && m.c == 0.0;
+        let segments = self
+            .iter()
+            .map(|step| match step.segment {
+                Segment::MoveTo { x, y } => {
+                    let q = p(x, y);
+                    Segment::MoveTo { x: q.x, y: q.y }
+                }
+                Segment::LineTo { x, y } => {
+                    let q = p(x, y);
+                    Segment::LineTo { x: q.x, y: q.y }
+                }
+                Segment::HorizontalTo { x } if axis_aligned => {
+                    Segment::HorizontalTo { x: m.a * x + m.e }
+                }
+                Segment::VerticalTo { y } if axis_aligned => {
+                    Segment::VerticalTo { y: m.d * y + m.f }
+                }
+                Segment::HorizontalTo { .. } | Segment::VerticalTo { .. } => {
+                    let q = m.apply(step.to);
+                    Segment::LineTo { x: q.x, y: q.y }
+                }
+                Segment::CubicTo {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let (q1, q2, q) = (p(x1, y1), p(x2, y2), p(x, y));
+                    Segment::CubicTo {
+                        x1: q1.x,
+                        y1: q1.y,
+                        x2: q2.x,
+                        y2: q2.y,
+                        x: q.x,
+                        y: q.y,
+                    }
+                }
+                // Affine maps keep reflected control points reflected
+                Segment::SmoothCubicTo { x2, y2, x, y } => {
+                    let (q2, q) = (p(x2, y2), p(x, y));
+                    Segment::SmoothCubicTo {
+                        x2: q2.x,
+                        y2: q2.y,
+                        x: q.x,
+                        y: q.y,
+                    }
+                }
+                Segment::QuadTo { x1, y1, x, y } => {
+                    let (q1, q) = (p(x1, y1), p(x, y));
+                    Segment::QuadTo {
+                        x1: q1.x,
+                        y1: q1.y,
+                        x: q.x,
+                        y: q.y,
+                    }
+                }
+                Segment::SmoothQuadTo { x, y } => {
+                    let q = p(x, y);
+                    Segment::SmoothQuadTo { x: q.x, y: q.y }
+                }
+                Segment::ArcTo {
+                    rx,
+                    ry,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                } => {
+                    let (rx, ry, rotation) = transform_ellipse(rx, ry, rotation, m);
+                    let q = p(x, y);
+                    Segment::ArcTo {
+                        rx,
+                        ry,
+                        rotation,
+                        large_arc,
+                        sweep: sweep != (m.a * m.d - m.b * m.c < 0.0),
+                        x: q.x,
+                        y: q.y,
+                    }
+                }
+                Segment::Close => Segment::Close,
+            })
+            .collect();
+        PathData { segments }
+    }
+
+    pub fn write(&self, opts: &WriteOptions) -> String {
+        let mut s = String::new();
+        for (i, step) in self.iter().enumerate() {
+            if step.segment == Segment::Close {
+                s.push('z');
+                continue;
+            }
+            let abs = format_segment(step.segment.letter(), &step.segment.args(), opts);
+            let rel_letter = step.segment.letter().to_ascii_lowercase();
+            let rel = || format_segment(rel_letter, &step.relative().args(), opts);
+
+            let best = match opts.mode {
+                CoordMode::Absolute => abs,
+                // The first moveto is absolute either way, so spell it `M`
+                CoordMode::Relative if i == 0 => abs,
+                CoordMode::Relative => rel(),
+                CoordMode::Shortest => {
+                    let mut best = abs;
+                    let mut consider = |candidate: String| {
+                        if candidate.len() < best.len() {
+                            best = candidate;
+                        }
+                    };
+                    consider(rel());
+                    if let Segment::LineTo { x, y } = step.segment {
+                        if (y - step.from.y).abs() < f64::EPSILON {
+                            let h = Segment::HorizontalTo { x };
+                            consider(format_segment('H', &h.args(), opts));
+                            consider(format_segment('h', &h.to_relative(step.from).args(), opts));
+                        }
+                        if (x - step.from.x).abs() < f64::EPSILON {
+                            let v = Segment::VerticalTo { y };
+                            consider(format_segment('V', &v.args(), opts));
+                            consider(format_segment('v', &v.to_relative(step.from).args(), opts));
+                        }
+                    }
+                    best
+                }
+            };
+            s.push_str(&best);
+        }
+        s
+    }
+}
+
+// The radii and rotation (degrees) of an ellipse after `m`: the singular
+// values and direction of the major axis of `m * rotate(rotation) * scale(rx ry)`
+fn transform_ellipse(rx: f64, ry: f64, rotation: f64, m: &Matrix) -> (f64, f64, f64) {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let e11 = (m.a * cos + m.c * sin) * rx;
+    let e12 = (-m.a * sin + m.c * cos) * ry;
+    let e21 = (m.b * cos + m.d * sin) * rx;
+    let e22 = (-m.b * sin + m.d * cos) * ry;
+
+    // E * E^T = [[a, b], [b, c]]
+    let a = e11 * e11 + e12 * e12;
+    let b = e11 * e21 + e12 * e22;
+    let c = e21 * e21 + e22 * e22;
+    let mid = (a + c) / 2.0;
+    let spread = (((a - c) / 2.0).powi(2) + b * b).sqrt();
+    let major = (mid + spread).sqrt();
+    let minor = (mid - spread).max(0.0).sqrt();
+    let angle = if spread < 1e-12 {
+        // A circle: any rotation will do
+        0.0
+    } else {
+        (0.5 * (2.0 * b).atan2(a - c)).to_degrees()
+    };
+    (major, minor, angle)
+}
+
+impl fmt::Display for PathData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.write(&WriteOptions::default()))
+    }
+}
+
+pub struct Steps<'a> {
+    segments: std::slice::Iter<'a, Segment>,
+    cur: Point,
+    start: Point,
+    control: Option<(char, Point)>,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let segment = *self.segments.next()?;
+        let from = self.cur;
+        let to = match segment {
+            Segment::Close => self.start,
+            _ => segment.end_point(from),
+        };
+        if let Segment::MoveTo { .. } = segment {
+            self.start = to;
+        }
+
+        // S reflects the last control point of a C/S, T that of a Q/T;
+        // otherwise the reflection is the current point
+        let reflect = |kind: char| match self.control {
+            Some((k, p)) if k == kind => Point::new(2.0 * from.x - p.x, 2.0 * from.y - p.y),
+            _ => from,
+        };
+        let reflected = match segment {
+            Segment::SmoothCubicTo { .. } => reflect('C'),
+            Segment::SmoothQuadTo { .. } => reflect('Q'),
+            _ => from,
+        };
+        let control = match segment {
+            Segment::CubicTo { x2, y2, .. } | Segment::SmoothCubicTo { x2, y2, .. } => {
+                Some(('C', Point::new(x2, y2)))
+            }
+            Segment::QuadTo { x1, y1, .. } => Some(('Q', Point::new(x1, y1))),
+            Segment::SmoothQuadTo { .. } => Some(('Q', reflected)),
+            _ => None,
+        };
+
+        self.control = control;
+        self.cur = to;
+        Some(Step {
+            segment,
+            from,
+            to,
+            subpath_start: self.start,
+            reflected,
+        })
+    }
+}
+
+/// How coordinates are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordMode {
+    /// Every command as given, in absolute coordinates
+    #[default]
+    Absolute,
+    /// Every command except the first moveto in relative coordinates
+    Relative,
+    /// Whichever of the absolute, relative, and for lines `H`/`V` forms is
+    /// shortest, per command
+    Shortest,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Decimal places to round to, `None` to write numbers as they are
+    pub precision: Option<usize>,
+    /// Write `.5` instead of `0.5`
+    pub remove_leading_zero: bool,
+    pub mode: CoordMode,
+}
+
+fn format_segment(letter: char, args: &[Arg], opts: &WriteOptions) -> String {
+    let mut s = String::new();
+    s.push(letter);
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            s.push(' ');
+        }
+        match arg {
+            Arg::Num(n) => s.push_str(&format_number(*n, opts)),
+            Arg::Flag(f) => s.push(if *f { '1' } else { '0' }),
+        }
+    }
+    s
+}
+
+/// Formats a number the way path data is written with `opts`.
+pub fn format_number(n: f64, opts: &WriteOptions) -> String {
+    let n = match opts.precision {
+        Some(p) => {
+            let factor = 10u32.pow(p as u32) as f64;
+            (n * factor).round() / factor
+        }
+        None => n,
+    };
+    // Avoid writing `-0`
+    let s = if n == 0.0 {
+        "0".to_string()
+    } else {
+        n.to_string()
+    };
+    if !opts.remove_leading_zero {
+        s
+    } else if let Some(rest) = s.strip_prefix("0.") {
+        format!(".{}", rest)
+    } else if let Some(rest) = s.strip_prefix("-0.") {
+        format!("-.{}", rest)
+    } else {
+        s
+    }
+}
+
+fn read_segment(lexer: &mut PathLexer, cmd: char) -> Option<Segment> {
+    let mut n = || lexer.read_number();
+    Some(match cmd {
+        'M' => Segment::MoveTo { x: n()?, y: n()? },
+        'L' => Segment::LineTo { x: n()?, y: n()? },
+        'H' => Segment::HorizontalTo { x: n()? },
+        'V' => Segment::VerticalTo { y: n()? },
+        'C' => Segment::CubicTo {
+            x1: n()?,
+            y1: n()?,
+            x2: n()?,
+            y2: n()?,
+            x: n()?,
+            y: n()?,
+        },
+        'S' => Segment::SmoothCubicTo {
+            x2: n()?,
+            y2: n()?,
+            x: n()?,
+            y: n()?,
+        },
+        'Q' => Segment::QuadTo {
+            x1: n()?,
+            y1: n()?,
+            x: n()?,
+            y: n()?,
+        },
+        'T' => Segment::SmoothQuadTo { x: n()?, y: n()? },
+        'A' => {
+            let rx = lexer.read_number()?;
+            let ry = lexer.read_number()?;
+            let rotation = lexer.read_number()?;
+            let large_arc = lexer.read_flag()?;
+            let sweep = lexer.read_flag()?;
+            let x = lexer.read_number()?;
+            let y = lexer.read_number()?;
+            Segment::ArcTo {
+                rx,
+                ry,
+                rotation,
+                large_arc,
+                sweep,
+                x,
+                y,
+            }
+        }
+        'Z' => Segment::Close,
+        _ => return None,
+    })
+}
+
+struct PathLexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> PathLexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn skip_ws_comma(&mut self) {
+        while self.pos < self.input.len() {
+            let c = self.input.as_bytes()[self.pos] as char;
+            if c.is_whitespace() || c == ',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        if self.pos >= self.input.len() {
+            None
+        } else {
+            Some(self.input.as_bytes()[self.pos] as char)
+        }
+    }
+
+    fn read_char(&mut self) -> Option<char> {
+        if self.pos >= self.input.len() {
+            None
+        } else {
+            let c = self.input.as_bytes()[self.pos] as char;
+            self.pos += 1;
+            Some(c)
+        }
+    }
+
+    fn read_number(&mut self) -> Option<f64> {
+        self.skip_ws_comma();
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        let start = self.pos;
+        let mut seen_dot = false;
+        let mut seen_exp = false;
+        if self.peek_char() == Some('+') || self.peek_char() == Some('-') {
+            self.pos += 1;
+        }
+
+        while self.pos < self.input.len() {
+            let c = self.input.as_bytes()[self.pos] as char;
+            if c.is_ascii_digit() {
+                self.pos += 1;
+            } else if c == '.' && !seen_dot && !seen_exp {
+                seen_dot = true;
+                self.pos += 1;
+            } else if (c == 'e' || c == 'E') && !seen_exp {
+                seen_exp = true;
+                self.pos += 1;
+                if self.peek_char() == Some('+') || self.peek_char() == Some('-') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let sub = &self.input[start..self.pos];
+        sub.parse::<f64>().ok()
+    }
+
+    fn read_flag(&mut self) -> Option<bool> {
+        self.skip_ws_comma();
+        match self.read_char() {
+            Some('0') => Some(false),
+            Some('1') => Some(true),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relative() {
+        let path = PathData::parse("m10 10 5 5h-5zm1 1l1 0 0 1a1 1 0 011 1");
+        assert_eq!(
+            path.segments,
+            vec![
+                Segment::MoveTo { x: 10.0, y: 10.0 },
+                Segment::LineTo { x: 15.0, y: 15.0 },
+                Segment::HorizontalTo { x: 10.0 },
+                Segment::Close,
+                // After `z` the pen is back at the subpath start
+                Segment::MoveTo { x: 11.0, y: 11.0 },
+                Segment::LineTo { x: 12.0, y: 11.0 },
+                Segment::LineTo { x: 12.0, y: 12.0 },
+                Segment::ArcTo {
+                    rx: 1.0,
+                    ry: 1.0,
+                    rotation: 0.0,
+                    large_arc: false,
+                    sweep: true,
+                    x: 13.0,
+                    y: 13.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_steps() {
+        let path = PathData::parse("M0 0C0 1 1 2 2 2S4 3 4 4T6 6z");
+        let steps: Vec<Step> = path.iter().collect();
+        assert_eq!(steps[2].from, Point::new(2.0, 2.0));
+        assert_eq!(
+            steps[2].normalized(),
+            Segment::CubicTo {
+                x1: 3.0,
+                y1: 2.0,
+                x2: 4.0,
+                y2: 3.0,
+                x: 4.0,
+                y: 4.0
+            }
+        );
+        // T after S reflects nothing
+        assert_eq!(
+            steps[3].normalized(),
+            Segment::QuadTo {
+                x1: 4.0,
+                y1: 4.0,
+                x: 6.0,
+                y: 6.0
+            }
+        );
+        assert_eq!(steps[4].to, Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_transform() {
+        let path = PathData::parse("M0 0H10A10 5 0 0 1 20 10");
+        let scaled = path.transform(&Matrix::scale(2.0, -2.0));
+        assert_eq!(scaled.to_string(), "M0 0H20A20 10 0 0 0 40 -20");
+
+        // A quarter turn makes H a line and turns the ellipse upright
+        let rotated = path.transform(&crate::transform::parse("rotate(90)"));
+        let opts = WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        };
+        assert_eq!(rotated.write(&opts), "M0 0L0 10A10 5 90 0 1 -10 20");
+    }
+
+    #[test]
+    fn test_write_modes() {
+        let path = PathData::parse("M10 20L20 20L20 25.5z");
+        let write = |mode, precision| {
+            path.write(&WriteOptions {
+                precision,
+                remove_leading_zero: true,
+                mode,
+            })
+        };
+        assert_eq!(write(CoordMode::Absolute, None), "M10 20L20 20L20 25.5z");
+        assert_eq!(write(CoordMode::Relative, None), "M10 20l10 0l0 5.5z");
+        assert_eq!(write(CoordMode::Shortest, Some(0)), "M10 20H20v6z");
+    }
+}
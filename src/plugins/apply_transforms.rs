@@ -0,0 +1,344 @@
+use crate::path::{self, CoordMode, PathData, WriteOptions};
+use crate::plugins::Plugin;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::references::{self, ReferenceIndex};
+use crate::style::{self, ComputedStyle, Origin, StyledNode};
+use crate::transform::{self, is_approx, Matrix};
+use crate::tree::{Document, Element, Node};
+
+/// Applies `transform`s to path data, including the transforms of groups
+/// whose content can all take them.
+pub struct ApplyTransforms {
+    pub float_precision: usize,
+    /// Round each path to what its rendered size needs instead of
+    /// `float_precision`
+    pub error_budget: Option<ErrorBudget>,
+}
+
+impl Default for ApplyTransforms {
+    fn default() -> Self {
+        Self {
+            float_precision: 3,
+            error_budget: None,
+        }
+    }
+}
+
+impl Plugin for ApplyTransforms {
+    fn apply(&self, doc: &mut Document) {
+        let styles = style::resolve(doc);
+        let errors = self.error_budget.map(|b| b.resolve(doc, &styles));
+        let index = ReferenceIndex::build(doc);
+        let ctx = Context {
+            index: &index,
+            opts: self,
+        };
+        ctx.process(
+            &mut doc.root,
+            &styles,
+            errors.as_deref(),
+            &Matrix::identity(),
+            None,
+            false,
+        );
+    }
+}
+
+struct Context<'a> {
+    index: &'a ReferenceIndex,
+    opts: &'a ApplyTransforms,
+}
+
+impl Context<'_> {
+    // `inherited` is what groups above pushed down; it's only ever set when
+    // the whole subtree was checked to take it. `space_error` is the error
+    // budget in the units `inherited` maps into, where baked paths end up.
+    // `shared` is set under a referenced element: each <use> of it passes
+    // down its own styles, so nothing there is changed.
+    fn process(
+        &self,
+        nodes: &mut [Node],
+        styles: &[StyledNode],
+        errors: Option<&[ErrorNode]>,
+        inherited: &Matrix,
+        space_error: Option<f64>,
+        shared: bool,
+    ) {
+        for (i, (node, styled)) in nodes.iter_mut().zip(styles).enumerate() {
+            let Node::Element(elem) = node else {
+                continue;
+            };
+            let error = errors.and_then(|e| e.get(i));
+            let children = error.map(|e| e.children.as_slice());
+            let own_error = error.and_then(|e| e.error);
+            let shared = shared
+                || elem
+                    .attributes
+                    .get("id")
+                    .is_some_and(|id| self.index.is_referenced(id));
+            let m = match transform::element_transform(elem, &styled.style) {
+                Some(local) => inherited.multiply(&local),
+                None => *inherited,
+            };
+            match elem.name.as_str() {
+                "g" => {
+                    if elem.attributes.contains_key("transform")
+                        && !shared
+                        && self.can_take(elem, styled, &m)
+                    {
+                        elem.attributes.shift_remove("transform");
+                        elem.attributes.shift_remove("transform-origin");
+                        self.process(
+                            &mut elem.children,
+                            &styled.children,
+                            children,
+                            &m,
+                            space_error,
+                            shared,
+                        );
+                    } else {
+                        self.process(
+                            &mut elem.children,
+                            &styled.children,
+                            children,
+                            inherited,
+                            own_error,
+                            shared,
+                        );
+                    }
+                }
+                "path" => {
+                    if !shared && !m.is_identity() && self.can_bake_path(elem, &styled.style, &m) {
+                        self.bake_path(elem, &styled.style, &m, space_error);
+                    }
+                }
+                _ => self.process(
+                    &mut elem.children,
+                    &styled.children,
+                    children,
+                    &Matrix::identity(),
+                    own_error,
+                    shared,
+                ),
+            }
+        }
+    }
+
+    // Whether everything under the group can absorb `m`, which already
+    // includes the group's own transform
+    fn can_take(&self, group: &Element, styled: &StyledNode, m: &Matrix) -> bool {
+        // What's under a referenced ID is shared with every <use> of it
+        if references::defines_referenced_id(group, self.index) {
+            return false;
+        }
+        // These apply in the group's coordinate system
+        if ["clip-path", "mask", "filter"]
+            .iter()
+            .any(|p| styled.style.value(p) != Some("none"))
+        {
+            return false;
+        }
+        group
+            .children
+            .iter()
+            .zip(&styled.children)
+            .all(|(child, styled)| {
+                let Node::Element(child) = child else {
+                    return true;
+                };
+                let Some(local) = transform::element_transform(child, &styled.style) else {
+                    return false;
+                };
+                let m = m.multiply(&local);
+                match child.name.as_str() {
+                    "g" => self.can_take(child, styled, &m),
+                    "path" => self.can_bake_path(child, &styled.style, &m),
+                    "title" | "desc" => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn can_bake_path(&self, elem: &Element, style: &ComputedStyle, m: &Matrix) -> bool {
+        if m.inverse().is_none() || !elem.attributes.contains_key("d") {
+            return false;
+        }
+        // An animated transform replaces the one baked in, and animated
+        // path data would stay untransformed
+        if has_animation_children(elem) {
+            return false;
+        }
+        // <textPath>, <mpath> and friends use the untransformed data
+        if elem
+            .attributes
+            .get("id")
+            .is_some_and(|id| self.index.is_referenced(id))
+        {
+            return false;
+        }
+        let none = |p: &str| style.value(p) == Some("none");
+        if ![
+            "clip-path",
+            "mask",
+            "filter",
+            "marker-start",
+            "marker-mid",
+            "marker-end",
+        ]
+        .into_iter()
+        .all(none)
+        {
+            return false;
+        }
+        // Gradients and patterns are laid out in user space or the bbox
+        if ["fill", "stroke"]
+            .iter()
+            .any(|p| style.value(p).is_none_or(|v| v.contains("url(")))
+        {
+            return false;
+        }
+        none("stroke") || stroke_changes(style, m).is_some()
+    }
+
+    fn bake_path(&self, elem: &mut Element, style: &ComputedStyle, m: &Matrix, error: Option<f64>) {
+        if style.value("stroke") != Some("none") {
+            for (name, value) in stroke_changes(style, m).unwrap_or_default() {
+                elem.attributes.insert(name.to_string(), value);
+            }
+        }
+        let opts = WriteOptions {
+            precision: Some(error.map_or(self.opts.float_precision, precision::decimals)),
+            remove_leading_zero: false,
+            mode: CoordMode::Shortest,
+        };
+        if let Some(d) = elem.attributes.get_mut("d") {
+            *d = PathData::parse(d).transform(m).write(&opts);
+        }
+        elem.attributes.shift_remove("transform");
+        elem.attributes.shift_remove("transform-origin");
+    }
+}
+
+fn has_animation_children(elem: &Element) -> bool {
+    elem.children.iter().any(|child| {
+        matches!(child, Node::Element(child) if matches!(
+            child.name.as_str(),
+            "animate" | "animateMotion" | "animateTransform" | "set"
+        ))
+    })
+}
+
+// The stroke attributes to set so the stroke looks the same after `m` is
+// applied to the geometry. `None` when that can't be done: a non-uniform
+// scale would need a different width in each direction.
+fn stroke_changes(style: &ComputedStyle, m: &Matrix) -> Option<Vec<(&'static str, String)>> {
+    let uniform = is_approx(m.a * m.a + m.b * m.b, m.c * m.c + m.d * m.d)
+        && is_approx(m.a * m.c + m.b * m.d, 0.0);
+    if !uniform || style.is_dynamic("vector-effect") {
+        return None;
+    }
+    if !matches!(style.value("vector-effect"), None | Some("none")) {
+        return None;
+    }
+    let scale = (m.a * m.d - m.b * m.c).abs().sqrt();
+
+    let mut changes = Vec::new();
+    for name in ["stroke-width", "stroke-dasharray", "stroke-dashoffset"] {
+        // Attributes can't override CSS
+        if matches!(
+            style.origin(name),
+            Origin::Stylesheet | Origin::StyleAttribute
+        ) {
+            return None;
+        }
+        let value = style.value(name)?;
+        if value == "none" || is_approx(scale, 1.0) {
+            continue;
+        }
+        let nums = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.trim_end_matches("px").parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if nums.iter().all(|n| *n == 0.0) {
+            continue;
+        }
+        let opts = WriteOptions {
+            precision: Some(6),
+            ..Default::default()
+        };
+        let scaled: Vec<String> = nums
+            .iter()
+            .map(|n| path::format_number(n * scale, &opts))
+            .collect();
+        changes.push((name, scaled.join(" ")));
+    }
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    fn run(input: &str) -> String {
+        let mut doc = parser::parse(input).unwrap();
+        ApplyTransforms::default().apply(&mut doc);
+        printer::print(&doc)
+    }
+
+    #[test]
+    fn test_apply_to_path() {
+        let input = "<svg><path d=\"M0 0h10v10\" transform=\"translate(5 5) scale(2)\"/></svg>";
+        let expected = "<svg><path d=\"M5 5H25V25\"/></svg>";
+        assert_eq!(run(input), expected);
+    }
+
+    #[test]
+    fn test_apply_group_transform_and_stroke() {
+        let input = "<svg><g transform=\"scale(2)\" stroke=\"red\"><path d=\"M0 0A5 5 0 0 1 10 0\"/><path d=\"M1 1h1\" stroke-width=\"3\"/></g></svg>";
+        let expected = "<svg><g stroke=\"red\"><path d=\"M0 0A10 10 0 0 1 20 0\" stroke-width=\"2\"/><path d=\"M2 2H4\" stroke-width=\"6\"/></g></svg>";
+        assert_eq!(run(input), expected);
+    }
+
+    #[test]
+    fn test_keep_when_meaning_changes() {
+        // Non-uniform scale on a stroke, a gradient fill, and a group with
+        // a clip path
+        let input = "<svg><path d=\"M0 0h10\" stroke=\"red\" transform=\"scale(2 1)\"/><path d=\"M0 0h10\" fill=\"url(#g)\" transform=\"scale(2)\"/><g transform=\"scale(2)\" clip-path=\"url(#c)\"><path d=\"M0 0h10\"/></g></svg>";
+        assert_eq!(run(input), input);
+    }
+
+    #[test]
+    fn test_keep_animated_paths() {
+        let input = "<svg><path transform=\"translate(10 0)\" d=\"M0 0h10v10z\"><animateTransform attributeName=\"transform\" type=\"rotate\" to=\"90\"/></path><g transform=\"scale(2)\"><path d=\"M0 0h10\"><set attributeName=\"d\" to=\"M0 0h5\"/></path></g></svg>";
+        assert_eq!(run(input), input);
+    }
+
+    #[test]
+    fn test_keep_used_content() {
+        // Each <use> passes down its own stroke width
+        let input = "<svg><defs><g id=\"g\" transform=\"scale(2)\"><path d=\"M0 0h10\" stroke=\"red\"/></g><g id=\"h\"><g transform=\"scale(2)\"><path d=\"M0 0h10\" stroke=\"red\"/></g></g></defs><use href=\"#g\" stroke-width=\"5\"/><use href=\"#g\" stroke-width=\"3\"/><use href=\"#h\" stroke-width=\"5\"/></svg>";
+        assert_eq!(run(input), input);
+    }
+
+    #[test]
+    fn test_error_budget() {
+        // 0.01px across 1000px of a one unit viewBox needs five decimals
+        let input = "<svg viewBox=\"0 0 1 1\" width=\"1000\"><path d=\"M0 0h.5v.5\" transform=\"translate(.123456 .123456)\"/></svg>";
+        let mut doc = parser::parse(input).unwrap();
+        ApplyTransforms {
+            error_budget: Some(ErrorBudget {
+                error: 0.01,
+                size: None,
+            }),
+            ..ApplyTransforms::default()
+        }
+        .apply(&mut doc);
+        assert_eq!(
+            printer::print(&doc),
+            "<svg viewBox=\"0 0 1 1\" width=\"1000\"><path d=\"M0.12346 0.12346h0.5v0.5\"/></svg>"
+        );
+    }
+}
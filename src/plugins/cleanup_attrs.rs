@@ -21,14 +21,15 @@ fn cleanup_attrs_in_nodes(nodes: &mut Vec<Node>) {
 }
 
 fn cleanup_element_attrs(elem: &mut Element) {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\s+").unwrap());
+
     for (_, value) in elem.attributes.iter_mut() {
         // 1. Replace newlines/tabs with spaces
         let mut new_value = value.replace(['\n', '\r', '\t'], " ");
 
         // 2. Collapse multiple spaces (using simple heuristic or regex)
         // Using a regex for simplicity: \s+ -> " "
-        static RE: OnceLock<Regex> = OnceLock::new();
-        let re = RE.get_or_init(|| Regex::new(r"\s+").unwrap());
         new_value = re.replace_all(&new_value, " ").to_string();
 
         // 3. Trim
@@ -1,47 +1,133 @@
-use crate::plugins::collections::find_used_ids;
 use crate::plugins::Plugin;
-use crate::tree::{Document, Element, Node};
-use std::collections::HashSet;
+use crate::references::{self, ReferenceIndex};
+use crate::tree::{Document, Node};
+use std::collections::{HashMap, HashSet};
 
-pub struct CleanupIds;
+pub struct CleanupIds {
+    // Rename kept IDs to the shortest available names
+    pub minify: bool,
+    // IDs that are never removed or renamed
+    pub preserve: Vec<String>,
+    pub preserve_prefixes: Vec<String>,
+}
+
+impl Default for CleanupIds {
+    fn default() -> Self {
+        Self {
+            minify: true,
+            preserve: Vec::new(),
+            preserve_prefixes: Vec::new(),
+        }
+    }
+}
+
+impl CleanupIds {
+    fn is_preserved(&self, id: &str) -> bool {
+        self.preserve.iter().any(|p| p == id)
+            || self
+                .preserve_prefixes
+                .iter()
+                .any(|p| id.starts_with(p.as_str()))
+    }
+
+    // Most referenced IDs get the shortest names; ties go in document order.
+    fn minified_names(&self, index: &ReferenceIndex) -> HashMap<String, String> {
+        let mut ids: Vec<&str> = index
+            .ids()
+            .filter(|id| index.is_referenced(id) && !self.is_preserved(id))
+            .collect();
+        ids.sort_by(|a, b| {
+            let count = |id: &str| index.references(id).len();
+            count(b)
+                .cmp(&count(a))
+                .then_with(|| index.definition(a).cmp(&index.definition(b)))
+        });
+
+        // Names of IDs that stay as they are
+        let taken: HashSet<&str> = index.ids().filter(|id| self.is_preserved(id)).collect();
+        let mut names = (0..)
+            .map(generate_name)
+            .filter(|name| !taken.contains(name.as_str()) && !self.is_preserved(name));
+
+        ids.into_iter()
+            .map(|id| (id.to_string(), names.next().unwrap_or_default()))
+            .filter(|(id, name)| id != name)
+            .collect()
+    }
+}
 
 impl Plugin for CleanupIds {
     fn apply(&self, doc: &mut Document) {
-        let mut used_ids = HashSet::new();
-        find_used_ids(
-            &Node::Element(Element {
-                name: "root".to_string(),
-                attributes: Default::default(),
-                children: doc.root.clone(), // Clone? Expensive.
-                                            // find_used_ids takes &Node.
-                                            // We can iterate doc.root.
-            }),
-            &mut used_ids,
-        );
-
-        // Actually, creating a fake root is ugly.
-        // Let's just iterate doc.root.
-        used_ids.clear();
-        for node in &doc.root {
-            find_used_ids(node, &mut used_ids);
-        }
+        let index = ReferenceIndex::build(doc);
+
+        // Scripts and stylesheets may refer to IDs in ways we can't rewrite
+        let renames = if self.minify && !has_script_or_style(&doc.root) {
+            self.minified_names(&index)
+        } else {
+            HashMap::new()
+        };
+
+        cleanup_ids_in_nodes(&mut doc.root, &index, self, &renames);
+    }
+}
+
+const NAME_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
-        cleanup_ids_in_nodes(&mut doc.root, &used_ids);
+// a, b, ..., Z, aa, ab, ...
+fn generate_name(mut n: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(NAME_CHARS[n % NAME_CHARS.len()]);
+        n /= NAME_CHARS.len();
+        if n == 0 {
+            break;
+        }
+        n -= 1;
     }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+fn has_script_or_style(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => {
+            elem.name == "script" || elem.name == "style" || has_script_or_style(&elem.children)
+        }
+        _ => false,
+    })
 }
 
-fn cleanup_ids_in_nodes(nodes: &mut Vec<Node>, used_ids: &HashSet<String>) {
+fn cleanup_ids_in_nodes(
+    nodes: &mut [Node],
+    index: &ReferenceIndex,
+    opts: &CleanupIds,
+    renames: &HashMap<String, String>,
+) {
     for node in nodes {
         if let Node::Element(elem) = node {
             // Check ID
-            if let Some(id) = elem.attributes.get("id").cloned() {
-                // Clone identifier to avoid borrow issues
-                if !used_ids.contains(&id) {
+            if let Some(id) = elem.attributes.get("id") {
+                if !index.is_referenced(id) && !opts.is_preserved(id) {
                     elem.attributes.shift_remove("id");
                 }
             }
 
-            cleanup_ids_in_nodes(&mut elem.children, used_ids);
+            if !renames.is_empty() {
+                for (name, value) in elem.attributes.iter_mut() {
+                    let renamed = if name == "id" {
+                        renames.get(value.as_str()).cloned()
+                    } else {
+                        references::rewrite_attribute(name, value, &mut |id| {
+                            renames.get(id).cloned()
+                        })
+                    };
+                    if let Some(renamed) = renamed {
+                        *value = renamed;
+                    }
+                }
+            }
+
+            cleanup_ids_in_nodes(&mut elem.children, index, opts, renames);
         }
     }
 }
@@ -58,7 +144,11 @@ mod tests {
         let expected = "<svg><rect/><rect id=\"used\"/><use href=\"#used\"/></svg>";
 
         let mut doc = parser::parse(input).unwrap();
-        CleanupIds.apply(&mut doc);
+        CleanupIds {
+            minify: false,
+            ..Default::default()
+        }
+        .apply(&mut doc);
         let output = printer::print(&doc);
 
         assert_eq!(output, expected);
@@ -70,9 +160,57 @@ mod tests {
         let expected = "<svg><linearGradient id=\"grad\"/><rect fill=\"url(#grad)\"/></svg>";
 
         let mut doc = parser::parse(input).unwrap();
-        CleanupIds.apply(&mut doc);
+        CleanupIds {
+            minify: false,
+            ..Default::default()
+        }
+        .apply(&mut doc);
         let output = printer::print(&doc);
 
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn test_cleanup_ids_smil_aria_style_refs() {
+        let input = "<svg aria-labelledby=\"t\"><style>#s{fill:red}</style><title id=\"t\">x</title><rect id=\"s\"/><rect id=\"btn\"/><set begin=\"btn.click\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        CleanupIds {
+            minify: false,
+            ..Default::default()
+        }
+        .apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+
+    #[test]
+    fn test_minify_ids() {
+        let input = "<svg><linearGradient id=\"linearGradient-3_1_\"/><path id=\"keep-me\"/><clipPath id=\"clip\"/><rect fill=\"url(#linearGradient-3_1_)\" clip-path=\"url(#clip)\"/><use href=\"#clip\"/><use href=\"#keep-me\"/><set begin=\"clip.end\"/></svg>";
+        let expected = "<svg><linearGradient id=\"b\"/><path id=\"keep-me\"/><clipPath id=\"a\"/><rect fill=\"url(#b)\" clip-path=\"url(#a)\"/><use href=\"#a\"/><use href=\"#keep-me\"/><set begin=\"a.end\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        CleanupIds {
+            preserve_prefixes: vec!["keep".to_string()],
+            ..Default::default()
+        }
+        .apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_no_minify_with_style() {
+        let input = "<svg><style>#g{fill:red}</style><linearGradient id=\"g\"/><rect fill=\"url(#g)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        CleanupIds::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+
+    #[test]
+    fn test_generate_name() {
+        assert_eq!(generate_name(0), "a");
+        assert_eq!(generate_name(51), "Z");
+        assert_eq!(generate_name(52), "aa");
+        assert_eq!(generate_name(53), "ab");
+    }
 }
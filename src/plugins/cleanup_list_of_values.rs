@@ -1,4 +1,6 @@
 use crate::plugins::Plugin;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::style;
 use crate::tree::{Document, Node};
 
 pub struct CleanupListOfValues {
@@ -6,6 +8,8 @@ pub struct CleanupListOfValues {
     pub leading_zero: bool,
     pub default_px: bool,
     pub convert_to_px: bool,
+    /// Round coordinates to what their rendered size needs instead
+    pub error_budget: Option<ErrorBudget>,
     // Target lists: viewBox, points, dx, dy, x, y, rotate (list), enable-background
 }
 
@@ -16,28 +20,43 @@ impl Default for CleanupListOfValues {
             leading_zero: true,
             default_px: true,
             convert_to_px: true,
+            error_budget: None,
         }
     }
 }
 
 impl Plugin for CleanupListOfValues {
     fn apply(&self, doc: &mut Document) {
-        process_lists(&mut doc.root, self);
+        let errors = self
+            .error_budget
+            .map(|b| b.resolve(doc, &style::resolve(doc)));
+        process_lists(&mut doc.root, errors.as_deref(), self);
     }
 }
 
-fn process_lists(nodes: &mut Vec<Node>, opts: &CleanupListOfValues) {
+fn process_lists(nodes: &mut [Node], errors: Option<&[ErrorNode]>, opts: &CleanupListOfValues) {
     let list_attrs = ["viewBox", "points", "dx", "dy", "x", "y", "rotate"];
 
-    for node in nodes {
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let error = errors.and_then(|e| e.get(i));
         if let Node::Element(elem) = node {
+            // `rotate` holds angles
+            let length_opts = error.and_then(|e| e.error).map(|e| CleanupListOfValues {
+                float_precision: precision::decimals(e),
+                ..*opts
+            });
             for attr in list_attrs {
+                let opts = match &length_opts {
+                    Some(length_opts) if attr != "rotate" => length_opts,
+                    _ => opts,
+                };
                 if let Some(val) = elem.attributes.get_mut(attr) {
                     let clean = cleanup_list(val, opts);
                     *val = clean;
                 }
             }
-            process_lists(&mut elem.children, opts);
+            let children = error.map(|e| e.children.as_slice());
+            process_lists(&mut elem.children, children, opts);
         }
     }
 }
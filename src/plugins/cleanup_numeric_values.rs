@@ -1,10 +1,14 @@
 use crate::plugins::Plugin;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::style;
 use crate::tree::{Document, Element, Node};
 
 pub struct CleanupNumericValues {
     pub float_precision: usize,
     pub remove_px: bool,
     pub leading_zero: bool,
+    /// Round lengths to what their rendered size needs instead
+    pub error_budget: Option<ErrorBudget>,
 }
 
 impl Default for CleanupNumericValues {
@@ -13,26 +17,36 @@ impl Default for CleanupNumericValues {
             float_precision: 3,
             remove_px: true,
             leading_zero: true,
+            error_budget: None,
         }
     }
 }
 
 impl Plugin for CleanupNumericValues {
     fn apply(&self, doc: &mut Document) {
-        cleanup_numeric_in_nodes(&mut doc.root, self);
+        let errors = self
+            .error_budget
+            .map(|b| b.resolve(doc, &style::resolve(doc)));
+        cleanup_numeric_in_nodes(&mut doc.root, errors.as_deref(), self);
     }
 }
 
-fn cleanup_numeric_in_nodes(nodes: &mut Vec<Node>, opts: &CleanupNumericValues) {
-    for node in nodes {
+fn cleanup_numeric_in_nodes(
+    nodes: &mut [Node],
+    errors: Option<&[ErrorNode]>,
+    opts: &CleanupNumericValues,
+) {
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let error = errors.and_then(|e| e.get(i));
         if let Node::Element(elem) = node {
-            cleanup_element_numeric(elem, opts);
-            cleanup_numeric_in_nodes(&mut elem.children, opts);
+            cleanup_element_numeric(elem, error.and_then(|e| e.error), opts);
+            let children = error.map(|e| e.children.as_slice());
+            cleanup_numeric_in_nodes(&mut elem.children, children, opts);
         }
     }
 }
 
-fn cleanup_element_numeric(elem: &mut Element, opts: &CleanupNumericValues) {
+fn cleanup_element_numeric(elem: &mut Element, error: Option<f64>, opts: &CleanupNumericValues) {
     // List of attributes to check
     let numeric_attrs = [
         "x",
@@ -51,8 +65,17 @@ fn cleanup_element_numeric(elem: &mut Element, opts: &CleanupNumericValues) {
         "font-size",
         "offset",
     ];
+    // Opacities and offsets aren't lengths
+    let length_opts = error.map(|e| CleanupNumericValues {
+        float_precision: precision::decimals(e),
+        ..*opts
+    });
 
     for attr in numeric_attrs {
+        let opts = match &length_opts {
+            Some(length_opts) if !attr.ends_with("opacity") && attr != "offset" => length_opts,
+            _ => opts,
+        };
         if let Some(val) = elem.attributes.get_mut(attr) {
             let new_val = cleanup_number(val, opts);
             *val = new_val;
@@ -60,7 +83,7 @@ fn cleanup_element_numeric(elem: &mut Element, opts: &CleanupNumericValues) {
     }
 }
 
-fn cleanup_number(val: &str, opts: &CleanupNumericValues) -> String {
+pub(crate) fn cleanup_number(val: &str, opts: &CleanupNumericValues) -> String {
     // 1. Remove px
     let mut clean_val = val.trim();
     if opts.remove_px && clean_val.ends_with("px") {
@@ -1,38 +1,112 @@
-use crate::tree::Node;
-use regex::Regex;
-use std::collections::HashSet;
-use std::sync::OnceLock;
+// SVG 1.1 presentation attributes: CSS properties that may also be written as
+// attributes. The `font` and `marker` shorthands are left out because
+// browsers don't honor them as attributes.
+pub const PRESENTATION_ATTRS: [&str; 59] = [
+    "alignment-baseline",
+    "baseline-shift",
+    "clip",
+    "clip-path",
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-profile",
+    "color-rendering",
+    "cursor",
+    "direction",
+    "display",
+    "dominant-baseline",
+    "enable-background",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "filter",
+    "flood-color",
+    "flood-opacity",
+    "font-family",
+    "font-size",
+    "font-size-adjust",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "glyph-orientation-horizontal",
+    "glyph-orientation-vertical",
+    "image-rendering",
+    "kerning",
+    "letter-spacing",
+    "lighting-color",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "mask",
+    "opacity",
+    "overflow",
+    "pointer-events",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "text-decoration",
+    "text-rendering",
+    "unicode-bidi",
+    "visibility",
+    "word-spacing",
+    "writing-mode",
+];
 
-pub fn find_used_ids(node: &Node, used_ids: &mut HashSet<String>) {
-    match node {
-        Node::Element(elem) => {
-            // Check all attributes for references
-            for (_, value) in &elem.attributes {
-                extract_ids_from_value(value, used_ids);
-            }
-
-            // Recurse
-            for child in &elem.children {
-                find_used_ids(child, used_ids);
-            }
-        }
-        _ => {}
-    }
-}
-
-fn extract_ids_from_value(value: &str, used_ids: &mut HashSet<String>) {
-    // 1. url(#id)
-    static URL_RE: OnceLock<Regex> = OnceLock::new();
-    let url_re = URL_RE.get_or_init(|| Regex::new(r"url\s*\(\s*#([^\s\)]+)\s*\)").unwrap());
-
-    for cap in url_re.captures_iter(value) {
-        if let Some(id) = cap.get(1) {
-            used_ids.insert(id.as_str().to_string());
-        }
-    }
-
-    // 2. href="#id" (xlink:href or href)
-    if value.starts_with('#') && value.len() > 1 {
-        used_ids.insert(value[1..].to_string());
-    }
-}
+// Properties whose value is inherited by descendants.
+pub const INHERITABLE_ATTRS: [&str; 44] = [
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-profile",
+    "color-rendering",
+    "cursor",
+    "direction",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "font",
+    "font-family",
+    "font-size",
+    "font-size-adjust",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "glyph-orientation-horizontal",
+    "glyph-orientation-vertical",
+    "image-rendering",
+    "kerning",
+    "letter-spacing",
+    "marker",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "pointer-events",
+    "shape-rendering",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "text-rendering",
+    "visibility",
+    "white-space",
+    "word-spacing",
+    "writing-mode",
+];
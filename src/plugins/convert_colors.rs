@@ -41,7 +41,7 @@ fn convert_element_colors(elem: &mut Element) {
     // Handle style attribute? (Complexity: parsing CSS. Skipping for now as SVGO usually handles this in a separate pass or complex parser)
 }
 
-fn convert_color(val: &str) -> String {
+pub(crate) fn convert_color(val: &str) -> String {
     let lower = val.to_lowercase();
 
     // 1. RGB conversion: rgb(r, g, b) -> #rrggbb
@@ -1,9 +1,22 @@
+use crate::path::{CoordMode, PathData, Point, Segment, Step, WriteOptions};
 use crate::plugins::Plugin;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::style::{self, ComputedStyle, StyledNode};
 use crate::tree::{Document, Node};
+use std::f64::consts::PI;
 
 pub struct ConvertPathData {
     pub float_precision: usize,
     pub leading_zero: bool,
+    /// Replace runs of curves that follow a circle with arcs
+    pub make_arcs: bool,
+    /// Replace curves that are nearly straight with lines
+    pub straight_curves: bool,
+    /// How far geometry may move, `None` for one unit of the last digit kept
+    pub tolerance: Option<f64>,
+    /// Derive precision and tolerance for each path from its rendered size
+    /// instead
+    pub error_budget: Option<ErrorBudget>,
     // Add more opts as needed
 }
 
@@ -12,730 +25,484 @@ impl Default for ConvertPathData {
         Self {
             float_precision: 3,
             leading_zero: true,
+            make_arcs: true,
+            straight_curves: true,
+            tolerance: None,
+            error_budget: None,
         }
     }
 }
 
+impl ConvertPathData {
+    fn tolerance(&self) -> f64 {
+        self.tolerance
+            .unwrap_or_else(|| 1.0 / 10u32.pow(self.float_precision as u32) as f64)
+    }
+}
+
+// Curves within this many tolerances, or this percentage of the radius
+// (whichever is less) of a circle become arcs, as in SVGO
+const ARC_THRESHOLD: f64 = 2.5;
+const ARC_TOLERANCE: f64 = 0.5;
+
 impl Plugin for ConvertPathData {
     fn apply(&self, doc: &mut Document) {
-        process_paths(&mut doc.root, self);
+        let styles = style::resolve(doc);
+        let errors = self.error_budget.map(|b| b.resolve(doc, &styles));
+        process_paths(&mut doc.root, &styles, errors.as_deref(), self);
     }
 }
 
-fn process_paths(nodes: &mut Vec<Node>, opts: &ConvertPathData) {
-    for node in nodes {
+fn process_paths(
+    nodes: &mut [Node],
+    styles: &[StyledNode],
+    errors: Option<&[ErrorNode]>,
+    opts: &ConvertPathData,
+) {
+    for (i, (node, styled)) in nodes.iter_mut().zip(styles).enumerate() {
+        let error = errors.and_then(|e| e.get(i));
         if let Node::Element(elem) = node {
             if elem.name == "path" {
                 if let Some(d) = elem.attributes.get_mut("d") {
-                    let new_d = optimize_path_data(d, opts);
+                    // Rounding takes half the budget, the rest of the
+                    // pipeline the other half
+                    let budgeted = error.and_then(|e| e.error).map(|e| ConvertPathData {
+                        float_precision: precision::decimals(e),
+                        tolerance: Some(e / 2.0),
+                        ..*opts
+                    });
+                    let ctx = PathContext::new(&styled.style);
+                    let new_d = optimize_path_data(d, budgeted.as_ref().unwrap_or(opts), &ctx);
                     *d = new_d;
                 }
             }
-            process_paths(&mut elem.children, opts);
+            let children = error.map(|e| e.children.as_slice());
+            process_paths(&mut elem.children, &styled.children, children, opts);
         }
     }
 }
 
-fn optimize_path_data(d: &str, opts: &ConvertPathData) -> String {
-    let commands = parse_path_data(d);
-    stringify_optimized(&commands, opts)
+// What else about the element depends on the exact commands
+#[derive(Default)]
+struct PathContext {
+    // Every vertex may carry a marker
+    has_markers: bool,
+    // Lone moves and zero-length closes can draw dots
+    has_caps: bool,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum Command {
-    // We store minimal data, usually absolute for analysis?
-    // Actually, to choose best representation, let's store Absolute coordinates internally
-    // and decide Rel/Abs at stringify time.
-    Move(f64, f64),
-    Line(f64, f64),
-    Horiz(f64),
-    Vert(f64),
-    Curve(f64, f64, f64, f64, f64, f64), // x1 y1 x2 y2 x y
-    SmoothCurve(f64, f64, f64, f64),     // x2 y2 x y
-    Quad(f64, f64, f64, f64),            // x1 y1 x y
-    SmoothQuad(f64, f64),                // x y
-    Arc(f64, f64, f64, bool, bool, f64, f64),
-    Close,
+impl PathContext {
+    fn new(style: &ComputedStyle) -> Self {
+        Self {
+            has_markers: ["marker-start", "marker-mid", "marker-end"]
+                .iter()
+                .any(|p| style.value(p) != Some("none")),
+            has_caps: style.value("stroke") != Some("none")
+                && style.value("stroke-linecap") != Some("butt"),
+        }
+    }
 }
 
-// Struct removed. Using PathLexer below.
-
-// ... Lexer implementation ...
-// To be robust, let's maintain the decent lexer from previous step but adapt output to Normalized Commands (Absolute).
-// Converting everything to absolute simplifies optimization logic (L vs H vs V).
-
-fn parse_path_data(d: &str) -> Vec<Command> {
-    // Current pen position
-    let mut cur_x = 0.0;
-    let mut cur_y = 0.0;
-
-    // Previous control point for S/T (absolute)
-    // If previous was C/S, ctrl point is second control point. Else current point (reflection).
-    // Actually we don't need to track prev control point during parsing if we just convert rel->abs.
-    // Rel just adds to cur_x/cur_y.
-
-    let mut commands = Vec::new();
-
-    // Reuse the lexer logic from previous step efficiently
-    let mut lexer = PathLexer::new(d);
-    let mut current_cmd_char = None;
-
-    loop {
-        lexer.skip_ws_comma();
-        let c_opt = lexer.peek_char();
-        if c_opt.is_none() {
-            break;
+fn optimize_path_data(d: &str, opts: &ConvertPathData, ctx: &PathContext) -> String {
+    let tolerance = opts.tolerance();
+    let mut segments = normalize(&PathData::parse(d));
+    if !ctx.has_markers {
+        segments = remove_zero_length(segments, tolerance);
+    }
+    if opts.straight_curves {
+        segments = straighten_curves(segments, tolerance);
+    }
+    if !ctx.has_markers {
+        segments = merge_collinear(segments, tolerance);
+        if !ctx.has_caps {
+            segments = remove_useless_moves(segments, tolerance);
+            segments = remove_closing_lines(segments, tolerance);
         }
+    }
+    // Merging curves into one arc drops the vertices mid markers sit on
+    if opts.make_arcs && !ctx.has_markers {
+        segments = make_arcs(segments, tolerance);
+    }
+    let segments = use_shorthands(segments, tolerance);
 
-        let c = c_opt.unwrap();
-        let cmd_char = if c.is_ascii_alphabetic() {
-            lexer.read_char().unwrap()
-        } else {
-            // Implicit
-            if let Some(cmd) = current_cmd_char {
-                if cmd == 'M' {
-                    'L'
-                } else if cmd == 'm' {
-                    'l'
-                } else {
-                    cmd
-                }
-            } else {
-                break;
-            }
-        };
-        current_cmd_char = Some(cmd_char);
-
-        match cmd_char {
-            'M' => {
-                while let (Some(x), Some(y)) = (lexer.read_number(), lexer.read_number()) {
-                    commands.push(Command::Move(x, y));
-                    cur_x = x;
-                    cur_y = y;
-                    current_cmd_char = Some('L'); // Subsequent are Line
-                }
-            }
-            'm' => {
-                while let (Some(dx), Some(dy)) = (lexer.read_number(), lexer.read_number()) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::Move(nx, ny));
-                    cur_x = nx;
-                    cur_y = ny;
-                    current_cmd_char = Some('l');
-                }
-            }
-            'L' => {
-                while let (Some(x), Some(y)) = (lexer.read_number(), lexer.read_number()) {
-                    commands.push(Command::Line(x, y));
-                    cur_x = x;
-                    cur_y = y;
-                }
-            }
-            'l' => {
-                while let (Some(dx), Some(dy)) = (lexer.read_number(), lexer.read_number()) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::Line(nx, ny));
-                    cur_x = nx;
-                    cur_y = ny;
-                }
-            }
-            'H' => {
-                while let Some(x) = lexer.read_number() {
-                    commands.push(Command::Horiz(x));
-                    cur_x = x;
-                }
-            }
-            'h' => {
-                while let Some(dx) = lexer.read_number() {
-                    let nx = cur_x + dx;
-                    commands.push(Command::Horiz(nx));
-                    cur_x = nx;
-                }
-            }
-            'V' => {
-                while let Some(y) = lexer.read_number() {
-                    commands.push(Command::Vert(y));
-                    cur_y = y;
-                }
-            }
-            'v' => {
-                while let Some(dy) = lexer.read_number() {
-                    let ny = cur_y + dy;
-                    commands.push(Command::Vert(ny));
-                    cur_y = ny;
-                }
-            }
-            'Z' | 'z' => {
-                commands.push(Command::Close);
-                // No args.
-                // Need start point of subpath? For Z calculation?
-                // Z closes to most recent Move.
-                // But for next command, pos is subpath start.
-                // Tracking subpath start is needed for correct pen pos after Z.
-            }
-            'C' => {
-                while let (Some(x1), Some(y1), Some(x2), Some(y2), Some(x), Some(y)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    commands.push(Command::Curve(x1, y1, x2, y2, x, y));
-                    cur_x = x;
-                    cur_y = y;
-                }
-            }
-            'c' => {
-                while let (Some(dx1), Some(dy1), Some(dx2), Some(dy2), Some(dx), Some(dy)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::Curve(
-                        cur_x + dx1,
-                        cur_y + dy1,
-                        cur_x + dx2,
-                        cur_y + dy2,
-                        nx,
-                        ny,
-                    ));
-                    cur_x = nx;
-                    cur_y = ny;
-                }
-            }
-            'S' => {
-                while let (Some(x2), Some(y2), Some(x), Some(y)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    commands.push(Command::SmoothCurve(x2, y2, x, y));
-                    cur_x = x;
-                    cur_y = y;
-                }
-            }
-            's' => {
-                while let (Some(dx2), Some(dy2), Some(dx), Some(dy)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::SmoothCurve(cur_x + dx2, cur_y + dy2, nx, ny));
-                    cur_x = nx;
-                    cur_y = ny;
-                }
-            }
-            'Q' => {
-                while let (Some(x1), Some(y1), Some(x), Some(y)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    commands.push(Command::Quad(x1, y1, x, y));
-                    cur_x = x;
-                    cur_y = y;
-                }
-            }
-            'q' => {
-                while let (Some(dx1), Some(dy1), Some(dx), Some(dy)) = (
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                    lexer.read_number(),
-                ) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::Quad(cur_x + dx1, cur_y + dy1, nx, ny));
-                    cur_x = nx;
-                    cur_y = ny;
-                }
-            }
-            'T' => {
-                while let (Some(x), Some(y)) = (lexer.read_number(), lexer.read_number()) {
-                    commands.push(Command::SmoothQuad(x, y));
-                    cur_x = x;
-                    cur_y = y;
-                }
-            }
-            't' => {
-                while let (Some(dx), Some(dy)) = (lexer.read_number(), lexer.read_number()) {
-                    let nx = cur_x + dx;
-                    let ny = cur_y + dy;
-                    commands.push(Command::SmoothQuad(nx, ny));
-                    cur_x = nx;
-                    cur_y = ny;
-                }
-            }
-            'A' => loop {
-                let rx = lexer.read_number();
-                if rx.is_none() {
-                    break;
-                }
-                let ry = lexer.read_number();
-                if ry.is_none() {
-                    break;
-                }
-                let rot = lexer.read_number();
-                if rot.is_none() {
-                    break;
-                }
-                let la = lexer.read_flag();
-                if la.is_none() {
-                    break;
-                }
-                let sf = lexer.read_flag();
-                if sf.is_none() {
-                    break;
-                }
-                let x = lexer.read_number();
-                if x.is_none() {
-                    break;
-                }
-                let y = lexer.read_number();
-                if y.is_none() {
-                    break;
-                }
+    PathData { segments }.write(&WriteOptions {
+        precision: Some(opts.float_precision),
+        remove_leading_zero: opts.leading_zero,
+        mode: CoordMode::Shortest,
+    })
+}
 
-                let rx = rx.unwrap();
-                let ry = ry.unwrap();
-                let rot = rot.unwrap();
-                let la = la.unwrap();
-                let sf = sf.unwrap();
-                let x = x.unwrap();
-                let y = y.unwrap();
-
-                commands.push(Command::Arc(rx, ry, rot, la, sf, x, y));
-                cur_x = x;
-                cur_y = y;
-            },
-            'a' => loop {
-                let rx = lexer.read_number();
-                if rx.is_none() {
-                    break;
-                }
-                let ry = lexer.read_number();
-                if ry.is_none() {
-                    break;
-                }
-                let rot = lexer.read_number();
-                if rot.is_none() {
-                    break;
-                }
-                let la = lexer.read_flag();
-                if la.is_none() {
-                    break;
-                }
-                let sf = lexer.read_flag();
-                if sf.is_none() {
-                    break;
-                }
-                let dx = lexer.read_number();
-                if dx.is_none() {
-                    break;
-                }
-                let dy = lexer.read_number();
-                if dy.is_none() {
-                    break;
-                }
+fn steps(segments: Vec<Segment>) -> Vec<Step> {
+    PathData { segments }.iter().collect()
+}
 
-                let rx = rx.unwrap();
-                let ry = ry.unwrap();
-                let rot = rot.unwrap();
-                let la = la.unwrap();
-                let sf = sf.unwrap();
-                let dx = dx.unwrap();
-                let dy = dy.unwrap();
-
-                let nx = cur_x + dx;
-                let ny = cur_y + dy;
-                commands.push(Command::Arc(rx, ry, rot, la, sf, nx, ny));
-                cur_x = nx;
-                cur_y = ny;
-            },
-            _ => {
-                break;
-            }
-        }
-    }
+fn near(a: Point, b: Point, tolerance: f64) -> bool {
+    (a.x - b.x).abs() <= tolerance && (a.y - b.y).abs() <= tolerance
+}
 
-    // Fix Close Z/z position tracking
-    // Parsing Z should implicitly move pen to start of subpath.
-    // For exact tracking we need to store start_x/y of last Move.
-    // Simplifying for now - Z does not produce coords.
+fn distance(a: Point, b: Point) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
 
-    commands
+// Only `M`, `L`, `C`, `Q`, `A` and `Z`: the passes below don't have to
+// care about shorthands, and the writer picks `H`/`V` again
+fn normalize(path: &PathData) -> Vec<Segment> {
+    path.iter()
+        .map(|step| match step.segment {
+            Segment::Close => Segment::Close,
+            _ => step.normalized(),
+        })
+        .collect()
 }
 
-fn format_num(n: f64, p: usize) -> String {
-    let factor = 10u32.pow(p as u32) as f64;
-    let rounded = (n * factor).round() / factor;
-    // Remove leading zeros etc.
-    let s = rounded.to_string();
-    if s.starts_with("0.") {
-        s[1..].to_string()
-    } else if s.starts_with("-0.") {
-        format!("-{}", &s[2..])
-    } else {
-        s
+fn is_zero_length(step: &Step, tolerance: f64) -> bool {
+    let at_start = |x: f64, y: f64| near(Point::new(x, y), step.from, tolerance);
+    match step.segment {
+        Segment::LineTo { x, y } | Segment::ArcTo { x, y, .. } => at_start(x, y),
+        Segment::CubicTo {
+            x1,
+            y1,
+            x2,
+            y2,
+            x,
+            y,
+        } => at_start(x1, y1) && at_start(x2, y2) && at_start(x, y),
+        Segment::QuadTo { x1, y1, x, y } => at_start(x1, y1) && at_start(x, y),
+        _ => false,
     }
 }
 
-fn stringify_optimized(commands: &[Command], opts: &ConvertPathData) -> String {
-    let mut s = String::new();
-    let p = opts.float_precision;
-
-    // State for optimization
-    let mut cur_x = 0.0;
-    let mut cur_y = 0.0;
-    let mut subpath_start_x = 0.0;
-    let mut subpath_start_y = 0.0;
-
-    for cmd in commands {
-        match cmd {
-            Command::Move(x, y) => {
-                // Always M? Or m?
-                // M is usually safest/clearest for start.
-                // relative m is only relative to (0,0) if first, or prev command.
-                let abs_str = format!("M{} {}", format_num(*x, p), format_num(*y, p));
-                let rel_str = format!(
-                    "m{} {}",
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                if rel_str.len() < abs_str.len() {
-                    s.push_str(&rel_str);
-                } else {
-                    s.push_str(&abs_str);
-                }
-
-                cur_x = *x;
-                cur_y = *y;
-                subpath_start_x = *x;
-                subpath_start_y = *y;
-            }
-            Command::Line(x, y) => {
-                let abs_x_s = format_num(*x, p);
-                let abs_y_s = format_num(*y, p);
-                let rel_x_s = format_num(*x - cur_x, p);
-                let rel_y_s = format_num(*y - cur_y, p);
-
-                // Candidates:
-                // L x y
-                // l dx dy
-                // H x (if y == cur_y)
-                // h dx (if y == cur_y)
-                // V y (if x == cur_x)
-                // v dy (if x == cur_x)
-
-                let mut best_str = format!("L{} {}", abs_x_s, abs_y_s);
-                let rel_l = format!("l{} {}", rel_x_s, rel_y_s);
-                if rel_l.len() < best_str.len() {
-                    best_str = rel_l;
-                }
+// Segments that go nowhere, unless they're all a subpath has: with round
+// caps, that draws a dot
+fn remove_zero_length(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let steps = steps(segments);
+    let mut out = Vec::with_capacity(steps.len());
+    let mut start = 0;
+    while start < steps.len() {
+        let end = steps[start + 1..]
+            .iter()
+            .position(|s| matches!(s.segment, Segment::MoveTo { .. }))
+            .map_or(steps.len(), |i| start + 1 + i);
+        let subpath = &steps[start..end];
+        let draws = subpath.iter().any(|s| {
+            !matches!(s.segment, Segment::MoveTo { .. } | Segment::Close)
+                && !is_zero_length(s, tolerance)
+        });
+        out.extend(
+            subpath
+                .iter()
+                .filter(|s| !draws || !is_zero_length(s, tolerance))
+                .map(|s| s.segment),
+        );
+        start = end;
+    }
+    out
+}
 
-                // H/h check (check rounded values to be safe?)
-                // Effectively we check if diff is close to 0.
-                if (y - cur_y).abs() < f64::EPSILON {
-                    let abs_h = format!("H{}", abs_x_s);
-                    let rel_h = format!("h{}", rel_x_s);
-                    if abs_h.len() < best_str.len() {
-                        best_str = abs_h;
-                    }
-                    if rel_h.len() < best_str.len() {
-                        best_str = rel_h;
-                    }
-                }
+// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0);
+    distance(p, Point::new(a.x + t * dx, a.y + t * dy))
+}
 
-                if (x - cur_x).abs() < f64::EPSILON {
-                    let abs_v = format!("V{}", abs_y_s);
-                    let rel_v = format!("v{}", rel_y_s);
-                    if abs_v.len() < best_str.len() {
-                        best_str = abs_v;
-                    }
-                    if rel_v.len() < best_str.len() {
-                        best_str = rel_v;
+// Curves whose control points sit on the chord, and arcs too flat to bulge
+fn straighten_curves(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    steps(segments)
+        .iter()
+        .map(|step| {
+            let (from, to) = (step.from, step.to);
+            let on_chord =
+                |x: f64, y: f64| segment_distance(Point::new(x, y), from, to) <= tolerance;
+            let straight = match step.segment {
+                Segment::CubicTo { x1, y1, x2, y2, .. } => on_chord(x1, y1) && on_chord(x2, y2),
+                Segment::QuadTo { x1, y1, .. } => on_chord(x1, y1),
+                Segment::ArcTo {
+                    rx, ry, large_arc, ..
+                } => {
+                    if rx == 0.0 || ry == 0.0 {
+                        true
+                    } else if large_arc || (rx - ry).abs() > tolerance {
+                        false
+                    } else {
+                        // Radii too small for the chord are scaled up
+                        let half = distance(from, to) / 2.0;
+                        let r = rx.abs().max(half);
+                        r - (r * r - half * half).max(0.0).sqrt() <= tolerance
                     }
                 }
-
-                s.push_str(&best_str);
-                cur_x = *x;
-                cur_y = *y;
-            }
-            Command::Horiz(x) => {
-                // Just optimize to H/h
-                let abs_s = format!("H{}", format_num(*x, p));
-                let rel_s = format!("h{}", format_num(*x - cur_x, p));
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-            }
-            Command::Vert(y) => {
-                let abs_s = format!("V{}", format_num(*y, p));
-                let rel_s = format!("v{}", format_num(*y - cur_y, p));
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_y = *y;
-            }
-            Command::Close => {
-                s.push('z');
-                cur_x = subpath_start_x;
-                cur_y = subpath_start_y;
-            }
-            // ... Implement others (Curve, Quad, Arc) similar way
-            Command::Curve(x1, y1, x2, y2, x, y) => {
-                let abs_coords = format!(
-                    "{} {} {} {} {} {}",
-                    format_num(*x1, p),
-                    format_num(*y1, p),
-                    format_num(*x2, p),
-                    format_num(*y2, p),
-                    format_num(*x, p),
-                    format_num(*y, p)
-                );
-                let rel_coords = format!(
-                    "{} {} {} {} {} {}",
-                    format_num(*x1 - cur_x, p),
-                    format_num(*y1 - cur_y, p),
-                    format_num(*x2 - cur_x, p),
-                    format_num(*y2 - cur_y, p),
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                let abs_s = format!("C{}", abs_coords);
-                let rel_s = format!("c{}", rel_coords);
-
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-                cur_y = *y;
-            }
-            Command::SmoothCurve(x2, y2, x, y) => {
-                let abs_coords = format!(
-                    "{} {} {} {}",
-                    format_num(*x2, p),
-                    format_num(*y2, p),
-                    format_num(*x, p),
-                    format_num(*y, p)
-                );
-                let rel_coords = format!(
-                    "{} {} {} {}",
-                    format_num(*x2 - cur_x, p),
-                    format_num(*y2 - cur_y, p),
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                let abs_s = format!("S{}", abs_coords);
-                let rel_s = format!("s{}", rel_coords);
-
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-                cur_y = *y;
-            }
-            Command::Quad(x1, y1, x, y) => {
-                let abs_coords = format!(
-                    "{} {} {} {}",
-                    format_num(*x1, p),
-                    format_num(*y1, p),
-                    format_num(*x, p),
-                    format_num(*y, p)
-                );
-                let rel_coords = format!(
-                    "{} {} {} {}",
-                    format_num(*x1 - cur_x, p),
-                    format_num(*y1 - cur_y, p),
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                let abs_s = format!("Q{}", abs_coords);
-                let rel_s = format!("q{}", rel_coords);
-
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-                cur_y = *y;
-            }
-            Command::SmoothQuad(x, y) => {
-                let abs_coords = format!("{} {}", format_num(*x, p), format_num(*y, p));
-                let rel_coords = format!(
-                    "{} {}",
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                let abs_s = format!("T{}", abs_coords);
-                let rel_s = format!("t{}", rel_coords);
-
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-                cur_y = *y;
+                _ => false,
+            };
+            if straight && !near(from, to, tolerance) {
+                Segment::LineTo { x: to.x, y: to.y }
+            } else {
+                step.segment
             }
-            Command::Arc(rx, ry, rot, la, sf, x, y) => {
-                // Formatting arc flags: 0 or 1
-                let la_s = if *la { "1" } else { "0" };
-                let sf_s = if *sf { "1" } else { "0" };
-
-                let abs_coords = format!(
-                    "{} {} {} {} {} {} {}",
-                    format_num(*rx, p),
-                    format_num(*ry, p),
-                    format_num(*rot, p),
-                    la_s,
-                    sf_s,
-                    format_num(*x, p),
-                    format_num(*y, p)
-                );
-                let rel_coords = format!(
-                    "{} {} {} {} {} {} {}",
-                    format_num(*rx, p),
-                    format_num(*ry, p),
-                    format_num(*rot, p),
-                    la_s,
-                    sf_s,
-                    format_num(*x - cur_x, p),
-                    format_num(*y - cur_y, p)
-                );
-
-                let abs_s = format!("A{}", abs_coords);
-                let rel_s = format!("a{}", rel_coords);
-
-                if rel_s.len() < abs_s.len() {
-                    s.push_str(&rel_s);
-                } else {
-                    s.push_str(&abs_s);
-                }
-                cur_x = *x;
-                cur_y = *y;
+        })
+        .collect()
+}
+
+// Runs of lines going on in the same direction become one line
+fn merge_collinear(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let mut out: Vec<Segment> = Vec::with_capacity(segments.len());
+    // Where the last line in `out` starts, and the points it passes through
+    let mut run: Option<(Point, Vec<Point>)> = None;
+    for step in steps(segments) {
+        let Segment::LineTo { x, y } = step.segment else {
+            run = None;
+            out.push(step.segment);
+            continue;
+        };
+        if let Some((start, points)) = &mut run {
+            let end = Point::new(x, y);
+            if points
+                .iter()
+                .all(|p| segment_distance(*p, *start, end) <= tolerance)
+            {
+                points.push(end);
+                out.pop();
+                out.push(step.segment);
+                continue;
             }
         }
+        run = Some((step.from, vec![step.to]));
+        out.push(step.segment);
     }
+    out
+}
 
-    s
+// Moves that don't start anything: followed by another move or the end of
+// the path, or going where a `Z` already left the pen
+fn remove_useless_moves(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let steps = steps(segments);
+    steps
+        .iter()
+        .enumerate()
+        .filter(|(i, step)| {
+            if !matches!(step.segment, Segment::MoveTo { .. }) {
+                return true;
+            }
+            match steps.get(i + 1) {
+                Some(next) if matches!(next.segment, Segment::MoveTo { .. }) => false,
+                // Keep something for the path to be
+                _ if *i == 0 => true,
+                None => false,
+                Some(_) => {
+                    !(matches!(steps[i - 1].segment, Segment::Close)
+                        && near(step.from, step.to, tolerance))
+                }
+            }
+        })
+        .map(|(_, step)| step.segment)
+        .collect()
 }
 
-// Reuse PathLexer from previous tool call (need to duplicate code here as I'm overwriting file)
-struct PathLexer<'a> {
-    input: &'a str,
-    pos: usize,
+// A line back to the subpath start right before `Z`, which draws it anyway
+fn remove_closing_lines(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let steps = steps(segments);
+    steps
+        .iter()
+        .enumerate()
+        .filter(|(i, step)| {
+            !(matches!(step.segment, Segment::LineTo { .. })
+                && near(step.to, step.subpath_start, tolerance)
+                && steps
+                    .get(i + 1)
+                    .is_some_and(|s| matches!(s.segment, Segment::Close)))
+        })
+        .map(|(_, step)| step.segment)
+        .collect()
 }
 
-impl<'a> PathLexer<'a> {
-    fn new(input: &'a str) -> Self {
-        Self { input, pos: 0 }
-    }
+// A piece of a circle spanning `angle` radians, ending at `to`
+struct CircleArc {
+    center: Point,
+    r: f64,
+    sweep: bool,
+    angle: f64,
+    to: Point,
+    tolerance: f64,
+}
 
-    fn skip_ws_comma(&mut self) {
-        while self.pos < self.input.len() {
-            let c = self.input.as_bytes()[self.pos] as char;
-            if c.is_whitespace() || c == ',' {
-                self.pos += 1;
-            } else {
-                break;
-            }
+impl CircleArc {
+    fn extend(&mut self, next: CircleArc) -> bool {
+        let tolerance = self.tolerance.min(next.tolerance);
+        if !near(self.center, next.center, tolerance)
+            || (self.r - next.r).abs() > tolerance
+            || self.sweep != next.sweep
+            || self.angle + next.angle >= 2.0 * PI - 1e-6
+        {
+            return false;
         }
+        self.angle += next.angle;
+        self.to = next.to;
+        true
     }
 
-    fn peek_char(&self) -> Option<char> {
-        if self.pos >= self.input.len() {
-            None
-        } else {
-            Some(self.input.as_bytes()[self.pos] as char)
+    fn segment(&self) -> Segment {
+        Segment::ArcTo {
+            rx: self.r,
+            ry: self.r,
+            rotation: 0.0,
+            large_arc: self.angle > PI,
+            sweep: self.sweep,
+            x: self.to.x,
+            y: self.to.y,
         }
     }
+}
 
-    fn read_char(&mut self) -> Option<char> {
-        if self.pos >= self.input.len() {
-            None
-        } else {
-            let c = self.input.as_bytes()[self.pos] as char;
-            self.pos += 1;
-            Some(c)
-        }
+fn cubic_at(p: [Point; 4], t: f64) -> Point {
+    let mt = 1.0 - t;
+    let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+    Point::new(
+        a * p[0].x + b * p[1].x + c * p[2].x + d * p[3].x,
+        a * p[0].y + b * p[1].y + c * p[2].y + d * p[3].y,
+    )
+}
+
+fn circle_through(a: Point, b: Point, c: Point) -> Option<Point> {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < 1e-12 {
+        return None;
     }
+    let (a2, b2, c2) = (
+        a.x * a.x + a.y * a.y,
+        b.x * b.x + b.y * b.y,
+        c.x * c.x + c.y * c.y,
+    );
+    Some(Point::new(
+        (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+        (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+    ))
+}
 
-    fn read_number(&mut self) -> Option<f64> {
-        self.skip_ws_comma();
-        if self.pos >= self.input.len() {
+// The circle a cubic follows, if it stays on it and keeps going one way
+fn fit_arc(step: &Step, tolerance: f64) -> Option<CircleArc> {
+    let Segment::CubicTo { x1, y1, x2, y2, .. } = step.segment else {
+        return None;
+    };
+    let p = [step.from, Point::new(x1, y1), Point::new(x2, y2), step.to];
+    if near(p[0], p[3], tolerance) {
+        return None;
+    }
+    let center = circle_through(p[0], cubic_at(p, 0.5), p[3])?;
+    let r = distance(center, p[0]);
+    let tolerance = (ARC_THRESHOLD * tolerance).min(ARC_TOLERANCE * r / 100.0);
+
+    let angle_of = |q: Point| (q.y - center.y).atan2(q.x - center.x);
+    let start = angle_of(p[0]);
+    let mid = (angle_of(cubic_at(p, 0.5)) - start + PI).rem_euclid(2.0 * PI) - PI;
+    let sweep = mid > 0.0;
+    let direction = if sweep { 1.0 } else { -1.0 };
+
+    let mut last = 0.0;
+    for t in [0.25, 0.5, 0.75, 1.0] {
+        let q = cubic_at(p, t);
+        if (distance(center, q) - r).abs() > tolerance {
             return None;
         }
-        let start = self.pos;
-        let mut seen_dot = false;
-        let mut seen_exp = false;
-        if self.peek_char() == Some('+') || self.peek_char() == Some('-') {
-            self.pos += 1;
+        let offset = (direction * (angle_of(q) - start)).rem_euclid(2.0 * PI);
+        if offset <= last {
+            return None;
         }
+        last = offset;
+    }
+    Some(CircleArc {
+        center,
+        r,
+        sweep,
+        angle: last,
+        to: p[3],
+        tolerance,
+    })
+}
 
-        while self.pos < self.input.len() {
-            let c = self.input.as_bytes()[self.pos] as char;
-            if c.is_ascii_digit() {
-                self.pos += 1;
-            } else if c == '.' && !seen_dot && !seen_exp {
-                seen_dot = true;
-                self.pos += 1;
-            } else if (c == 'e' || c == 'E') && !seen_exp {
-                seen_exp = true;
-                self.pos += 1;
-                if self.peek_char() == Some('+') || self.peek_char() == Some('-') {
-                    self.pos += 1;
-                }
-            } else {
+// Runs of cubics along one circle become a single arc
+fn make_arcs(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let steps = steps(segments);
+    let mut out = Vec::with_capacity(steps.len());
+    let mut i = 0;
+    while i < steps.len() {
+        let Some(mut arc) = fit_arc(&steps[i], tolerance) else {
+            out.push(steps[i].segment);
+            i += 1;
+            continue;
+        };
+        i += 1;
+        while let Some(next) = steps.get(i).and_then(|s| fit_arc(s, tolerance)) {
+            if !arc.extend(next) {
                 break;
             }
+            i += 1;
         }
-        let sub = &self.input[start..self.pos];
-        sub.parse::<f64>().ok()
+        out.push(arc.segment());
     }
+    out
+}
 
-    fn read_flag(&mut self) -> Option<bool> {
-        self.skip_ws_comma();
-        match self.read_char() {
-            Some('0') => Some(false),
-            Some('1') => Some(true),
-            _ => None,
-        }
-    }
+// `C` becomes `S` and `Q` becomes `T` when the first control point is the
+// one the shorthand would imply
+fn use_shorthands(segments: Vec<Segment>, tolerance: f64) -> Vec<Segment> {
+    let reflect = |p: Point, about: Point| Point::new(2.0 * about.x - p.x, 2.0 * about.y - p.y);
+    // The last control point in effect, for `C`/`S` or `Q`/`T`
+    let mut control: Option<(char, Point)> = None;
+    steps(segments)
+        .iter()
+        .map(|step| {
+            let implied = |kind: char| match control {
+                Some((k, p)) if k == kind => reflect(p, step.from),
+                _ => step.from,
+            };
+            match step.segment {
+                Segment::CubicTo {
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    x,
+                    y,
+                } => {
+                    let shorthand = near(Point::new(x1, y1), implied('C'), tolerance);
+                    control = Some(('C', Point::new(x2, y2)));
+                    if shorthand {
+                        Segment::SmoothCubicTo { x2, y2, x, y }
+                    } else {
+                        step.segment
+                    }
+                }
+                Segment::QuadTo { x1, y1, x, y } => {
+                    let implied = implied('Q');
+                    if near(Point::new(x1, y1), implied, tolerance) {
+                        control = Some(('Q', implied));
+                        Segment::SmoothQuadTo { x, y }
+                    } else {
+                        control = Some(('Q', Point::new(x1, y1)));
+                        step.segment
+                    }
+                }
+                segment => {
+                    control = None;
+                    segment
+                }
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    fn optimize(d: &str) -> String {
+        optimize_path_data(d, &ConvertPathData::default(), &PathContext::default())
+    }
 
     #[test]
     fn test_optimize_line_rel() {
@@ -743,7 +510,7 @@ mod tests {
         // L 11 11 (7 chars)
         // l 1 1 (5 chars) -> Wins
         let input = "M 10 10 L 11 11";
-        let out = optimize_path_data(input, &ConvertPathData::default());
+        let out = optimize(input);
         assert_eq!(out, "M10 10l1 1");
     }
 
@@ -754,15 +521,23 @@ mod tests {
         // l 10 0 (6)
         // H 20 (3) -> Wins
         let input = "M 10 10 L 20 10";
-        let out = optimize_path_data(input, &ConvertPathData::default());
+        let out = optimize(input);
         assert_eq!(out, "M10 10H20");
     }
 
+    #[test]
+    fn test_relative_after_close() {
+        // `z` moves the pen back to the subpath start
+        let input = "M10 10h10v10zm5 5h1";
+        let out = optimize(input);
+        assert_eq!(out, "M10 10H20V20zm5 5h1");
+    }
+
     #[test]
     fn test_optimize_arc() {
         // Circle path from convert_shape_to_path
         let input = "M0 50A50 50 0 1 0 100 50A50 50 0 1 0 0 50z";
-        let out = optimize_path_data(input, &ConvertPathData::default());
+        let out = optimize(input);
         println!("Optimized Arc: '{}'", out);
         // Should not lose the arcs!
         assert!(
@@ -771,4 +546,105 @@ mod tests {
             out
         );
     }
+
+    #[test]
+    fn test_make_arcs_and_straighten() {
+        // Three quarter circles of radius 5, and a curve that's a line
+        let input = "M0 0C0 2.7614 2.2386 5 5 5C7.7614 5 10 2.7614 10 0C10 -2.7614 7.7614 -5 5 -5M0 20C3 20.0001 6 20 10 20";
+        assert_eq!(optimize(input), "M0 0A5 5 0 1 0 5 -5M0 20H10");
+
+        // Not under markers
+        let ctx = PathContext {
+            has_markers: true,
+            ..PathContext::default()
+        };
+        let quarters = "M0 0C0 2.7614 2.2386 5 5 5C7.7614 5 10 2.7614 10 0";
+        assert!(optimize_path_data(quarters, &ConvertPathData::default(), &ctx).contains('C'));
+    }
+
+    #[test]
+    fn test_shorthands() {
+        assert_eq!(
+            optimize("M0 0C0 10 10 10 10 0C10 -10 20 -10 20 0"),
+            "M0 0C0 10 10 10 10 0S20 -10 20 0"
+        );
+        assert_eq!(
+            optimize("M0 0Q5 10 10 0Q15 -10 20 0"),
+            "M0 0Q5 10 10 0T20 0"
+        );
+    }
+
+    #[test]
+    fn test_merge_lines_and_moves() {
+        assert_eq!(optimize("M0 0L5 0L10 0L10 5L10 10L0 10"), "M0 0H10V10H0");
+        assert_eq!(
+            optimize("M5 5M0 0L10 0L10 10L0 0ZM0 0L5 5M20 20"),
+            "M0 0H10V10zL5 5"
+        );
+    }
+
+    #[test]
+    fn test_keep_closing_line_with_caps() {
+        let run = |input: &str| {
+            let mut doc = parser::parse(input).unwrap();
+            ConvertPathData::default().apply(&mut doc);
+            printer::print(&doc)
+        };
+        assert_eq!(
+            run("<svg><path d=\"M0 0L10 0L0 0Z\" stroke=\"red\"/></svg>"),
+            "<svg><path d=\"M0 0H10z\" stroke=\"red\"/></svg>"
+        );
+        assert_eq!(
+            run("<svg><path d=\"M0 0L10 0L0 0Z\" stroke=\"red\" stroke-linecap=\"round\"/></svg>"),
+            "<svg><path d=\"M0 0H10H0z\" stroke=\"red\" stroke-linecap=\"round\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_error_budget() {
+        // 0.1px at 24px: whole units on a 2400 unit map, thousandths on an
+        // icon one unit across
+        let run = |view_box: &str, d: &str| {
+            let input = format!("<svg viewBox=\"{}\"><path d=\"{}\"/></svg>", view_box, d);
+            let mut doc = parser::parse(&input).unwrap();
+            ConvertPathData {
+                error_budget: Some(ErrorBudget {
+                    error: 0.1,
+                    size: Some(24.0),
+                }),
+                ..ConvertPathData::default()
+            }
+            .apply(&mut doc);
+            printer::print(&doc)
+        };
+        assert!(run("0 0 2400 2400", "M10.4 10.4L1000.3 1000.2").contains("d=\"M10 10l990 990\""));
+        assert!(run("0 0 1 1", "M0.12345 0.45678L0.5 0.5").contains("d=\"M.123 .457L.5 .5\""));
+
+        // Bounding box units don't scale with the viewBox
+        let input = "<svg viewBox=\"0 0 2400 2400\"><clipPath clipPathUnits=\"objectBoundingBox\"><path d=\"M0.1 0.1L0.9 0.15L0.5 0.85z\"/></clipPath></svg>";
+        let mut doc = parser::parse(input).unwrap();
+        ConvertPathData {
+            error_budget: Some(ErrorBudget {
+                error: 0.1,
+                size: Some(24.0),
+            }),
+            ..ConvertPathData::default()
+        }
+        .apply(&mut doc);
+        assert!(printer::print(&doc).contains("d=\"M.1 .1L.9 .15L.5 .85z\""));
+    }
+
+    #[test]
+    fn test_zero_length() {
+        // A lone zero-length segment is a dot with round caps
+        assert_eq!(optimize("M0 0L0 0L10 0M5 5L5 5"), "M0 0H10M5 5H5");
+        let ctx = PathContext {
+            has_markers: true,
+            ..PathContext::default()
+        };
+        assert_eq!(
+            optimize_path_data("M0 0L0 0L10 0", &ConvertPathData::default(), &ctx),
+            "M0 0H0H10"
+        );
+    }
 }
@@ -0,0 +1,416 @@
+use crate::css;
+use crate::path::{self, PathData, Point, Segment, WriteOptions};
+use crate::plugins::Plugin;
+use crate::references::ReferenceIndex;
+use crate::style::{self, ComputedStyle, StyledNode};
+use crate::tree::{Document, Element, Node};
+use indexmap::IndexMap;
+
+/// Rewrites paths that draw a rectangle, circle, ellipse, line or polygon
+/// as that basic shape, when it's shorter.
+pub struct ConvertPathToShapes {
+    pub float_precision: usize,
+}
+
+impl Default for ConvertPathToShapes {
+    fn default() -> Self {
+        Self { float_precision: 3 }
+    }
+}
+
+// Cubic approximations of a quarter ellipse place their control points at
+// this fraction of the radius; exporters vary in the last digits
+const KAPPA: f64 = 0.5522847498;
+// Moving both control points of a cubic by `d` moves its midpoint by up to
+// `0.75 * d`, so control points may be off by this many times the precision
+const KAPPA_TOLERANCE: f64 = 1.0 / 0.75;
+
+impl Plugin for ConvertPathToShapes {
+    fn apply(&self, doc: &mut Document) {
+        // Rules for `path` would stop matching
+        if has_stylesheet(&doc.root) {
+            return;
+        }
+        let styles = style::resolve(doc);
+        let index = ReferenceIndex::build(doc);
+        process(&mut doc.root, &styles, &index, self);
+    }
+}
+
+fn has_stylesheet(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => css::style_text(elem).is_some() || has_stylesheet(&elem.children),
+        _ => false,
+    })
+}
+
+fn process(
+    nodes: &mut [Node],
+    styles: &[StyledNode],
+    index: &ReferenceIndex,
+    opts: &ConvertPathToShapes,
+) {
+    for (node, styled) in nodes.iter_mut().zip(styles) {
+        if let Node::Element(elem) = node {
+            if elem.name == "path" && can_convert(elem, &styled.style, index) {
+                convert(elem, opts);
+            }
+            process(&mut elem.children, &styled.children, index, opts);
+        }
+    }
+}
+
+// Markers, dashes, `pathLength`, animations and references all depend on
+// the exact path data, and where it starts
+fn can_convert(elem: &Element, style: &ComputedStyle, index: &ReferenceIndex) -> bool {
+    let referenced = elem
+        .attributes
+        .get("id")
+        .is_some_and(|id| index.is_referenced(id));
+    let animated = elem
+        .children
+        .iter()
+        .any(|child| matches!(child, Node::Element(_)));
+    !referenced
+        && !animated
+        && !elem.attributes.contains_key("pathLength")
+        && [
+            "marker-start",
+            "marker-mid",
+            "marker-end",
+            "stroke-dasharray",
+        ]
+        .iter()
+        .all(|p| style.value(p) == Some("none"))
+}
+
+enum Shape {
+    Line(Point, Point),
+    Rect { x: f64, y: f64, w: f64, h: f64 },
+    Ellipse { cx: f64, cy: f64, rx: f64, ry: f64 },
+    Polygon(Vec<Point>),
+}
+
+fn convert(elem: &mut Element, opts: &ConvertPathToShapes) {
+    let Some(d) = elem.attributes.get("d") else {
+        return;
+    };
+    let eps = 1.0 / 10u32.pow(opts.float_precision as u32) as f64;
+    let Some(shape) = recognize(&PathData::parse(d), eps) else {
+        return;
+    };
+
+    let fmt = |n: f64| {
+        path::format_number(
+            n,
+            &WriteOptions {
+                precision: Some(opts.float_precision),
+                ..Default::default()
+            },
+        )
+    };
+    let (name, attrs): (&str, Vec<(&str, String)>) = match shape {
+        Shape::Line(p1, p2) => (
+            "line",
+            vec![
+                ("x1", fmt(p1.x)),
+                ("y1", fmt(p1.y)),
+                ("x2", fmt(p2.x)),
+                ("y2", fmt(p2.y)),
+            ],
+        ),
+        Shape::Rect { x, y, w, h } => (
+            "rect",
+            vec![
+                ("x", fmt(x)),
+                ("y", fmt(y)),
+                ("width", fmt(w)),
+                ("height", fmt(h)),
+            ],
+        ),
+        Shape::Ellipse { cx, cy, rx, ry } if fmt(rx) == fmt(ry) => (
+            "circle",
+            vec![("cx", fmt(cx)), ("cy", fmt(cy)), ("r", fmt(rx))],
+        ),
+        Shape::Ellipse { cx, cy, rx, ry } => (
+            "ellipse",
+            vec![
+                ("cx", fmt(cx)),
+                ("cy", fmt(cy)),
+                ("rx", fmt(rx)),
+                ("ry", fmt(ry)),
+            ],
+        ),
+        Shape::Polygon(points) => {
+            let points = points
+                .iter()
+                .map(|p| format!("{} {}", fmt(p.x), fmt(p.y)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            ("polygon", vec![("points", points)])
+        }
+    };
+    // Coordinates of 0 are the default
+    let attrs: Vec<_> = attrs
+        .into_iter()
+        .filter(|(name, value)| {
+            value != "0" || !matches!(*name, "x" | "y" | "cx" | "cy" | "x1" | "y1" | "x2" | "y2")
+        })
+        .collect();
+
+    let old_len = elem.name.len() + attr_len("d", d);
+    let new_len = name.len() + attrs.iter().map(|(n, v)| attr_len(n, v)).sum::<usize>();
+    if new_len >= old_len {
+        return;
+    }
+
+    // The shape's attributes take the place of `d`
+    let mut attributes = IndexMap::new();
+    for (key, value) in elem.attributes.drain(..) {
+        if key == "d" {
+            for (name, value) in &attrs {
+                attributes.insert(name.to_string(), value.clone());
+            }
+        } else {
+            attributes.insert(key, value);
+        }
+    }
+    elem.attributes = attributes;
+    elem.name = name.to_string();
+}
+
+// Length of ` name="value"` in the output
+fn attr_len(name: &str, value: &str) -> usize {
+    name.len() + value.len() + 4
+}
+
+fn recognize(path: &PathData, eps: f64) -> Option<Shape> {
+    let steps: Vec<_> = path.iter().collect();
+    let (first, rest) = steps.split_first()?;
+    let Segment::MoveTo { x, y } = first.segment else {
+        return None;
+    };
+    let start = Point::new(x, y);
+    let (closed, body) = match rest.split_last() {
+        Some((last, body)) if last.segment == Segment::Close => (true, body),
+        _ => (false, rest),
+    };
+    if body.is_empty() {
+        return None;
+    }
+    let segments: Vec<Segment> = body.iter().map(|step| step.normalized()).collect();
+    let near = |a: Point, b: Point| (a.x - b.x).abs() <= eps && (a.y - b.y).abs() <= eps;
+
+    if segments.iter().all(|s| matches!(s, Segment::LineTo { .. })) {
+        let mut points: Vec<Point> = std::iter::once(start)
+            .chain(body.iter().map(|step| step.to))
+            .collect();
+        if !closed {
+            return match points[..] {
+                [p1, p2] => Some(Shape::Line(p1, p2)),
+                _ => None,
+            };
+        }
+        if points.len() > 1 && near(points[points.len() - 1], start) {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return None;
+        }
+        return Some(as_rect(&points, eps).unwrap_or(Shape::Polygon(points)));
+    }
+
+    if !closed || !near(body[body.len() - 1].to, start) {
+        return None;
+    }
+    let ends: Vec<Point> = body.iter().map(|step| step.to).collect();
+    if segments.iter().all(|s| matches!(s, Segment::ArcTo { .. })) {
+        return ellipse_from_arcs(start, &segments, eps);
+    }
+    if segments.len() == 4
+        && segments
+            .iter()
+            .all(|s| matches!(s, Segment::CubicTo { .. }))
+    {
+        let (cx, cy, rx, ry) = quarter_points(start, &ends, eps)?;
+        let center = Point::new(cx, cy);
+        let mut from = start;
+        for segment in &segments {
+            let Segment::CubicTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } = *segment
+            else {
+                return None;
+            };
+            // Control points lie on the tangents at the extreme points
+            let to = Point::new(x, y);
+            let c1 = Point::new(
+                from.x + KAPPA * (to.x - center.x),
+                from.y + KAPPA * (to.y - center.y),
+            );
+            let c2 = Point::new(
+                to.x + KAPPA * (from.x - center.x),
+                to.y + KAPPA * (from.y - center.y),
+            );
+            let tolerance = KAPPA_TOLERANCE * eps;
+            let close = |a: Point, b: Point| {
+                (a.x - b.x).abs() <= tolerance && (a.y - b.y).abs() <= tolerance
+            };
+            if !close(Point::new(x1, y1), c1) || !close(Point::new(x2, y2), c2) {
+                return None;
+            }
+            from = to;
+        }
+        return Some(Shape::Ellipse { cx, cy, rx, ry });
+    }
+    None
+}
+
+// Four corners going around an axis-aligned rectangle
+fn as_rect(points: &[Point], eps: f64) -> Option<Shape> {
+    if points.len() != 4 {
+        return None;
+    }
+    let same = |a: f64, b: f64| (a - b).abs() <= eps;
+    let [p0, p1, p2, p3] = [points[0], points[1], points[2], points[3]];
+    let horizontal_first =
+        same(p0.y, p1.y) && same(p1.x, p2.x) && same(p2.y, p3.y) && same(p3.x, p0.x);
+    let vertical_first =
+        same(p0.x, p1.x) && same(p1.y, p2.y) && same(p2.x, p3.x) && same(p3.y, p0.y);
+    if !horizontal_first && !vertical_first {
+        return None;
+    }
+    let x = p0.x.min(p2.x);
+    let y = p0.y.min(p2.y);
+    let w = (p2.x - p0.x).abs();
+    let h = (p2.y - p0.y).abs();
+    (w > eps && h > eps).then_some(Shape::Rect { x, y, w, h })
+}
+
+// Two half or four quarter arcs of one unrotated ellipse, turning the same
+// way
+fn ellipse_from_arcs(start: Point, segments: &[Segment], eps: f64) -> Option<Shape> {
+    let mut radii = None;
+    let mut ends = Vec::new();
+    for segment in segments {
+        let Segment::ArcTo {
+            rx,
+            ry,
+            rotation,
+            large_arc,
+            sweep,
+            x,
+            y,
+        } = *segment
+        else {
+            return None;
+        };
+        let (rx, ry) = (rx.abs(), ry.abs());
+        if rotation != 0.0 && (rx - ry).abs() > eps {
+            return None;
+        }
+        match radii {
+            None => radii = Some((rx, ry, sweep)),
+            Some((r0x, r0y, s0)) => {
+                if (r0x - rx).abs() > eps || (r0y - ry).abs() > eps || s0 != sweep {
+                    return None;
+                }
+            }
+        }
+        if segments.len() == 4 && large_arc {
+            return None;
+        }
+        ends.push(Point::new(x, y));
+    }
+    let (arc_rx, arc_ry, _) = radii?;
+
+    let (cx, cy, rx, ry) = match segments.len() {
+        2 => {
+            let (p0, p1) = (start, ends[0]);
+            let (cx, cy) = ((p0.x + p1.x) / 2.0, (p0.y + p1.y) / 2.0);
+            if (p0.y - p1.y).abs() <= eps {
+                (cx, cy, (p1.x - p0.x).abs() / 2.0, arc_ry)
+            } else if (p0.x - p1.x).abs() <= eps {
+                (cx, cy, arc_rx, (p1.y - p0.y).abs() / 2.0)
+            } else {
+                return None;
+            }
+        }
+        4 => quarter_points(start, &ends, eps)?,
+        _ => return None,
+    };
+    // Radii that are too small get scaled up; others must match
+    let matches = |a: f64, b: f64| (a - b).abs() <= eps;
+    if !matches(rx, arc_rx) || !matches(ry, arc_ry) || rx <= eps || ry <= eps {
+        return None;
+    }
+    Some(Shape::Ellipse { cx, cy, rx, ry })
+}
+
+// The ellipse whose extreme points are `start` and the first three of
+// `ends`, in order around it: center and radii
+fn quarter_points(start: Point, ends: &[Point], eps: f64) -> Option<(f64, f64, f64, f64)> {
+    let (p1, p2, p3) = match ends {
+        [p1, p2, p3, ..] => (*p1, *p2, *p3),
+        _ => return None,
+    };
+    let cx = (start.x + p2.x) / 2.0;
+    let cy = (start.y + p2.y) / 2.0;
+    let same = |a: f64, b: f64| (a - b).abs() <= eps;
+    // The other two points are opposite too, on the other axis
+    if !same(p1.x + p3.x, 2.0 * cx) || !same(p1.y + p3.y, 2.0 * cy) {
+        return None;
+    }
+    let (rx, ry) = if same(start.y, cy) && same(p1.x, cx) {
+        ((start.x - cx).abs(), (p1.y - cy).abs())
+    } else if same(start.x, cx) && same(p1.y, cy) {
+        ((p1.x - cx).abs(), (start.y - cy).abs())
+    } else {
+        return None;
+    };
+    Some((cx, cy, rx, ry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    fn run(input: &str) -> String {
+        let mut doc = parser::parse(input).unwrap();
+        ConvertPathToShapes::default().apply(&mut doc);
+        printer::print(&doc)
+    }
+
+    #[test]
+    fn test_convert_to_shapes() {
+        let input = "<svg><path d=\"M0 50A50 50 0 1 0 100 50A50 50 0 1 0 0 50z\" fill=\"red\"/><path d=\"M10 5a10 5 0 0 1 10 5a10 5 0 0 1-10 5a10 5 0 0 1-10-5a10 5 0 0 1 10-5z\"/><path d=\"M 10 20 L 110 20 L 110 70 L 10 70 L 10 20 Z\"/></svg>";
+        let expected = "<svg><circle cx=\"50\" cy=\"50\" r=\"50\" fill=\"red\"/><ellipse cx=\"10\" cy=\"10\" rx=\"10\" ry=\"5\"/><rect x=\"10\" y=\"20\" width=\"100\" height=\"50\"/></svg>";
+        assert_eq!(run(input), expected);
+    }
+
+    #[test]
+    fn test_cubic_ellipse() {
+        let input = "<svg><path d=\"M20 10C20 15.523 15.523 20 10 20C4.477 20 0 15.523 0 10C0 4.477 4.477 0 10 0C15.523 0 20 4.477 20 10z\"/></svg>";
+        let expected = "<svg><circle cx=\"10\" cy=\"10\" r=\"10\"/></svg>";
+        assert_eq!(run(input), expected);
+
+        // Visibly flatter than a circle at this precision
+        let input = "<svg><path d=\"M20 10C20 15.6 15.6 20 10 20C4.4 20 0 15.6 0 10C0 4.4 4.4 0 10 0C15.6 0 20 4.4 20 10z\"/></svg>";
+        assert_eq!(run(input), input);
+    }
+
+    #[test]
+    fn test_keep_paths() {
+        // Markers, dashes, open polylines, and a rect, line and polygon that
+        // would be longer
+        let input = "<svg><path d=\"M0 0A1 1 0 0 0 2 0A1 1 0 0 0 0 0z\" marker-start=\"url(#m)\"/><path d=\"M0 0A1 1 0 0 0 2 0A1 1 0 0 0 0 0z\" stroke-dasharray=\"2\"/><path d=\"M0 0h10v10\"/><path d=\"M0 0h10v10H0z\"/><path d=\"M1 1h1\"/><path d=\"M0 0L10 0L5 8z\"/></svg>";
+        assert_eq!(run(input), input);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::path::{self, CoordMode, PathData, Segment, WriteOptions};
 use crate::plugins::Plugin;
 use crate::tree::{Document, Element, Node};
 
@@ -46,36 +47,139 @@ fn get_num(elem: &Element, attr: &str, def: f64) -> f64 {
         .unwrap_or(def)
 }
 
+fn write_path(segments: Vec<Segment>, mode: CoordMode) -> String {
+    PathData { segments }.write(&WriteOptions {
+        mode,
+        ..WriteOptions::default()
+    })
+}
+
 fn convert_rect(elem: &Element) -> Option<String> {
     let w = get_num(elem, "width", 0.0);
     let h = get_num(elem, "height", 0.0);
     let x = get_num(elem, "x", 0.0);
     let y = get_num(elem, "y", 0.0);
-    let rx_raw = elem
-        .attributes
-        .get("rx")
-        .and_then(|v| v.parse::<f64>().ok());
-    let ry_raw = elem
-        .attributes
-        .get("ry")
-        .and_then(|v| v.parse::<f64>().ok());
-
-    // Simplification: handle regular rects first
-    // TODO: Handle rounded rects (rx, ry)
-    if w == 0.0 || h == 0.0 {
+
+    if w <= 0.0 || h <= 0.0 {
         return None; // Invalid or invisible
     }
 
-    if rx_raw.is_none() && ry_raw.is_none() {
-        // M x y H x+w V y+h H x z
-        // Or simpler: M x y h w v h h -w z
-        // Let's use absolute for simplicity initially
-        return Some(format!("M{} {}h{}v{}h-{}z", x, y, w, h, w));
+    let Some((rx, ry)) = corner_radii(elem, w, h)? else {
+        // M x y h w v h h -w z
+        return Some(write_path(
+            vec![
+                Segment::MoveTo { x, y },
+                Segment::HorizontalTo { x: x + w },
+                Segment::VerticalTo { y: y + h },
+                Segment::HorizontalTo { x },
+                Segment::Close,
+            ],
+            CoordMode::Relative,
+        ));
+    };
+
+    // Straight edges between the corners, skipped when the corners meet
+    let arc = |x, y| Segment::ArcTo {
+        rx,
+        ry,
+        rotation: 0.0,
+        large_arc: false,
+        sweep: true,
+        x,
+        y,
+    };
+    let mut segments = vec![Segment::MoveTo { x: x + rx, y }];
+    let edge = |segments: &mut Vec<Segment>, segment: Segment, length: f64| {
+        if length > 0.0 {
+            segments.push(segment);
+        }
+    };
+    edge(
+        &mut segments,
+        Segment::HorizontalTo { x: x + w - rx },
+        w - 2.0 * rx,
+    );
+    segments.push(arc(x + w, y + ry));
+    edge(
+        &mut segments,
+        Segment::VerticalTo { y: y + h - ry },
+        h - 2.0 * ry,
+    );
+    segments.push(arc(x + w - rx, y + h));
+    edge(
+        &mut segments,
+        Segment::HorizontalTo { x: x + rx },
+        w - 2.0 * rx,
+    );
+    segments.push(arc(x, y + h - ry));
+    edge(
+        &mut segments,
+        Segment::VerticalTo { y: y + ry },
+        h - 2.0 * ry,
+    );
+    segments.push(arc(x + rx, y));
+    segments.push(Segment::Close);
+    let d = write_path(merge_corners(segments), CoordMode::Relative);
+
+    // Rounded rects are often shorter as they are, once their numbers are
+    // cleaned up too
+    let shape_len: usize = ["x", "y", "width", "height", "rx", "ry"]
+        .iter()
+        .filter(|name| elem.attributes.contains_key(**name))
+        .map(|name| {
+            let n = path::format_number(get_num(elem, name, 0.0), &WriteOptions::default());
+            attr_len(name, &n)
+        })
+        .sum();
+    (attr_len("d", &d) < shape_len).then_some(d)
+}
+
+// Two corners with no edge between them are one half-ellipse arc
+fn merge_corners(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut out: Vec<Segment> = Vec::with_capacity(segments.len());
+    let mut merged_last = false;
+    for segment in segments {
+        if let (Some(Segment::ArcTo { x, y, .. }), Segment::ArcTo { x: nx, y: ny, .. }) =
+            (out.last_mut(), &segment)
+        {
+            if !merged_last {
+                (*x, *y) = (*nx, *ny);
+                merged_last = true;
+                continue;
+            }
+        }
+        merged_last = false;
+        out.push(segment);
     }
+    out
+}
+
+// The corner radii, `Some(None)` for square corners and `None` when they
+// aren't plain numbers (`em`, `%`, ...) and can't be worked out here. A
+// missing or `auto` radius takes the other one's value, and both are
+// clamped to half the size.
+fn corner_radii(elem: &Element, w: f64, h: f64) -> Option<Option<(f64, f64)>> {
+    let radius = |name: &str| -> Option<Option<f64>> {
+        match elem.attributes.get(name).map(|v| v.trim()) {
+            None | Some("auto") => Some(None),
+            Some(v) => {
+                let r = v.strip_suffix("px").unwrap_or(v).parse::<f64>().ok()?;
+                Some((r >= 0.0).then_some(r))
+            }
+        }
+    };
+    let (rx, ry) = match (radius("rx")?, radius("ry")?) {
+        (Some(rx), Some(ry)) => (rx, ry),
+        (Some(r), None) | (None, Some(r)) => (r, r),
+        (None, None) => return Some(None),
+    };
+    let (rx, ry) = (rx.min(w / 2.0), ry.min(h / 2.0));
+    Some((rx > 0.0 && ry > 0.0).then_some((rx, ry)))
+}
 
-    // Rounded rects are complex to implement correctly with arcs.
-    // For now, let's skip complex rounded rects or implement basic.
-    None
+// Length of ` name="value"` in the output
+fn attr_len(name: &str, value: &str) -> usize {
+    name.len() + value.len() + 4
 }
 
 fn convert_circle(elem: &Element) -> Option<String> {
@@ -86,21 +190,7 @@ fn convert_circle(elem: &Element) -> Option<String> {
     let cx = get_num(elem, "cx", 0.0);
     let cy = get_num(elem, "cy", 0.0);
 
-    // Two Arcs
-    // M cx-r cy A r r 0 1 0 cx+r cy A r r 0 1 0 cx-r cy
-    Some(format!(
-        "M{} {}A{} {} 0 1 0 {} {}A{} {} 0 1 0 {} {}z",
-        cx - r,
-        cy,
-        r,
-        r,
-        cx + r,
-        cy,
-        r,
-        r,
-        cx - r,
-        cy
-    ))
+    Some(ellipse_path(cx, cy, r, r))
 }
 
 fn convert_ellipse(elem: &Element) -> Option<String> {
@@ -112,19 +202,29 @@ fn convert_ellipse(elem: &Element) -> Option<String> {
     let cx = get_num(elem, "cx", 0.0);
     let cy = get_num(elem, "cy", 0.0);
 
-    Some(format!(
-        "M{} {}A{} {} 0 1 0 {} {}A{} {} 0 1 0 {} {}z",
-        cx - rx,
-        cy,
-        rx,
-        ry,
-        cx + rx,
-        cy,
+    Some(ellipse_path(cx, cy, rx, ry))
+}
+
+// Two arcs: M cx-rx cy A rx ry 0 1 0 cx+rx cy A rx ry 0 1 0 cx-rx cy z
+fn ellipse_path(cx: f64, cy: f64, rx: f64, ry: f64) -> String {
+    let arc = |x| Segment::ArcTo {
         rx,
         ry,
-        cx - rx,
-        cy
-    ))
+        rotation: 0.0,
+        large_arc: true,
+        sweep: false,
+        x,
+        y: cy,
+    };
+    write_path(
+        vec![
+            Segment::MoveTo { x: cx - rx, y: cy },
+            arc(cx + rx),
+            arc(cx - rx),
+            Segment::Close,
+        ],
+        CoordMode::Absolute,
+    )
 }
 
 fn convert_line(elem: &Element) -> Option<String> {
@@ -133,51 +233,41 @@ fn convert_line(elem: &Element) -> Option<String> {
     let x2 = get_num(elem, "x2", 0.0);
     let y2 = get_num(elem, "y2", 0.0);
 
-    Some(format!("M{} {}L{} {}", x1, y1, x2, y2))
+    Some(write_path(
+        vec![
+            Segment::MoveTo { x: x1, y: y1 },
+            Segment::LineTo { x: x2, y: y2 },
+        ],
+        CoordMode::Absolute,
+    ))
 }
 
 fn convert_poly(elem: &Element, close: bool) -> Option<String> {
     let points = elem.attributes.get("points")?;
-    let points_clean = points.replace(',', " ");
-    let coords: Vec<&str> = points_clean.split_whitespace().collect();
+    let coords: Vec<f64> = points
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map_while(|s| s.parse().ok())
+        .collect();
 
     if coords.len() < 2 {
         return None;
     }
 
-    let mut d = String::from("M");
-    // This simple logic expects pairs.
-    // real parser would be better but simple replace works for standard inputs
-
-    // Actually, simple split might assume x y x y
-    // We construct "M x1 y1 L x2 y2 L x3 y3"
-
-    // Check if even?
-    // Let's just append.
-    // M x1 y1 L x2 y2 ...
-
-    // First pair
-    if coords.len() >= 2 {
-        d.push_str(coords[0]);
-        d.push(' ');
-        d.push_str(coords[1]);
-
-        // Rest
-        for i in (2..coords.len()).step_by(2) {
-            if i + 1 < coords.len() {
-                d.push_str("L");
-                d.push_str(coords[i]);
-                d.push(' ');
-                d.push_str(coords[i + 1]);
-            }
-        }
-    }
-
+    // M x1 y1 L x2 y2 L x3 y3 ..., ignoring an odd trailing coordinate
+    let mut segments: Vec<Segment> = coords
+        .chunks_exact(2)
+        .map(|p| Segment::LineTo { x: p[0], y: p[1] })
+        .collect();
+    segments[0] = Segment::MoveTo {
+        x: coords[0],
+        y: coords[1],
+    };
     if close {
-        d.push('z');
+        segments.push(Segment::Close);
     }
 
-    Some(d)
+    Some(write_path(segments, CoordMode::Absolute))
 }
 
 fn remove_shape_attrs(elem: &mut Element) {
@@ -238,4 +328,27 @@ mod tests {
         assert!(out.contains("path"));
         assert!(out.contains("d=\"M0 0L100 0L100 100z"));
     }
+
+    #[test]
+    fn test_rounded_rect_path() {
+        // ry follows rx and is clamped to half the height
+        let elem = parser::parse("<rect x=\"10\" y=\"20\" width=\"100\" height=\"50\" rx=\"30\"/>")
+            .unwrap();
+        let Some(Node::Element(rect)) = elem.root.first() else {
+            panic!("no rect");
+        };
+        let (rx, ry) = corner_radii(rect, 100.0, 50.0).unwrap().unwrap();
+        assert_eq!((rx, ry), (30.0, 25.0));
+    }
+
+    #[test]
+    fn test_rounded_rect_to_path() {
+        // Rounded all the way, the corners meet and it's shorter as a path
+        let input = "<svg><rect x=\"5\" y=\"5\" width=\"100\" height=\"20\" rx=\"50\" ry=\"10\"/><rect width=\"10\" height=\"10\" rx=\"2\"/><rect width=\"10\" height=\"10\" rx=\"1em\"/><rect width=\"10\" height=\"10\" rx=\"10%\" ry=\"auto\"/></svg>";
+        let expected = "<svg><path d=\"M55 5a50 10 0 0 1 0 20a50 10 0 0 1 0 -20z\"/><rect width=\"10\" height=\"10\" rx=\"2\"/><rect width=\"10\" height=\"10\" rx=\"1em\"/><rect width=\"10\" height=\"10\" rx=\"10%\" ry=\"auto\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertShapeToPath.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
@@ -1,53 +1,134 @@
+use crate::css::{self, Selector};
+use crate::plugins::collections::PRESENTATION_ATTRS;
 use crate::plugins::Plugin;
-use crate::tree::{Document, Node};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashSet;
 
 pub struct ConvertStyleToAttrs;
 
 impl Plugin for ConvertStyleToAttrs {
     fn apply(&self, doc: &mut Document) {
-        process_style(&mut doc.root);
+        // Phase 1: properties that <style> rules may set on each styled element.
+        // A presentation attribute loses to any of them, while the style attribute wins.
+        let Some(sheet_rules) = collect_sheet_rules(&doc.root) else {
+            return; // Unparseable stylesheet: can't tell what would be overridden
+        };
+        let mut blocked = Vec::new();
+        collect_blocked_props(&doc.root, &sheet_rules, &mut Vec::new(), &mut blocked);
+
+        // Phase 2: convert, visiting styled elements in the same order
+        let mut blocked = blocked.into_iter();
+        process_style(&mut doc.root, &mut blocked);
+    }
+}
+
+struct SheetRule {
+    // `None` for selectors we can't evaluate, which are assumed to match
+    selector: Option<Selector>,
+    props: Vec<String>,
+}
+
+fn collect_sheet_rules(nodes: &[Node]) -> Option<Vec<SheetRule>> {
+    let mut rules = Vec::new();
+    let mut stack = vec![nodes];
+    while let Some(nodes) = stack.pop() {
+        for node in nodes {
+            if let Node::Element(elem) = node {
+                if let Some(text) = css::style_text(elem) {
+                    let sheet = css::parse_stylesheet(&text)?;
+                    for (rule, _) in sheet.rules() {
+                        let props: Vec<String> =
+                            rule.declarations.iter().map(|d| d.name.clone()).collect();
+                        for selector in &rule.selectors {
+                            rules.push(SheetRule {
+                                selector: Selector::parse(selector),
+                                props: props.clone(),
+                            });
+                        }
+                    }
+                }
+                stack.push(&elem.children);
+            }
+        }
     }
+    Some(rules)
 }
 
-fn process_style(nodes: &mut Vec<Node>) {
+fn collect_blocked_props<'a>(
+    nodes: &'a [Node],
+    rules: &[SheetRule],
+    ancestors: &mut Vec<&'a Element>,
+    out: &mut Vec<HashSet<String>>,
+) {
     for node in nodes {
         if let Node::Element(elem) = node {
-            if let Some(style_val) = elem.attributes.shift_remove("style") {
-                // Parse "key: value; key2: value2"
-                let props = parse_style(&style_val);
-                for (k, v) in props {
-                    // Only overwrite if not present? Or always?
-                    // Style takes precedence over attributes. So we should overwrite.
-                    elem.attributes.insert(k, v);
+            if elem.attributes.contains_key("style") {
+                let mut props = HashSet::new();
+                for rule in rules {
+                    let applies = rule
+                        .selector
+                        .as_ref()
+                        .is_none_or(|s| s.matches(elem, ancestors));
+                    if applies {
+                        props.extend(rule.props.iter().cloned());
+                    }
                 }
-                // style is removed (shifted out)
+                out.push(props);
             }
-            process_style(&mut elem.children);
+            ancestors.push(elem);
+            collect_blocked_props(&elem.children, rules, ancestors, out);
+            ancestors.pop();
         }
     }
 }
 
-fn parse_style(s: &str) -> Vec<(String, String)> {
-    let mut props = Vec::new();
-    // Simple split by ; then :
-    // Does not handle quoted strings containing ; or :.
-    // SVG style usually simple?
+fn process_style(nodes: &mut Vec<Node>, blocked: &mut std::vec::IntoIter<HashSet<String>>) {
+    for node in nodes {
+        if let Node::Element(elem) = node {
+            if let Some(style_val) = elem.attributes.get("style") {
+                let mut blocked_props = blocked.next().unwrap_or_default();
+                let decls = css::parse_declarations(style_val);
+                // A declaration that stays in `style` beats any attribute, so
+                // every other declaration of its property has to stay too
+                blocked_props.extend(
+                    decls
+                        .iter()
+                        .filter(|d| !is_convertible(d))
+                        .map(|d| d.name.clone()),
+                );
+                let mut kept = Vec::new();
 
-    for pair in s.split(';') {
-        let pair = pair.trim();
-        if pair.is_empty() {
-            continue;
-        }
+                for decl in decls {
+                    if is_convertible(&decl) && !blocked_props.contains(&decl.name) {
+                        // Style takes precedence over attributes, so overwrite.
+                        elem.attributes.insert(decl.name, decl.value);
+                    } else {
+                        kept.push(decl);
+                    }
+                }
 
-        if let Some((k, v)) = pair.split_once(':') {
-            let key = k.trim().to_string();
-            let value = v.trim().to_string();
-            if !key.is_empty() && !value.is_empty() {
-                props.push((key, value));
+                if kept.is_empty() {
+                    elem.attributes.shift_remove("style");
+                } else {
+                    elem.attributes
+                        .insert("style".to_string(), css::stringify_declarations(&kept));
+                }
             }
+            process_style(&mut elem.children, blocked);
         }
     }
-    props
+}
+
+fn is_convertible(decl: &css::Declaration) -> bool {
+    // !important beats stylesheet rules, an attribute never does
+    if decl.important || !PRESENTATION_ATTRS.contains(&decl.name.as_str()) {
+        return false;
+    }
+    // CSS-only functions aren't understood in attribute values
+    let value = decl.value.to_ascii_lowercase();
+    !["var(", "calc(", "env(", "attr("]
+        .iter()
+        .any(|f| value.contains(f))
 }
 
 #[cfg(test)]
@@ -75,4 +156,35 @@ mod tests {
         assert!(out.contains("fill=\"red\""));
         assert!(out.contains("stroke=\"blue\""));
     }
+
+    #[test]
+    fn test_keep_non_presentation_props() {
+        let input = "<svg><rect style=\"transform-origin: center; fill: url('data:a;b') ; mix-blend-mode: multiply; --c: red; stroke: var(--c); opacity: .5 !important\"/></svg>";
+        let expected = "<svg><rect style=\"transform-origin:center;mix-blend-mode:multiply;--c:red;stroke:var(--c);opacity:.5!important\" fill=\"url('data:a;b')\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertStyleToAttrs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_overridden_declarations() {
+        // `stroke: blue` as an attribute would lose to the kept `var()`
+        let input = "<svg><rect style=\"stroke: var(--c); stroke: blue; fill: red\"/></svg>";
+        let expected = "<svg><rect style=\"stroke:var(--c);stroke:blue\" fill=\"red\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertStyleToAttrs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_respect_stylesheet_cascade() {
+        let input = "<svg><style>g > .a:hover { fill: blue }</style><g><rect class=\"a\" style=\"fill: red; stroke: red\"/></g><rect style=\"fill: red\"/></svg>";
+        let expected = "<svg><style>g > .a:hover { fill: blue }</style><g><rect class=\"a\" style=\"fill:red\" stroke=\"red\"/></g><rect fill=\"red\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertStyleToAttrs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
@@ -1,10 +1,17 @@
+use crate::path::Point;
 use crate::plugins::Plugin;
-use crate::tree::{Document, Node};
-use std::f64::consts::PI;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::style::{self, ComputedStyle, Origin, StyledNode};
+use crate::transform::{self, is_approx, Matrix};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashSet;
 
 pub struct ConvertTransform {
     pub float_precision: usize,
     pub deg_precision: usize,
+    /// Derive the precision of each `transform` from its rendered size
+    /// instead
+    pub error_budget: Option<ErrorBudget>,
 }
 
 impl Default for ConvertTransform {
@@ -12,74 +19,243 @@ impl Default for ConvertTransform {
         Self {
             float_precision: 3,
             deg_precision: 3,
+            error_budget: None,
         }
     }
 }
 
+const TRANSFORM_ATTRS: &[&str] = &["transform", "gradientTransform", "patternTransform"];
+
 impl Plugin for ConvertTransform {
     fn apply(&self, doc: &mut Document) {
-        process_transforms(&mut doc.root, self);
+        let styles = style::resolve(doc);
+        let mut inherited = HashSet::new();
+        collect_inherited_gradients(&doc.root, &mut inherited);
+        let errors = self.error_budget.map(|b| b.resolve(doc, &styles));
+        let ctx = Context {
+            inherited: &inherited,
+            relative: self.error_budget.and_then(|b| b.relative(doc)),
+            opts: self,
+        };
+        let root_error = self.error_budget.and_then(|b| b.root_error(doc));
+        process_transforms(&mut doc.root, &styles, errors.as_deref(), root_error, &ctx);
+    }
+}
+
+struct Context<'a> {
+    inherited: &'a HashSet<String>,
+    // The error budget as a fraction of the viewBox
+    relative: Option<f64>,
+    opts: &'a ConvertTransform,
+}
+
+// How many decimals each part of a transform gets
+#[derive(Debug, Clone, Copy)]
+struct Rounding {
+    // Translations, in the parent's user units
+    translate: usize,
+    // Scale, skew and matrix factors
+    linear: usize,
+    deg: usize,
+}
+
+impl Rounding {
+    fn fixed(opts: &ConvertTransform) -> Self {
+        Self {
+            translate: opts.float_precision,
+            linear: opts.float_precision,
+            deg: opts.deg_precision,
+        }
+    }
+
+    // Translations may move content by the parent's error. Changing a
+    // factor or angle by the relative error moves content as large as the
+    // viewBox by about as much.
+    fn budgeted(parent_error: f64, relative: f64) -> Self {
+        Self {
+            translate: precision::decimals(parent_error),
+            linear: precision::decimals(relative),
+            deg: precision::decimals(relative.to_degrees()),
+        }
     }
 }
 
-fn process_transforms(nodes: &mut Vec<Node>, opts: &ConvertTransform) {
+fn process_transforms(
+    nodes: &mut [Node],
+    styles: &[StyledNode],
+    errors: Option<&[ErrorNode]>,
+    parent_error: Option<f64>,
+    ctx: &Context,
+) {
+    let opts = ctx.opts;
+    for (i, (node, styled)) in nodes.iter_mut().zip(styles).enumerate() {
+        let error = errors.and_then(|e| e.get(i));
+        if let Node::Element(elem) = node {
+            let paint_server = matches!(
+                elem.name.as_str(),
+                "linearGradient" | "radialGradient" | "pattern"
+            );
+            if !paint_server {
+                fold_origin(elem, &styled.style);
+            }
+            for name in TRANSFORM_ATTRS {
+                // Paint server transforms apply in units we don't know here
+                let rounding = match (parent_error, ctx.relative) {
+                    (Some(e), Some(r)) if *name == "transform" => Rounding::budgeted(e, r),
+                    _ => Rounding::fixed(opts),
+                };
+                if let Some(t) = elem.attributes.get_mut(*name) {
+                    let new_t = optimize_transform(t, &rounding);
+                    if new_t.is_empty() {
+                        elem.attributes.shift_remove(*name); // Remove if identity/empty
+                    } else {
+                        *t = new_t;
+                    }
+                }
+            }
+            // Other gradients would inherit the coordinates without the
+            // transform, and browsers disagree on origins for gradients
+            let shared = elem
+                .attributes
+                .get("id")
+                .is_some_and(|id| ctx.inherited.contains(id));
+            if paint_server
+                && !shared
+                && !["href", "xlink:href", "transform-origin"]
+                    .iter()
+                    .any(|a| elem.attributes.contains_key(*a))
+            {
+                fold_gradient_transform(elem, opts);
+            }
+            process_transforms(
+                &mut elem.children,
+                &styled.children,
+                error.map(|e| e.children.as_slice()),
+                error.and_then(|e| e.error),
+                ctx,
+            );
+        }
+    }
+}
+
+// IDs of gradients and patterns that others inherit from through `href`
+fn collect_inherited_gradients(nodes: &[Node], out: &mut HashSet<String>) {
     for node in nodes {
         if let Node::Element(elem) = node {
-            if let Some(t) = elem.attributes.get_mut("transform") {
-                let new_t = optimize_transform(t, opts);
-                if new_t.is_empty() {
-                    elem.attributes.shift_remove("transform"); // Remove if identity/empty
-                } else {
-                    *t = new_t;
+            if matches!(
+                elem.name.as_str(),
+                "linearGradient" | "radialGradient" | "pattern"
+            ) {
+                for attr in ["href", "xlink:href"] {
+                    if let Some(id) = elem.attributes.get(attr).and_then(|v| v.strip_prefix('#')) {
+                        out.insert(id.to_string());
+                    }
                 }
             }
-            process_transforms(&mut elem.children, opts);
+            collect_inherited_gradients(&elem.children, out);
         }
     }
 }
 
-// 2D Affine Matrix
-// [ a c e ]
-// [ b d f ]
-// [ 0 0 1 ]
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Matrix {
-    a: f64,
-    b: f64,
-    c: f64,
-    d: f64,
-    e: f64,
-    f: f64,
+// A `transform-origin` in user units becomes part of the transform itself
+fn fold_origin(elem: &mut Element, style: &ComputedStyle) {
+    if style.origin("transform-origin") != Origin::Attribute
+        || style.origin("transform") != Origin::Attribute
+    {
+        return;
+    }
+    let Some(m) = transform::element_transform(elem, style) else {
+        return;
+    };
+    // Written exactly, it's optimized right after
+    elem.attributes.insert(
+        "transform".to_string(),
+        format!("matrix({} {} {} {} {} {})", m.a, m.b, m.c, m.d, m.e, m.f),
+    );
+    elem.attributes.shift_remove("transform-origin");
 }
 
-impl Matrix {
-    fn identity() -> Self {
-        Self {
-            a: 1.0,
-            b: 0.0,
-            c: 0.0,
-            d: 1.0,
-            e: 0.0,
-            f: 0.0,
+// A gradient transform that keeps circles round can move into the
+// gradient's own coordinates
+fn fold_gradient_transform(elem: &mut Element, opts: &ConvertTransform) {
+    let Some(t) = elem.attributes.get("gradientTransform") else {
+        return;
+    };
+    let m = transform::parse(t);
+    let uniform = is_approx(m.a * m.a + m.b * m.b, m.c * m.c + m.d * m.d)
+        && is_approx(m.a * m.c + m.b * m.d, 0.0);
+    if !uniform {
+        return;
+    }
+    let Some(changes) = gradient_coords(elem, &m) else {
+        return;
+    };
+    for (name, value, default) in changes {
+        let value = format_num(value, opts.float_precision);
+        if elem.attributes.contains_key(name) || value != default {
+            elem.attributes.insert(name.to_string(), value);
         }
     }
+    elem.attributes.shift_remove("gradientTransform");
+}
 
-    fn multiply(&self, other: &Matrix) -> Matrix {
-        // Self * Other (Order depends on pre/post mult convention. SVG is post-mult: P' = M * P)
-        Matrix {
-            a: self.a * other.a + self.c * other.b,
-            b: self.b * other.a + self.d * other.b,
-            c: self.a * other.c + self.c * other.d,
-            d: self.b * other.c + self.d * other.d,
-            e: self.a * other.e + self.c * other.f + self.e,
-            f: self.b * other.e + self.d * other.f + self.f,
+// The gradient's coordinates mapped through `m`, with the value each
+// defaults to
+fn gradient_coords(elem: &Element, m: &Matrix) -> Option<Vec<(&'static str, f64, &'static str)>> {
+    let bbox_units = elem
+        .attributes
+        .get("gradientUnits")
+        .is_none_or(|u| u != "userSpaceOnUse");
+    // Defaults are percentages, which only mean something for bbox units
+    let get = |name: &str, default: f64| match elem.attributes.get(name) {
+        Some(v) => parse_coord(v, bbox_units),
+        None if bbox_units => Some(default),
+        None => None,
+    };
+    let scale = (m.a * m.d - m.b * m.c).abs().sqrt();
+
+    let mut out = Vec::new();
+    match elem.name.as_str() {
+        "linearGradient" => {
+            for (x, y, dx) in [("x1", "y1", 0.0), ("x2", "y2", 1.0)] {
+                let p = m.apply(Point::new(get(x, dx)?, get(y, 0.0)?));
+                out.push((x, p.x, if dx == 0.0 { "0" } else { "1" }));
+                out.push((y, p.y, "0"));
+            }
+        }
+        "radialGradient" => {
+            let (cx, cy) = (get("cx", 0.5)?, get("cy", 0.5)?);
+            let c = m.apply(Point::new(cx, cy));
+            out.push(("cx", c.x, ".5"));
+            out.push(("cy", c.y, ".5"));
+            out.push(("r", get("r", 0.5)? * scale, ".5"));
+            // The focus follows the center unless it's set
+            if elem.attributes.contains_key("fx") || elem.attributes.contains_key("fy") {
+                let f = m.apply(Point::new(get("fx", cx)?, get("fy", cy)?));
+                out.push(("fx", f.x, ""));
+                out.push(("fy", f.y, ""));
+            }
+            if elem.attributes.contains_key("fr") {
+                out.push(("fr", get("fr", 0.0)? * scale, ""));
+            }
         }
+        _ => return None,
     }
+    Some(out)
 }
 
-fn optimize_transform(transform_str: &str, opts: &ConvertTransform) -> String {
+fn parse_coord(v: &str, bbox_units: bool) -> Option<f64> {
+    let v = v.trim();
+    match v.strip_suffix('%') {
+        Some(pct) if bbox_units => pct.parse::<f64>().ok().map(|n| n / 100.0),
+        Some(_) => None,
+        None => v.trim_end_matches("px").parse().ok(),
+    }
+}
+
+fn optimize_transform(transform_str: &str, rounding: &Rounding) -> String {
     // 1. Parse into list of matrices
-    let matrices = parse_transform(transform_str);
+    let matrices = transform::parse_list(transform_str);
     if matrices.is_empty() {
         return String::new();
     }
@@ -92,63 +268,122 @@ fn optimize_transform(transform_str: &str, opts: &ConvertTransform) -> String {
 
     // 3. Decompose / Stringify
     // Check if identity
-    if is_identity(&combined) {
+    if combined.is_identity() {
         return String::new();
     }
 
-    // Check for simple cases to stringify compactly
-    // 1. Translate only (a=1, d=1, b=0, c=0)
-    if is_approx(combined.a, 1.0)
-        && is_approx(combined.d, 1.0)
-        && is_approx(combined.b, 0.0)
-        && is_approx(combined.c, 0.0)
-    {
-        return format!(
-            "translate({} {})",
-            format_num(combined.e, opts.float_precision),
-            format_num(combined.f, opts.float_precision)
-        );
+    // Whichever form is shortest once rounded, as long as it still
+    // describes the same transform
+    candidates(&combined, rounding)
+        .into_iter()
+        .filter(|c| matches_within(&transform::parse(c), &combined, rounding))
+        .min_by_key(|c| c.len())
+        .unwrap_or_else(|| format_matrix(&combined, rounding))
+}
+
+// Decompositions of `m` into named functions, simplest first; `matrix(...)`
+// is the fallback
+fn candidates(m: &Matrix, rounding: &Rounding) -> Vec<String> {
+    let num = |n: f64| format_num(n, rounding.translate);
+    let factor = |n: f64| format_num(n, rounding.linear);
+    let deg = |n: f64| format_num(n, rounding.deg);
+    let mut out = Vec::new();
+
+    let translate = if num(m.e) == "0" && num(m.f) == "0" {
+        None
+    } else if num(m.f) == "0" {
+        Some(format!("translate({})", num(m.e)))
+    } else {
+        Some(format!("translate({} {})", num(m.e), num(m.f)))
+    };
+    let scale = |sx: f64, sy: f64| {
+        if factor(sx) == factor(sy) {
+            format!("scale({})", factor(sx))
+        } else {
+            format!("scale({} {})", factor(sx), factor(sy))
+        }
+    };
+
+    // rotate(θ) scale(sx sy) skewX(φ)
+    let sx = m.a.hypot(m.b);
+    if sx > 1e-10 {
+        let angle = m.b.atan2(m.a);
+        let (sin, cos) = angle.sin_cos();
+        let sy = (m.a * m.d - m.b * m.c) / sx;
+        let skew = ((cos * m.c + sin * m.d) / sx).atan();
+
+        let mut rest = Vec::new();
+        if !is_approx(sx, 1.0) || !is_approx(sy, 1.0) {
+            rest.push(scale(sx, sy));
+        }
+        if !is_approx(skew, 0.0) {
+            rest.push(format!("skewX({})", deg(skew.to_degrees())));
+        }
+
+        let mut parts: Vec<String> = translate.iter().cloned().collect();
+        if !is_approx(angle, 0.0) {
+            parts.push(format!("rotate({})", deg(angle.to_degrees())));
+        }
+        parts.extend(rest.iter().cloned());
+        out.push(parts.join(" "));
+
+        // The translation folded into the rotation's center
+        if !is_approx(angle, 0.0) && translate.is_some() {
+            let det = 2.0 - 2.0 * cos;
+            let cx = ((1.0 - cos) * m.e - sin * m.f) / det;
+            let cy = (sin * m.e + (1.0 - cos) * m.f) / det;
+            let mut parts = vec![format!(
+                "rotate({} {} {})",
+                deg(angle.to_degrees()),
+                num(cx),
+                num(cy)
+            )];
+            parts.extend(rest);
+            out.push(parts.join(" "));
+        }
     }
 
-    // 2. Scale only (b=0, c=0, e=0, f=0)
-    if is_approx(combined.b, 0.0)
-        && is_approx(combined.c, 0.0)
-        && is_approx(combined.e, 0.0)
-        && is_approx(combined.f, 0.0)
-    {
-        if (combined.a - combined.d).abs() < f64::EPSILON {
-            return format!("scale({})", format_num(combined.a, opts.float_precision));
+    // skewY(φ) scale(sx sy)
+    if is_approx(m.c, 0.0) && !is_approx(m.b, 0.0) && m.a.abs() > 1e-10 {
+        let mut parts: Vec<String> = translate.iter().cloned().collect();
+        parts.push(format!("skewY({})", deg((m.b / m.a).atan().to_degrees())));
+        if !is_approx(m.a, 1.0) || !is_approx(m.d, 1.0) {
+            parts.push(scale(m.a, m.d));
         }
-        return format!(
-            "scale({} {})",
-            format_num(combined.a, opts.float_precision),
-            format_num(combined.d, opts.float_precision)
-        );
+        out.push(parts.join(" "));
     }
 
-    // Default to matrix(...)
+    out.push(format_matrix(m, rounding));
+    out
+}
+
+fn format_matrix(m: &Matrix, rounding: &Rounding) -> String {
+    let (p, t) = (rounding.linear, rounding.translate);
     format!(
         "matrix({} {} {} {} {} {})",
-        format_num(combined.a, opts.float_precision),
-        format_num(combined.b, opts.float_precision),
-        format_num(combined.c, opts.float_precision),
-        format_num(combined.d, opts.float_precision),
-        format_num(combined.e, opts.float_precision),
-        format_num(combined.f, opts.float_precision)
+        format_num(m.a, p),
+        format_num(m.b, p),
+        format_num(m.c, p),
+        format_num(m.d, p),
+        format_num(m.e, t),
+        format_num(m.f, t)
     )
 }
 
-fn is_approx(a: f64, b: f64) -> bool {
-    (a - b).abs() < 1e-10
-}
-
-fn is_identity(m: &Matrix) -> bool {
-    is_approx(m.a, 1.0)
-        && is_approx(m.b, 0.0)
-        && is_approx(m.c, 0.0)
-        && is_approx(m.d, 1.0)
-        && is_approx(m.e, 0.0)
-        && is_approx(m.f, 0.0)
+// Rounding may move each component by about one unit of the last digit
+fn matches_within(candidate: &Matrix, m: &Matrix, rounding: &Rounding) -> bool {
+    let unit = |p: usize| 1.0 / 10u32.pow(p as u32) as f64;
+    let (linear, translate) = (unit(rounding.linear), unit(rounding.translate));
+    [
+        (candidate.a, m.a, linear),
+        (candidate.b, m.b, linear),
+        (candidate.c, m.c, linear),
+        (candidate.d, m.d, linear),
+        (candidate.e, m.e, translate),
+        (candidate.f, m.f, translate),
+    ]
+    .iter()
+    .all(|(x, y, tolerance)| (x - y).abs() <= *tolerance)
 }
 
 fn format_num(n: f64, p: usize) -> String {
@@ -164,204 +399,104 @@ fn format_num(n: f64, p: usize) -> String {
     }
 }
 
-fn parse_transform(s: &str) -> Vec<Matrix> {
-    parse_transform_manual(s)
-}
-
-fn parse_transform_manual(s: &str) -> Vec<Matrix> {
-    let mut matrices = Vec::new();
-    let mut chars = s.char_indices().peekable();
-
-    while let Some((_, c)) = chars.next() {
-        if c.is_ascii_alphabetic() {
-            // Read name
-            let mut name = String::new();
-            name.push(c);
-            while let Some((_, nc)) = chars.peek() {
-                if nc.is_ascii_alphabetic() {
-                    name.push(*nc);
-                    chars.next();
-                } else {
-                    break;
-                }
-            }
-
-            // Skip to (
-            while let Some((_, nc)) = chars.peek() {
-                if *nc == '(' {
-                    chars.next();
-                    break;
-                }
-                chars.next();
-            }
-
-            // Read args
-            let mut args = Vec::new();
-            let mut cur_num = String::new();
-            while let Some((_, nc)) = chars.peek() {
-                if *nc == ')' {
-                    chars.next();
-                    break;
-                }
-                let cc = *nc;
-                if cc.is_numeric() || cc == '.' || cc == '-' || cc == 'e' || cc == 'E' {
-                    cur_num.push(cc);
-                    chars.next();
-                } else {
-                    if !cur_num.is_empty() {
-                        if let Ok(n) = cur_num.parse::<f64>() {
-                            args.push(n);
-                        }
-                        cur_num.clear();
-                    }
-                    chars.next(); // Skip separator
-                }
-            }
-            if !cur_num.is_empty() {
-                if let Ok(n) = cur_num.parse::<f64>() {
-                    args.push(n);
-                }
-            }
-
-            match name.as_str() {
-                "translate" => {
-                    let tx = *args.get(0).unwrap_or(&0.0);
-                    let ty = *args.get(1).unwrap_or(&0.0);
-                    matrices.push(Matrix {
-                        a: 1.0,
-                        b: 0.0,
-                        c: 0.0,
-                        d: 1.0,
-                        e: tx,
-                        f: ty,
-                    });
-                }
-                "scale" => {
-                    let sx = *args.get(0).unwrap_or(&1.0);
-                    let sy = *args.get(1).unwrap_or(&sx); // if 1 arg, scale(s, s)
-                    matrices.push(Matrix {
-                        a: sx,
-                        b: 0.0,
-                        c: 0.0,
-                        d: sy,
-                        e: 0.0,
-                        f: 0.0,
-                    });
-                }
-                "rotate" => {
-                    let angle = *args.get(0).unwrap_or(&0.0);
-                    // cx, cy optional
-                    let cx = *args.get(1).unwrap_or(&0.0);
-                    let cy = *args.get(2).unwrap_or(&0.0);
-
-                    let rad = angle * PI / 180.0;
-                    let c = rad.cos();
-                    let s = rad.sin();
-
-                    // define rotate(a, cx, cy) as translate(cx, cy) rotate(a) translate(-cx, -cy)
-                    let mut m = Matrix::identity();
-                    if cx != 0.0 || cy != 0.0 {
-                        m = m.multiply(&Matrix {
-                            a: 1.0,
-                            b: 0.0,
-                            c: 0.0,
-                            d: 1.0,
-                            e: cx,
-                            f: cy,
-                        });
-                    }
-                    m = m.multiply(&Matrix {
-                        a: c,
-                        b: s,
-                        c: -s,
-                        d: c,
-                        e: 0.0,
-                        f: 0.0,
-                    });
-                    if cx != 0.0 || cy != 0.0 {
-                        m = m.multiply(&Matrix {
-                            a: 1.0,
-                            b: 0.0,
-                            c: 0.0,
-                            d: 1.0,
-                            e: -cx,
-                            f: -cy,
-                        });
-                    }
-                    matrices.push(m);
-                }
-                "skewX" => {
-                    let a = *args.get(0).unwrap_or(&0.0);
-                    let rad = a * PI / 180.0;
-                    matrices.push(Matrix {
-                        a: 1.0,
-                        b: 0.0,
-                        c: rad.tan(),
-                        d: 1.0,
-                        e: 0.0,
-                        f: 0.0,
-                    });
-                }
-                "skewY" => {
-                    let a = *args.get(0).unwrap_or(&0.0);
-                    let rad = a * PI / 180.0;
-                    matrices.push(Matrix {
-                        a: 1.0,
-                        b: rad.tan(),
-                        c: 0.0,
-                        d: 1.0,
-                        e: 0.0,
-                        f: 0.0,
-                    });
-                }
-                "matrix" => {
-                    if args.len() == 6 {
-                        matrices.push(Matrix {
-                            a: args[0],
-                            b: args[1],
-                            c: args[2],
-                            d: args[3],
-                            e: args[4],
-                            f: args[5],
-                        });
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-    matrices
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser;
+    use crate::printer;
 
     #[test]
     fn test_translate_merge() {
-        // translate(10) translate(20) -> translate(30 0)
+        // translate(10) translate(20) -> translate(30)
         let input = "translate(10) translate(20)";
-        let opts = ConvertTransform::default();
+        let opts = Rounding::fixed(&ConvertTransform::default());
         let out = optimize_transform(input, &opts);
 
-        // translate(30 0) vs translate(30).
-        assert!(out.contains("translate(30 0)"));
+        assert_eq!(out, "translate(30)");
     }
 
     #[test]
     fn test_scale_merge() {
         let input = "scale(2) scale(3)";
-        let opts = ConvertTransform::default();
+        let opts = Rounding::fixed(&ConvertTransform::default());
         let out = optimize_transform(input, &opts);
         // 2*3 = 6
         assert!(out.contains("scale(6)"));
     }
 
+    #[test]
+    fn test_rotate_around_center() {
+        let opts = Rounding::fixed(&ConvertTransform::default());
+        assert_eq!(
+            optimize_transform("rotate(45 12 12)", &opts),
+            "rotate(45 12 12)"
+        );
+        assert_eq!(
+            optimize_transform("translate(10 20) rotate(-90)", &opts),
+            "rotate(-90 15 5)"
+        );
+    }
+
+    #[test]
+    fn test_skew() {
+        let opts = Rounding::fixed(&ConvertTransform::default());
+        assert_eq!(optimize_transform("skewX(30)", &opts), "skewX(30)");
+        assert_eq!(
+            optimize_transform("skewY(30) scale(2)", &opts),
+            "skewY(30) scale(2)"
+        );
+        // Nothing shorter than the matrix itself
+        assert_eq!(
+            optimize_transform("matrix(1 2 3 4 5 6)", &opts),
+            "matrix(1 2 3 4 5 6)"
+        );
+    }
+
     #[test]
     fn test_identity() {
         let input = "translate(0) scale(1)";
-        let opts = ConvertTransform::default();
+        let opts = Rounding::fixed(&ConvertTransform::default());
         let out = optimize_transform(input, &opts);
         assert_eq!(out, "");
     }
+
+    #[test]
+    fn test_gradient_and_pattern_transforms() {
+        let input = "<svg><linearGradient id=\"a\" gradientTransform=\"translate(10 20) scale(2)\" x1=\"0\" y1=\"0\" x2=\"5\" y2=\"0\" gradientUnits=\"userSpaceOnUse\"/><radialGradient id=\"b\" gradientTransform=\"rotate(45 .5 .5)\"/><linearGradient id=\"c\" gradientTransform=\"matrix(2 0 0 1 0 0)\"/><pattern id=\"d\" patternTransform=\"matrix(2 0 0 2 0 0)\"/></svg>";
+        let expected = "<svg><linearGradient id=\"a\" x1=\"10\" y1=\"20\" x2=\"20\" y2=\"20\" gradientUnits=\"userSpaceOnUse\"/><radialGradient id=\"b\"/><linearGradient id=\"c\" gradientTransform=\"scale(2 1)\"/><pattern id=\"d\" patternTransform=\"scale(2)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertTransform::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_transform_origin() {
+        let input = "<svg><rect transform=\"rotate(90)\" transform-origin=\"10 10\"/><rect transform=\"scale(2)\" transform-origin=\"center\"/></svg>";
+        let expected = "<svg><rect transform=\"rotate(90 10 10)\"/><rect transform=\"scale(2)\" transform-origin=\"center\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertTransform::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_error_budget() {
+        // 0.1px at 24px on a 2400 unit map: translations are good to 10
+        // units, factors to 0.1/24
+        let input = "<svg viewBox=\"0 0 2400 2400\"><g transform=\"translate(10.46 20.2) scale(1.23456)\"/></svg>";
+        let expected =
+            "<svg viewBox=\"0 0 2400 2400\"><g transform=\"translate(10 20) scale(1.235)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ConvertTransform {
+            error_budget: Some(ErrorBudget {
+                error: 0.1,
+                size: Some(24.0),
+            }),
+            ..ConvertTransform::default()
+        }
+        .apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
@@ -0,0 +1,497 @@
+use crate::bbox::{BBoxes, Rect};
+use crate::css;
+use crate::path::{self, CoordMode, PathData, WriteOptions};
+use crate::plugins::remove_off_canvas_paths::{self, is_non_rendering};
+use crate::plugins::Plugin;
+use crate::transform::{self, Matrix};
+use crate::tree::{Document, Element, Node};
+
+/// Fits the root `viewBox` to the drawing's bounding box.
+///
+/// With a `target`, the content is instead scaled and centered onto that
+/// grid (e.g. `0 0 24 24`), baking the transform into the coordinates when
+/// possible.
+pub struct CropViewBox {
+    /// Space to leave around the content, in user units
+    pub padding: f64,
+    /// Grow the shorter side to make the viewBox square
+    pub square: bool,
+    /// `viewBox` to fit the content onto, as `[x, y, width, height]`
+    pub target: Option<[f64; 4]>,
+}
+
+impl Default for CropViewBox {
+    fn default() -> Self {
+        Self {
+            padding: 0.0,
+            square: false,
+            target: None,
+        }
+    }
+}
+
+// Enough to keep rounding well below a device pixel
+const PRECISION: usize = 6;
+
+// Properties that would need scaling along with the geometry
+const SCALED_PROPS: &[&str] = &[
+    "stroke-width",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "transform",
+    "transform-origin",
+    "transform-box",
+    "vector-effect",
+    "d",
+    "x",
+    "y",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "width",
+    "height",
+];
+
+impl Plugin for CropViewBox {
+    fn apply(&self, doc: &mut Document) {
+        let Some(root_index) = doc
+            .root
+            .iter()
+            .position(|node| matches!(node, Node::Element(e) if e.name == "svg"))
+        else {
+            return;
+        };
+        let Some(content) = content_bbox(doc, root_index) else {
+            return;
+        };
+        let rect = self.fit(content);
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+        let Some(Node::Element(root)) = doc.root.get_mut(root_index) else {
+            return;
+        };
+
+        match self.target {
+            None => {
+                resize_viewport(root, &rect);
+                root.attributes
+                    .insert("viewBox".to_string(), format_view_box(&rect));
+            }
+            Some([x, y, w, h]) if w > 0.0 && h > 0.0 => {
+                let target = Rect::new(x, y, w, h);
+                let s = (w / rect.width()).min(h / rect.height());
+                let m = Matrix::translate(
+                    x + (w - rect.width() * s) / 2.0 - rect.min_x * s,
+                    y + (h - rect.height() * s) / 2.0 - rect.min_y * s,
+                )
+                .multiply(&Matrix::scale(s, s));
+                if !m.is_identity() {
+                    if can_bake(root) {
+                        bake_root(root, &m, s);
+                    } else {
+                        wrap(root, &m);
+                    }
+                }
+                root.attributes
+                    .insert("viewBox".to_string(), format_view_box(&target));
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+impl CropViewBox {
+    fn fit(&self, content: Rect) -> Rect {
+        let mut rect = content.expand(self.padding);
+        if self.square {
+            let grow = (rect.width() - rect.height()) / 2.0;
+            if grow > 0.0 {
+                rect.min_y -= grow;
+                rect.max_y += grow;
+            } else {
+                rect.min_x += grow;
+                rect.max_x -= grow;
+            }
+        }
+        rect
+    }
+}
+
+// The union of everything the root draws, cut down to what its viewport
+// shows. `None` if any part is unknown.
+fn content_bbox(doc: &Document, root_index: usize) -> Option<Rect> {
+    let Some(Node::Element(root)) = doc.root.get(root_index) else {
+        return None;
+    };
+    if root.attributes.contains_key("transform") {
+        return None;
+    }
+    let bboxes = BBoxes::new(doc, true);
+    // What's outside the viewport is hidden unless the root lets it
+    // overflow. Without a known viewport size, at least the viewBox shows.
+    let canvas = match bboxes.style(&[root_index]) {
+        Some(style) if remove_off_canvas_paths::clips(style) => {
+            remove_off_canvas_paths::canvas(root)
+                .or_else(|| remove_off_canvas_paths::view_box(root))
+        }
+        _ => None,
+    };
+    let mut content: Option<Rect> = None;
+    for (i, child) in root.children.iter().enumerate() {
+        let Node::Element(child) = child else {
+            continue;
+        };
+        if is_non_rendering(&child.name) {
+            continue;
+        }
+        let rect = bboxes.element(&[root_index, i], &Matrix::identity())?;
+        let Some(rect) = canvas.map_or(Some(rect), |canvas| rect.intersection(&canvas)) else {
+            continue;
+        };
+        content = Some(match content {
+            Some(content) => content.union(&rect),
+            None => rect,
+        });
+    }
+    content
+}
+
+// Keeps the drawing's size on screen by resizing the viewport along with
+// the viewBox
+fn resize_viewport(root: &mut Element, rect: &Rect) {
+    let width = root.attributes.get("width").and_then(|v| parse_number(v));
+    let height = root.attributes.get("height").and_then(|v| parse_number(v));
+    let old = match root.attributes.get("viewBox") {
+        Some(view_box) => match Rect::from_view_box(view_box) {
+            Some(rect) => rect,
+            None => return,
+        },
+        None => match (width, height) {
+            (Some(w), Some(h)) => Rect::new(0.0, 0.0, w, h),
+            _ => return,
+        },
+    };
+    if let Some(w) = width {
+        let w = w * rect.width() / old.width();
+        root.attributes.insert("width".to_string(), format(w));
+    }
+    if let Some(h) = height {
+        let h = h * rect.height() / old.height();
+        root.attributes.insert("height".to_string(), format(h));
+    }
+}
+
+// Baking only covers plain shapes with plain numbers: anything referenced,
+// styled by a stylesheet or sized in units would need more than a matrix
+fn can_bake(root: &Element) -> bool {
+    can_bake_attrs(root)
+        && root.children.iter().all(|child| match child {
+            Node::Element(child) => can_bake_element(child),
+            _ => true,
+        })
+}
+
+fn can_bake_element(elem: &Element) -> bool {
+    match elem.name.as_str() {
+        "title" | "desc" | "metadata" => true,
+        "g" | "a" => {
+            can_bake_attrs(elem)
+                && elem.children.iter().all(|child| match child {
+                    Node::Element(child) => can_bake_element(child),
+                    _ => true,
+                })
+        }
+        "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {
+            let (xs, ys, sizes) = geometry(&elem.name);
+            can_bake_attrs(elem)
+                && !has_element_children(elem)
+                && xs
+                    .iter()
+                    .chain(ys)
+                    .chain(sizes)
+                    .all(|name| match elem.attributes.get(*name) {
+                        Some(v) => parse_number(v).is_some(),
+                        None => true,
+                    })
+                && elem
+                    .attributes
+                    .get("points")
+                    .is_none_or(|v| parse_numbers(v).is_some())
+        }
+        _ => false,
+    }
+}
+
+fn can_bake_attrs(elem: &Element) -> bool {
+    elem.attributes
+        .iter()
+        .all(|(name, value)| match name.as_str() {
+            "style" => css::parse_declarations(value).iter().all(|decl| {
+                !SCALED_PROPS.contains(&decl.name.as_str()) && !decl.value.contains("url(")
+            }),
+            "stroke-width" | "stroke-dashoffset" => parse_number(value).is_some(),
+            "stroke-dasharray" => value.trim() == "none" || parse_numbers(value).is_some(),
+            "vector-effect" | "transform-origin" | "transform-box" => false,
+            _ => !value.contains("url("),
+        })
+}
+
+fn has_element_children(elem: &Element) -> bool {
+    elem.children
+        .iter()
+        .any(|child| matches!(child, Node::Element(_)))
+}
+
+// The x coordinates, y coordinates and lengths of a basic shape
+fn geometry(
+    name: &str,
+) -> (
+    &'static [&'static str],
+    &'static [&'static str],
+    &'static [&'static str],
+) {
+    match name {
+        "rect" => (&["x"], &["y"], &["width", "height", "rx", "ry"]),
+        "circle" => (&["cx"], &["cy"], &["r"]),
+        "ellipse" => (&["cx"], &["cy"], &["rx", "ry"]),
+        "line" => (&["x1", "x2"], &["y1", "y2"], &[]),
+        _ => (&[], &[], &[]),
+    }
+}
+
+fn bake_root(root: &mut Element, m: &Matrix, s: f64) {
+    let uses_stroke = uses_stroke(root);
+    bake_attrs(root, m, s);
+    // Strokes were drawn at the initial width of 1
+    if uses_stroke && !root.attributes.contains_key("stroke-width") {
+        root.attributes
+            .insert("stroke-width".to_string(), format(s));
+    }
+    for child in &mut root.children {
+        if let Node::Element(child) = child {
+            bake_element(child, m, s);
+        }
+    }
+}
+
+fn bake_element(elem: &mut Element, m: &Matrix, s: f64) {
+    if matches!(elem.name.as_str(), "title" | "desc" | "metadata") {
+        return;
+    }
+    bake_attrs(elem, m, s);
+
+    let (xs, ys, sizes) = geometry(&elem.name);
+    // Missing coordinates default to 0, which moves too
+    let get = |elem: &Element, name: &str| {
+        elem.attributes
+            .get(name)
+            .and_then(|v| parse_number(v))
+            .unwrap_or(0.0)
+    };
+    for name in xs {
+        let x = m.a * get(elem, name) + m.e;
+        elem.attributes.insert(name.to_string(), format(x));
+    }
+    for name in ys {
+        let y = m.d * get(elem, name) + m.f;
+        elem.attributes.insert(name.to_string(), format(y));
+    }
+    for name in sizes {
+        if elem.attributes.contains_key(*name) {
+            let v = get(elem, name) * s;
+            elem.attributes.insert(name.to_string(), format(v));
+        }
+    }
+    if let Some(d) = elem.attributes.get_mut("d") {
+        *d = PathData::parse(d).transform(m).write(&WriteOptions {
+            precision: Some(PRECISION),
+            remove_leading_zero: false,
+            mode: CoordMode::Shortest,
+        });
+    }
+    if let Some(points) = elem.attributes.get_mut("points") {
+        let nums = parse_numbers(points).unwrap_or_default();
+        *points = nums
+            .chunks_exact(2)
+            .map(|xy| {
+                let p = m.apply(path::Point::new(xy[0], xy[1]));
+                format!("{},{}", format(p.x), format(p.y))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    for child in &mut elem.children {
+        if let Node::Element(child) = child {
+            bake_element(child, m, s);
+        }
+    }
+}
+
+// Stroke lengths scale with the content; a transform `t` on scaled content
+// becomes `m t m⁻¹`
+fn bake_attrs(elem: &mut Element, m: &Matrix, s: f64) {
+    for name in ["stroke-width", "stroke-dashoffset"] {
+        if let Some(v) = elem.attributes.get_mut(name) {
+            if let Some(n) = parse_number(v) {
+                *v = format(n * s);
+            }
+        }
+    }
+    if let Some(v) = elem.attributes.get_mut("stroke-dasharray") {
+        if let Some(nums) = parse_numbers(v) {
+            *v = nums
+                .iter()
+                .map(|n| format(n * s))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+    }
+    if let Some(v) = elem.attributes.get_mut("transform") {
+        if let Some(inverse) = m.inverse() {
+            let t = transform::parse(v);
+            *v = format_matrix(&m.multiply(&t).multiply(&inverse));
+        }
+    }
+}
+
+fn uses_stroke(elem: &Element) -> bool {
+    let own = elem.attributes.get("stroke").is_some_and(|v| v != "none")
+        || elem.attributes.get("style").is_some_and(|style| {
+            css::parse_declarations(style)
+                .iter()
+                .any(|decl| decl.name == "stroke" && decl.value != "none")
+        });
+    own || elem.children.iter().any(|child| match child {
+        Node::Element(child) => uses_stroke(child),
+        _ => false,
+    })
+}
+
+// Moves the drawn content into a `<g transform>`, leaving metadata and
+// definitions where they are
+fn wrap(root: &mut Element, m: &Matrix) {
+    let mut group = Element::new("g");
+    group
+        .attributes
+        .insert("transform".to_string(), format_matrix(m));
+    let mut kept = Vec::new();
+    for child in std::mem::take(&mut root.children) {
+        match &child {
+            Node::Element(elem) if is_non_rendering(&elem.name) || elem.name == "script" => {
+                kept.push(child)
+            }
+            _ => group.children.push(child),
+        }
+    }
+    kept.push(Node::Element(group));
+    root.children = kept;
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().trim_end_matches("px").parse().ok()
+}
+
+fn parse_numbers(s: &str) -> Option<Vec<f64>> {
+    s.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect()
+}
+
+fn format(n: f64) -> String {
+    path::format_number(
+        n,
+        &WriteOptions {
+            precision: Some(PRECISION),
+            ..Default::default()
+        },
+    )
+}
+
+fn format_view_box(rect: &Rect) -> String {
+    [rect.min_x, rect.min_y, rect.width(), rect.height()]
+        .iter()
+        .map(|n| format(*n))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_matrix(m: &Matrix) -> String {
+    format!(
+        "matrix({} {} {} {} {} {})",
+        format(m.a),
+        format(m.b),
+        format(m.c),
+        format(m.d),
+        format(m.e),
+        format(m.f)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    fn run(plugin: CropViewBox, input: &str) -> String {
+        let mut doc = parser::parse(input).unwrap();
+        plugin.apply(&mut doc);
+        printer::print(&doc)
+    }
+
+    #[test]
+    fn test_crop_to_content() {
+        let input = "<svg width=\"200\" height=\"200\" viewBox=\"0 0 100 100\"><rect x=\"10\" y=\"20\" width=\"30\" height=\"40\"/></svg>";
+        let expected = "<svg width=\"60\" height=\"80\" viewBox=\"10 20 30 40\"><rect x=\"10\" y=\"20\" width=\"30\" height=\"40\"/></svg>";
+        assert_eq!(run(CropViewBox::default(), input), expected);
+    }
+
+    #[test]
+    fn test_padding_and_square() {
+        let input = "<svg viewBox=\"0 0 100 100\"><rect x=\"10\" y=\"20\" width=\"30\" height=\"40\"/></svg>";
+        let expected = "<svg viewBox=\"0 15 50 50\"><rect x=\"10\" y=\"20\" width=\"30\" height=\"40\"/></svg>";
+        let plugin = CropViewBox {
+            padding: 5.0,
+            square: true,
+            target: None,
+        };
+        assert_eq!(run(plugin, input), expected);
+    }
+
+    #[test]
+    fn test_bake_onto_grid() {
+        let input = "<svg viewBox=\"0 0 100 100\"><path d=\"M10 10h40v20h-40z\"/><g transform=\"translate(10 0)\" stroke-width=\"0\"><circle cx=\"20\" cy=\"20\" r=\"5\"/></g></svg>";
+        let expected = "<svg viewBox=\"0 0 24 24\"><path d=\"M0 6H24V18H0z\"/><g transform=\"matrix(1 0 0 1 6 0)\" stroke-width=\"0\"><circle cx=\"6\" cy=\"12\" r=\"3\"/></g></svg>";
+        let plugin = CropViewBox {
+            target: Some([0.0, 0.0, 24.0, 24.0]),
+            ..Default::default()
+        };
+        assert_eq!(run(plugin, input), expected);
+    }
+
+    #[test]
+    fn test_wrap_when_not_bakeable() {
+        let input = "<svg viewBox=\"0 0 10 10\"><title>t</title><path d=\"M0 0h10v10z\" style=\"stroke-width:2\"/></svg>";
+        let expected = "<svg viewBox=\"0 0 20 20\"><title>t</title><g transform=\"matrix(2 0 0 2 0 0)\"><path d=\"M0 0h10v10z\" style=\"stroke-width:2\"/></g></svg>";
+        let plugin = CropViewBox {
+            target: Some([0.0, 0.0, 20.0, 20.0]),
+            ..Default::default()
+        };
+        assert_eq!(run(plugin, input), expected);
+    }
+
+    #[test]
+    fn test_ignore_clipped_content() {
+        // The second rect is off the canvas, unless the root lets it show
+        let input = "<svg viewBox=\"0 0 100 100\"><rect x=\"10\" y=\"10\" width=\"30\" height=\"50\"/><rect x=\"300\" y=\"10\" width=\"50\" height=\"50\"/></svg>";
+        assert!(run(CropViewBox::default(), input).contains("viewBox=\"10 10 30 50\""));
+
+        let input = input.replacen("<svg ", "<svg overflow=\"visible\" ", 1);
+        assert!(run(CropViewBox::default(), &input).contains("viewBox=\"10 10 340 50\""));
+    }
+}
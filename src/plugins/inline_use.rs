@@ -0,0 +1,407 @@
+use crate::bbox::Rect;
+use crate::path::{self, WriteOptions};
+use crate::plugins::collections::INHERITABLE_ATTRS;
+use crate::plugins::Plugin;
+use crate::references::ReferenceIndex;
+use crate::tree::{Document, Element, Node};
+
+/// Replaces a `<use>` with the content it references when nothing else
+/// refers to that content, then drops the definition.
+pub struct InlineUse;
+
+impl Plugin for InlineUse {
+    fn apply(&self, doc: &mut Document) {
+        // Stylesheets could match the <use>/<symbol> structure
+        if has_style_or_script(&doc.root) {
+            return;
+        }
+        // Paths change after every inlining, so look them up again each time
+        let mut skipped = 0;
+        loop {
+            let index = ReferenceIndex::build(doc);
+            let mut uses = Vec::new();
+            collect_uses(&doc.root, &mut Vec::new(), false, &mut uses);
+
+            let Some((use_path, def_path)) = uses
+                .into_iter()
+                .filter_map(|use_path| {
+                    let def_path = inline_target(doc, &index, &use_path)?;
+                    Some((use_path, def_path))
+                })
+                .nth(skipped)
+            else {
+                break;
+            };
+            if !inline(doc, use_path, &def_path) {
+                skipped += 1;
+            }
+        }
+    }
+}
+
+fn has_style_or_script(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => {
+            elem.name == "style" || elem.name == "script" || has_style_or_script(&elem.children)
+        }
+        _ => false,
+    })
+}
+
+// <use> elements outside clip paths (which only accept shapes)
+fn collect_uses(nodes: &[Node], path: &mut Vec<usize>, in_clip: bool, out: &mut Vec<Vec<usize>>) {
+    for (i, node) in nodes.iter().enumerate() {
+        if let Node::Element(elem) = node {
+            path.push(i);
+            if elem.name == "use" && !in_clip {
+                out.push(path.clone());
+            }
+            let in_clip = in_clip || elem.name == "clipPath";
+            collect_uses(&elem.children, path, in_clip, out);
+            path.pop();
+        }
+    }
+}
+
+fn href(elem: &Element) -> Option<&str> {
+    elem.attributes
+        .get("href")
+        .or_else(|| elem.attributes.get("xlink:href"))
+        .and_then(|h| h.strip_prefix('#'))
+}
+
+// The definition a <use> can swallow: referenced only by this <use>, not
+// rendered on its own (a <symbol> or inside <defs>) and not containing it.
+fn inline_target(doc: &Document, index: &ReferenceIndex, use_path: &[usize]) -> Option<Vec<usize>> {
+    let Some(Node::Element(use_elem)) = doc.node_at(use_path) else {
+        return None;
+    };
+    // Animations and titles on the <use> itself would be lost
+    if use_elem
+        .children
+        .iter()
+        .any(|c| matches!(c, Node::Element(_)))
+    {
+        return None;
+    }
+    let id = href(use_elem)?;
+    let def_path = index.definition(id)?.to_vec();
+    if index.references(id).len() != 1 || use_path.starts_with(&def_path) {
+        return None;
+    }
+    let Some(Node::Element(def)) = doc.node_at(&def_path) else {
+        return None;
+    };
+    if def.name == "svg" {
+        return None;
+    }
+    let in_defs = (1..def_path.len()).any(
+        |len| matches!(doc.node_at(&def_path[..len]), Some(Node::Element(e)) if e.name == "defs"),
+    );
+    (def.name == "symbol" || in_defs).then_some(def_path)
+}
+
+fn inline(doc: &mut Document, mut use_path: Vec<usize>, def_path: &[usize]) -> bool {
+    let (Some(Node::Element(use_elem)), Some(Node::Element(def))) =
+        (doc.node_at(&use_path), doc.node_at(def_path))
+    else {
+        return false;
+    };
+    let Some(replacement) = replacement(use_elem, def) else {
+        return false;
+    };
+
+    // Remove the definition, then find the <use> again
+    let Some((last, parent)) = def_path.split_last() else {
+        return false;
+    };
+    let removed = match parent {
+        [] => Some(&mut doc.root),
+        _ => match doc.node_at_mut(parent) {
+            Some(Node::Element(elem)) => Some(&mut elem.children),
+            _ => None,
+        },
+    };
+    let Some(siblings) = removed else {
+        return false;
+    };
+    siblings.remove(*last);
+    if use_path.len() > parent.len()
+        && use_path.starts_with(parent)
+        && use_path[parent.len()] > *last
+    {
+        use_path[parent.len()] -= 1;
+    }
+
+    let Some((use_last, use_parent)) = use_path.split_last() else {
+        return false;
+    };
+    let siblings = match use_parent {
+        [] => &mut doc.root,
+        _ => match doc.node_at_mut(use_parent) {
+            Some(Node::Element(elem)) => &mut elem.children,
+            _ => return false,
+        },
+    };
+    siblings.splice(*use_last..*use_last + 1, replacement);
+    true
+}
+
+// Attributes of <use> that position or link it, rather than style it
+const USE_ATTRS: [&str; 6] = ["href", "xlink:href", "x", "y", "width", "height"];
+
+fn replacement(use_elem: &Element, def: &Element) -> Option<Vec<Node>> {
+    let x = number_attr(use_elem, "x")?;
+    let y = number_attr(use_elem, "y")?;
+
+    let mut transform = use_elem.attributes.get("transform").cloned();
+    if x != 0.0 || y != 0.0 {
+        append_transform(&mut transform, &format!("translate({} {})", fmt(x), fmt(y)));
+    }
+
+    // Outer group: the <use>'s own attributes, which the content inherits
+    let mut outer = Element::new("g");
+    for (name, value) in &use_elem.attributes {
+        if !USE_ATTRS.contains(&name.as_str()) && name != "transform" {
+            outer.attributes.insert(name.clone(), value.clone());
+        }
+    }
+
+    let content = if def.name == "symbol" {
+        symbol_content(use_elem, def, &mut transform)?
+    } else {
+        let mut def = def.clone();
+        // Fold inherited properties and the transform into the element itself
+        // when the <use> has nothing that needs a group
+        let foldable = outer
+            .attributes
+            .keys()
+            .all(|name| INHERITABLE_ATTRS.contains(&name.as_str()));
+        if foldable {
+            for (name, value) in outer.attributes.drain(..) {
+                def.attributes.entry(name).or_insert(value);
+            }
+            if let Some(t) = transform.take() {
+                let combined = match def.attributes.get("transform") {
+                    Some(own) => format!("{} {}", t, own),
+                    None => t,
+                };
+                def.attributes.insert("transform".to_string(), combined);
+            }
+        }
+        vec![Node::Element(def)]
+    };
+
+    if let Some(t) = transform {
+        outer.attributes.insert("transform".to_string(), t);
+    }
+    if outer.attributes.is_empty() {
+        return Some(content);
+    }
+    outer.children = content;
+    Some(vec![Node::Element(outer)])
+}
+
+// Symbol attributes that describe its viewport, not its content
+const SYMBOL_ATTRS: [&str; 8] = [
+    "id",
+    "viewBox",
+    "preserveAspectRatio",
+    "x",
+    "y",
+    "width",
+    "height",
+    "overflow",
+];
+
+fn symbol_content(
+    use_elem: &Element,
+    symbol: &Element,
+    transform: &mut Option<String>,
+) -> Option<Vec<Node>> {
+    let mut inner = Element::new("g");
+    for (name, value) in &symbol.attributes {
+        if !SYMBOL_ATTRS.contains(&name.as_str()) {
+            inner.attributes.insert(name.clone(), value.clone());
+        }
+    }
+    inner.children = symbol.children.clone();
+
+    let overflow_visible = symbol
+        .attributes
+        .get("overflow")
+        .is_some_and(|o| o == "visible" || o == "auto");
+    let view_box = symbol
+        .attributes
+        .get("viewBox")
+        .and_then(|v| Rect::from_view_box(v));
+    let size = (
+        number_attr(use_elem, "width").filter(|_| use_elem.attributes.contains_key("width")),
+        number_attr(use_elem, "height").filter(|_| use_elem.attributes.contains_key("height")),
+    );
+
+    match (overflow_visible, view_box, size) {
+        // Nothing is clipped: the viewBox becomes a plain transform
+        (true, Some(vb), (Some(w), Some(h))) => {
+            let align = symbol
+                .attributes
+                .get("preserveAspectRatio")
+                .map_or("xMidYMid meet", |s| s.as_str());
+            append_transform(transform, &view_box_transform(vb, w, h, align)?);
+        }
+        (true, None, _) => {}
+        // The symbol clips to its viewport: keep one as a nested <svg>, like
+        // the instance a renderer would create
+        _ => {
+            let mut svg = Element::new("svg");
+            for name in ["width", "height"] {
+                if let Some(value) = use_elem.attributes.get(name) {
+                    svg.attributes.insert(name.to_string(), value.clone());
+                }
+            }
+            for name in ["viewBox", "preserveAspectRatio", "overflow"] {
+                if let Some(value) = symbol.attributes.get(name) {
+                    svg.attributes.insert(name.to_string(), value.clone());
+                }
+            }
+            svg.children = wrap(inner);
+            return Some(vec![Node::Element(svg)]);
+        }
+    }
+    Some(wrap(inner))
+}
+
+fn wrap(group: Element) -> Vec<Node> {
+    if group.attributes.is_empty() {
+        group.children
+    } else {
+        vec![Node::Element(group)]
+    }
+}
+
+fn append_transform(transform: &mut Option<String>, t: &str) {
+    *transform = Some(match transform.take() {
+        Some(existing) => format!("{} {}", existing, t),
+        None => t.to_string(),
+    });
+}
+
+fn number_attr(elem: &Element, name: &str) -> Option<f64> {
+    match elem.attributes.get(name) {
+        Some(v) => v.trim().trim_end_matches("px").parse().ok(),
+        None => Some(0.0),
+    }
+}
+
+// The transform mapping a viewBox onto a `width` x `height` viewport
+fn view_box_transform(vb: Rect, width: f64, height: f64, align: &str) -> Option<String> {
+    let (min_x, min_y, vb_w, vb_h) = (vb.min_x, vb.min_y, vb.width(), vb.height());
+    let mut parts = align.split_whitespace();
+    let align = parts.next().unwrap_or("xMidYMid");
+    let slice = parts.next() == Some("slice");
+
+    let (mut sx, mut sy) = (width / vb_w, height / vb_h);
+    if align != "none" {
+        let s = if slice { sx.max(sy) } else { sx.min(sy) };
+        sx = s;
+        sy = s;
+    }
+    let mut tx = -min_x * sx;
+    let mut ty = -min_y * sy;
+    if align != "none" {
+        if align.len() != 8 {
+            return None;
+        }
+        let (ax, ay) = align.split_at(4);
+        let extra_x = width - vb_w * sx;
+        let extra_y = height - vb_h * sy;
+        match ax {
+            "xMid" => tx += extra_x / 2.0,
+            "xMax" => tx += extra_x,
+            "xMin" => {}
+            _ => return None,
+        }
+        match ay {
+            "YMid" => ty += extra_y / 2.0,
+            "YMax" => ty += extra_y,
+            "YMin" => {}
+            _ => return None,
+        }
+    }
+
+    let mut t = String::new();
+    if tx != 0.0 || ty != 0.0 {
+        t.push_str(&format!("translate({} {})", fmt(tx), fmt(ty)));
+    }
+    if sx != 1.0 || sy != 1.0 {
+        if !t.is_empty() {
+            t.push(' ');
+        }
+        if sx == sy {
+            t.push_str(&format!("scale({})", fmt(sx)));
+        } else {
+            t.push_str(&format!("scale({} {})", fmt(sx), fmt(sy)));
+        }
+    }
+    Some(t)
+}
+
+fn fmt(n: f64) -> String {
+    path::format_number(
+        n,
+        &WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_inline_def() {
+        let input = "<svg><defs><path id=\"p\" d=\"M0 0h10\" transform=\"scale(2)\"/><rect id=\"r\"/></defs><use href=\"#p\" x=\"5\" fill=\"red\"/><use href=\"#r\" opacity=\".5\"/><use href=\"#r\"/></svg>";
+        let expected = "<svg><defs><rect id=\"r\"/></defs><path id=\"p\" d=\"M0 0h10\" transform=\"translate(5 0) scale(2)\" fill=\"red\"/><use href=\"#r\" opacity=\".5\"/><use href=\"#r\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        InlineUse.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_inline_symbol() {
+        let input = "<svg><symbol id=\"s\" viewBox=\"0 0 10 10\" overflow=\"visible\" fill=\"blue\"><path d=\"M0 0h10\"/></symbol><use xlink:href=\"#s\" width=\"20\" height=\"20\" y=\"4\" opacity=\".5\"/></svg>";
+        let expected = "<svg><g opacity=\".5\" transform=\"translate(0 4) scale(2)\"><g fill=\"blue\"><path d=\"M0 0h10\"/></g></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        InlineUse.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_inline_clipping_symbol() {
+        let input = "<svg><symbol id=\"s\" viewBox=\"0 0 10 10\"><path d=\"M0 0h10\"/></symbol><use href=\"#s\" x=\"1\" width=\"20\" height=\"20\"/></svg>";
+        let expected = "<svg><g transform=\"translate(1 0)\"><svg width=\"20\" height=\"20\" viewBox=\"0 0 10 10\"><path d=\"M0 0h10\"/></svg></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        InlineUse.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_view_box_transform() {
+        let vb = Rect::new(0.0, 0.0, 10.0, 20.0);
+        assert_eq!(
+            view_box_transform(vb, 40.0, 40.0, "xMidYMid meet").unwrap(),
+            "translate(10 0) scale(2)"
+        );
+        assert_eq!(
+            view_box_transform(vb, 40.0, 40.0, "none").unwrap(),
+            "scale(4 2)"
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use crate::css::{self, AtRuleBody, CssItem, Declaration, Selector};
+use crate::plugins::cleanup_numeric_values::{cleanup_number, CleanupNumericValues};
+use crate::plugins::convert_colors::convert_color;
+use crate::plugins::Plugin;
+use crate::tree::{Document, Element, Node};
+
+pub struct MinifyStyles {
+    pub float_precision: usize,
+    // Drop rules whose selectors match no element in the document
+    pub remove_unused: bool,
+}
+
+impl Default for MinifyStyles {
+    fn default() -> Self {
+        Self {
+            float_precision: 3,
+            remove_unused: true,
+        }
+    }
+}
+
+impl Plugin for MinifyStyles {
+    fn apply(&self, doc: &mut Document) {
+        // Scripts can add classes or elements at runtime, so usage can't be trusted.
+        let prune = self.remove_unused && !has_scripts(&doc.root);
+
+        // Phase 1: minify <style> contents against the unmodified document
+        let mut results = Vec::new();
+        collect_styles(&doc.root, &doc.root, prune, self, &mut results);
+
+        // Phase 2: write them back in the same (document) order
+        let mut results = results.into_iter();
+        apply_styles(&mut doc.root, &mut results, self);
+    }
+}
+
+const COLOR_PROPS: [&str; 13] = [
+    "fill",
+    "stroke",
+    "color",
+    "stop-color",
+    "flood-color",
+    "lighting-color",
+    "background",
+    "background-color",
+    "border-color",
+    "outline-color",
+    "caret-color",
+    "text-decoration-color",
+    "column-rule-color",
+];
+
+fn has_scripts(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => {
+            elem.name == "script"
+                || elem.attributes.keys().any(|k| k.starts_with("on"))
+                || has_scripts(&elem.children)
+        }
+        _ => false,
+    })
+}
+
+fn collect_styles(
+    nodes: &[Node],
+    root: &[Node],
+    prune: bool,
+    opts: &MinifyStyles,
+    results: &mut Vec<Option<String>>,
+) {
+    for node in nodes {
+        if let Node::Element(elem) = node {
+            if css::is_style_element(elem) {
+                results.push(minify_style_element(elem, root, prune, opts));
+            }
+            collect_styles(&elem.children, root, prune, opts, results);
+        }
+    }
+}
+
+fn minify_style_element(
+    elem: &Element,
+    root: &[Node],
+    prune: bool,
+    opts: &MinifyStyles,
+) -> Option<String> {
+    let mut text = String::new();
+    for child in &elem.children {
+        match child {
+            // Raw text may hold escaped markup; leave it alone rather than mangle entities.
+            Node::Text(t) if t.contains(['&', '<']) => return None,
+            Node::Text(t) | Node::Cdata(t) => text.push_str(t),
+            _ => {}
+        }
+    }
+
+    let mut sheet = css::parse_stylesheet(&text)?;
+    if prune {
+        prune_items(&mut sheet.items, root);
+    }
+    sheet.items = minify_items(sheet.items, opts);
+    Some(css::stringify(&sheet))
+}
+
+fn apply_styles(
+    nodes: &mut Vec<Node>,
+    results: &mut std::vec::IntoIter<Option<String>>,
+    opts: &MinifyStyles,
+) {
+    for node in nodes.iter_mut() {
+        if let Node::Element(elem) = node {
+            if css::is_style_element(elem) {
+                if let Some(Some(css)) = results.next() {
+                    let cdata = elem.children.iter().any(|c| matches!(c, Node::Cdata(_)));
+                    elem.children.clear();
+                    if !css.is_empty() {
+                        elem.children.push(if cdata {
+                            Node::Cdata(css)
+                        } else {
+                            Node::Text(css)
+                        });
+                    }
+                }
+            }
+
+            if let Some(style) = elem.attributes.get("style") {
+                let mut declarations = css::parse_declarations(style);
+                for decl in &mut declarations {
+                    minify_declaration(decl, opts);
+                }
+                dedupe_declarations(&mut declarations);
+                let minified = css::stringify_declarations(&declarations);
+                if minified.is_empty() {
+                    elem.attributes.shift_remove("style");
+                } else {
+                    elem.attributes.insert("style".to_string(), minified);
+                }
+            }
+
+            apply_styles(&mut elem.children, results, opts);
+        }
+    }
+
+    // Styles left without any rules are useless
+    nodes.retain(|node| match node {
+        Node::Element(elem) => !(css::is_style_element(elem) && elem.children.is_empty()),
+        _ => true,
+    });
+}
+
+fn prune_items(items: &mut Vec<CssItem>, root: &[Node]) {
+    items.retain_mut(|item| match item {
+        CssItem::Rule(rule) => {
+            rule.selectors
+                .retain(|selector| is_selector_used(selector, root));
+            !rule.selectors.is_empty()
+        }
+        CssItem::AtRule(at_rule) if at_rule.is_conditional() => {
+            if let AtRuleBody::Rules(rules) = &mut at_rule.body {
+                prune_items(rules, root);
+                !rules.is_empty()
+            } else {
+                true
+            }
+        }
+        CssItem::AtRule(_) => true,
+    });
+}
+
+fn is_selector_used(selector: &str, root: &[Node]) -> bool {
+    match Selector::parse(selector) {
+        Some(parsed) => any_element_matches(root, &parsed, &mut Vec::new()),
+        // Unknown syntax: keep it to be safe
+        None => true,
+    }
+}
+
+fn any_element_matches<'a>(
+    nodes: &'a [Node],
+    selector: &Selector,
+    ancestors: &mut Vec<&'a Element>,
+) -> bool {
+    for node in nodes {
+        if let Node::Element(elem) = node {
+            if selector.matches(elem, ancestors) {
+                return true;
+            }
+            ancestors.push(elem);
+            let found = any_element_matches(&elem.children, selector, ancestors);
+            ancestors.pop();
+            if found {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn minify_items(items: Vec<CssItem>, opts: &MinifyStyles) -> Vec<CssItem> {
+    let mut out: Vec<CssItem> = Vec::with_capacity(items.len());
+
+    for mut item in items {
+        match &mut item {
+            CssItem::Rule(rule) => {
+                for decl in &mut rule.declarations {
+                    minify_declaration(decl, opts);
+                }
+                dedupe_declarations(&mut rule.declarations);
+                if rule.declarations.is_empty() {
+                    continue;
+                }
+            }
+            CssItem::AtRule(at_rule) => match &mut at_rule.body {
+                AtRuleBody::Rules(rules) => {
+                    *rules = minify_items(std::mem::take(rules), opts);
+                    if rules.is_empty() {
+                        continue;
+                    }
+                }
+                AtRuleBody::Declarations(decls) => {
+                    for decl in decls.iter_mut() {
+                        minify_declaration(decl, opts);
+                    }
+                    dedupe_declarations(decls);
+                }
+                AtRuleBody::None => {}
+            },
+        }
+
+        // Merge with the previous rule when adjacent, which can't change the cascade
+        if let (Some(CssItem::Rule(prev)), CssItem::Rule(rule)) = (out.last_mut(), &item) {
+            if prev.selectors == rule.selectors {
+                prev.declarations.extend(rule.declarations.iter().cloned());
+                dedupe_declarations(&mut prev.declarations);
+                continue;
+            }
+            if prev.declarations == rule.declarations
+                && is_mergeable(&prev.selectors)
+                && is_mergeable(&rule.selectors)
+            {
+                for selector in &rule.selectors {
+                    if !prev.selectors.contains(selector) {
+                        prev.selectors.push(selector.clone());
+                    }
+                }
+                continue;
+            }
+        }
+        out.push(item);
+    }
+
+    out
+}
+
+// A selector list is dropped entirely if one selector is invalid, so only
+// merge selectors every browser understands.
+fn is_mergeable(selectors: &[String]) -> bool {
+    selectors
+        .iter()
+        .all(|s| !s.contains(":-") && Selector::parse(s).is_some())
+}
+
+fn minify_declaration(decl: &mut Declaration, opts: &MinifyStyles) {
+    let mut value = css::minify_value(&decl.value);
+
+    // Custom properties are opaque token streams, and `U+0025-00FF` only
+    // looks like numbers
+    if !decl.name.starts_with("--") && !decl.name.eq_ignore_ascii_case("unicode-range") {
+        let num_opts = CleanupNumericValues {
+            float_precision: opts.float_precision,
+            remove_px: false,
+            leading_zero: true,
+            error_budget: None,
+        };
+        value = shorten_numbers(&value, &num_opts);
+
+        if COLOR_PROPS.contains(&decl.name.as_str()) {
+            value = split_top_level_spaces(&value)
+                .into_iter()
+                .map(convert_color)
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+    }
+
+    decl.value = value;
+}
+
+// Exact duplicates are redundant; differing repeats are often intentional fallbacks.
+fn dedupe_declarations(declarations: &mut Vec<Declaration>) {
+    let mut i = 0;
+    while i < declarations.len() {
+        if declarations[i + 1..].contains(&declarations[i]) {
+            declarations.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn split_top_level_spaces(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' | '\'' if quote == Some(c) => quote = None,
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => depth = depth.saturating_sub(1),
+            ' ' if quote.is_none() && depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Rounds and trims numbers in a CSS value, keeping their units. Strings,
+/// `url(...)`, hex colors and identifiers are copied verbatim.
+fn shorten_numbers(value: &str, opts: &CleanupNumericValues) -> String {
+    let bytes = value.as_bytes();
+    let mut out = String::with_capacity(value.len());
+    let mut i = 0;
+
+    let is_digit_at = |i: usize| bytes.get(i).is_some_and(|b| b.is_ascii_digit());
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+
+        if c == b'"' || c == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != c {
+                i += 1;
+            }
+            i = (i + 1).min(bytes.len());
+        } else if value[i..].len() >= 4 && value[i..i + 4].eq_ignore_ascii_case("url(") {
+            while i < bytes.len() && bytes[i] != b')' {
+                i += 1;
+            }
+            i = (i + 1).min(bytes.len());
+        } else if c == b'#'
+            || c == b'_'
+            || c.is_ascii_alphabetic()
+            || c >= 0x80
+            || (c == b'-' && !is_digit_at(i + 1) && bytes.get(i + 1) != Some(&b'.'))
+        {
+            i += 1;
+            while i < bytes.len()
+                && (bytes[i].is_ascii_alphanumeric()
+                    || matches!(bytes[i], b'-' | b'_')
+                    || bytes[i] >= 0x80)
+            {
+                i += 1;
+            }
+        } else if c.is_ascii_digit()
+            || (c == b'.' && is_digit_at(i + 1))
+            || (matches!(c, b'+' | b'-')
+                && (is_digit_at(i + 1) || (bytes.get(i + 1) == Some(&b'.') && is_digit_at(i + 2))))
+        {
+            if matches!(c, b'+' | b'-') {
+                i += 1;
+            }
+            while is_digit_at(i) {
+                i += 1;
+            }
+            if bytes.get(i) == Some(&b'.') && is_digit_at(i + 1) {
+                i += 1;
+                while is_digit_at(i) {
+                    i += 1;
+                }
+            }
+            if matches!(bytes.get(i), Some(b'e') | Some(b'E')) {
+                let exp_digits = if matches!(bytes.get(i + 1), Some(b'+') | Some(b'-')) {
+                    i + 2
+                } else {
+                    i + 1
+                };
+                if is_digit_at(exp_digits) {
+                    i = exp_digits;
+                    while is_digit_at(i) {
+                        i += 1;
+                    }
+                }
+            }
+
+            let number = &value[start..i];
+            let cleaned = cleanup_number(number, opts);
+            out.push_str(if cleaned.len() <= number.len() {
+                &cleaned
+            } else {
+                number
+            });
+            continue;
+        } else {
+            i += 1;
+            while i < bytes.len() && !bytes[i].is_ascii() {
+                i += 1;
+            }
+        }
+
+        out.push_str(&value[start..i]);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_minify_style_element() {
+        let input = "<svg><style>\n/* icon */\n.a {\n  fill: #FF0000;\n  stroke-width: 0.500px;\n}\n</style><rect class=\"a\"/></svg>";
+        let expected =
+            "<svg><style>.a{fill:#f00;stroke-width:.5px}</style><rect class=\"a\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MinifyStyles::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_remove_unused_rules() {
+        let input = "<svg><style>.a, .b { fill: red } #missing { fill: blue } @media print { g rect:hover { fill: green } }</style><rect class=\"a\"/></svg>";
+        let expected = "<svg><style>.a{fill:red}</style><rect class=\"a\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MinifyStyles::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_merge_adjacent_rules() {
+        let input = "<svg><style><![CDATA[.a{fill:red}.a{stroke:blue}.b{stroke:blue;fill:red}.c{stroke:blue;fill:red}]]></style><g class=\"a b c\"/></svg>";
+        let expected =
+            "<svg><style><![CDATA[.a{fill:red;stroke:blue}.b,.c{stroke:blue;fill:red}]]></style><g class=\"a b c\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MinifyStyles::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_minify_style_attribute() {
+        let input = "<svg><rect style=\"fill : rgb(0, 0, 0) ; /* x */ opacity: 0.50 ; fill : rgb(0, 0, 0)\"/><g style=\" \"/></svg>";
+        let expected = "<svg><rect style=\"opacity:.5;fill:#000\"/><g/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MinifyStyles::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_unicode_range() {
+        let input = "<svg><style>@font-face { font-family: f; unicode-range: U+0025-00FF, U+4?? }</style></svg>";
+        let expected =
+            "<svg><style>@font-face{font-family:f;unicode-range:U+0025-00FF,U+4??}</style></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MinifyStyles::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+}
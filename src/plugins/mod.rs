@@ -2,6 +2,11 @@ use crate::tree::Document;
 
 pub trait Plugin {
     fn apply(&self, doc: &mut Document);
+
+    /// A summary of what the last `apply` did, for plugins worth reporting on.
+    fn report(&self) -> Option<String> {
+        None
+    }
 }
 
 pub mod cleanup_attrs;
@@ -50,6 +55,17 @@ pub mod remove_empty_containers;
 pub mod remove_unused_ns;
 pub mod sort_defs_children;
 
+pub mod apply_transforms;
+pub mod convert_path_to_shapes;
+pub mod crop_view_box;
+pub mod inline_use;
+pub mod minify_styles;
+pub mod prefix_ids;
+pub mod remove_duplicate_defs;
+pub mod remove_off_canvas_paths;
+pub mod reuse_paths;
+pub mod simplify_paths;
+
 pub use cleanup_attrs::CleanupAttrs;
 pub use convert_colors::ConvertColors;
 pub use remove_comments::RemoveComments;
@@ -94,3 +110,14 @@ pub use move_group_attrs_to_elems::MoveGroupAttrsToElems;
 pub use remove_empty_containers::RemoveEmptyContainers;
 pub use remove_unused_ns::RemoveUnusedNS;
 pub use sort_defs_children::SortDefsChildren;
+
+pub use apply_transforms::ApplyTransforms;
+pub use convert_path_to_shapes::ConvertPathToShapes;
+pub use crop_view_box::CropViewBox;
+pub use inline_use::InlineUse;
+pub use minify_styles::MinifyStyles;
+pub use prefix_ids::PrefixIds;
+pub use remove_duplicate_defs::RemoveDuplicateDefs;
+pub use remove_off_canvas_paths::RemoveOffCanvasPaths;
+pub use reuse_paths::ReusePaths;
+pub use simplify_paths::{Simplification, SimplifyPaths, Tolerance};
@@ -1,3 +1,4 @@
+use crate::plugins::collections::INHERITABLE_ATTRS;
 use crate::plugins::Plugin;
 use crate::tree::{Document, Node};
 
@@ -40,53 +41,7 @@ fn process_nodes(nodes: &mut Vec<Node>) {
                             }
 
                             // Only move inheritable attributes
-                            let inheritable = matches!(
-                                k.as_str(),
-                                "clip-rule"
-                                    | "color"
-                                    | "color-interpolation"
-                                    | "color-interpolation-filters"
-                                    | "color-profile"
-                                    | "color-rendering"
-                                    | "cursor"
-                                    | "direction"
-                                    | "fill"
-                                    | "fill-opacity"
-                                    | "fill-rule"
-                                    | "font"
-                                    | "font-family"
-                                    | "font-size"
-                                    | "font-size-adjust"
-                                    | "font-stretch"
-                                    | "font-style"
-                                    | "font-variant"
-                                    | "font-weight"
-                                    | "glyph-orientation-horizontal"
-                                    | "glyph-orientation-vertical"
-                                    | "image-rendering"
-                                    | "kerning"
-                                    | "letter-spacing"
-                                    | "marker"
-                                    | "marker-end"
-                                    | "marker-mid"
-                                    | "marker-start"
-                                    | "pointer-events"
-                                    | "shape-rendering"
-                                    | "stroke"
-                                    | "stroke-dasharray"
-                                    | "stroke-dashoffset"
-                                    | "stroke-linecap"
-                                    | "stroke-linejoin"
-                                    | "stroke-miterlimit"
-                                    | "stroke-opacity"
-                                    | "stroke-width"
-                                    | "text-anchor"
-                                    | "text-rendering"
-                                    | "visibility"
-                                    | "white-space"
-                                    | "word-spacing"
-                                    | "writing-mode"
-                            );
+                            let inheritable = INHERITABLE_ATTRS.contains(&k.as_str());
 
                             if !inheritable {
                                 continue;
@@ -1,24 +1,28 @@
+use crate::path::{self, WriteOptions};
 use crate::plugins::Plugin;
-use crate::tree::{Document, Node};
+use crate::style::{self, ComputedStyle, Origin, StyledNode};
+use crate::transform::{self, is_approx};
+use crate::tree::{Document, Element, Node};
 
 pub struct MoveGroupAttrsToElems;
 
 impl Plugin for MoveGroupAttrsToElems {
     fn apply(&self, doc: &mut Document) {
-        process_nodes(&mut doc.root);
+        let styles = style::resolve(doc);
+        process_nodes(&mut doc.root, &styles);
     }
 }
 
-fn process_nodes(nodes: &mut Vec<Node>) {
+fn process_nodes(nodes: &mut [Node], styles: &[StyledNode]) {
     // We need to iterate and modify.
     // If we modify a group, we might need to process its children again?
     // Let's do a recursive traversal.
 
-    for node in nodes {
+    for (node, styled) in nodes.iter_mut().zip(styles) {
         if let Node::Element(elem) = node {
             if elem.name == "g" {
                 // Try to move attributes to children
-                if !elem.children.is_empty() {
+                if !elem.children.is_empty() && can_move_transform(styled) {
                     let cached_transform = elem.attributes.get("transform").cloned();
 
                     // inheritable attributes
@@ -29,7 +33,8 @@ fn process_nodes(nodes: &mut Vec<Node>) {
                     // This is complex. Let's strictly implement Transform propagation for now as it solves the "unwrappable group" issue.
 
                     if let Some(ref root_transform) = cached_transform {
-                        for child in &mut elem.children {
+                        for (child, child_styled) in elem.children.iter_mut().zip(&styled.children)
+                        {
                             if let Node::Element(child_elem) = child {
                                 // Prepend group transform to child transform
                                 if let Some(child_t) = child_elem.attributes.get_mut("transform") {
@@ -39,6 +44,7 @@ fn process_nodes(nodes: &mut Vec<Node>) {
                                         .attributes
                                         .insert("transform".to_string(), root_transform.clone());
                                 }
+                                apply_to_attributes(child_elem, &child_styled.style);
                             }
                         }
                         // Remove from group
@@ -47,11 +53,128 @@ fn process_nodes(nodes: &mut Vec<Node>) {
                 }
             }
 
-            process_nodes(&mut elem.children);
+            process_nodes(&mut elem.children, &styled.children);
         }
     }
 }
 
+// The transform must come from the group's own attribute, children must not
+// get theirs from CSS, and the group must not have effects that are applied
+// in its coordinate system.
+fn can_move_transform(styled: &StyledNode) -> bool {
+    let group = &styled.style;
+    // Initial: the attribute was pushed down from an outer group just now
+    if !matches!(
+        group.origin("transform"),
+        Origin::Attribute | Origin::Initial
+    ) || has_transform_origin(group)
+        || ["clip-path", "mask", "filter"]
+            .iter()
+            .any(|p| group.value(p) != Some("none"))
+    {
+        return false;
+    }
+    styled.children.iter().all(|child| {
+        let style = &child.style;
+        !style.is_dynamic("transform")
+            && !has_transform_origin(style)
+            && matches!(
+                style.origin("transform"),
+                Origin::Attribute | Origin::Initial
+            )
+    })
+}
+
+// An origin applies around the whole transform list, so it can't take
+// another transform in front
+fn has_transform_origin(style: &ComputedStyle) -> bool {
+    ["transform-origin", "transform-box"]
+        .iter()
+        .any(|p| style.is_dynamic(p) || style.origin(p) != Origin::Initial)
+}
+
+// Simple shapes can take a translate, and circles and ellipses a scale,
+// in their own coordinates instead
+fn apply_to_attributes(elem: &mut Element, style: &ComputedStyle) {
+    let Some(t) = elem.attributes.get("transform") else {
+        return;
+    };
+    let m = transform::parse(t);
+    if !is_approx(m.b, 0.0) || !is_approx(m.c, 0.0) || m.a <= 0.0 || m.d <= 0.0 {
+        return;
+    }
+    // Things laid out in user space would stay behind
+    if ["clip-path", "mask", "filter"]
+        .iter()
+        .any(|p| style.value(p) != Some("none"))
+        || ["fill", "stroke"]
+            .iter()
+            .any(|p| style.value(p).is_none_or(|v| v.contains("url(")))
+    {
+        return;
+    }
+    let translate = is_approx(m.a, 1.0) && is_approx(m.d, 1.0);
+    // A scaled stroke would need a new width
+    let unstroked = style.value("stroke") == Some("none");
+
+    let (x, y, sizes): (&str, &str, &[&str]) = match elem.name.as_str() {
+        "rect" | "use" if translate => ("x", "y", &[]),
+        "text" if translate && !has_positioned_children(elem) => ("x", "y", &[]),
+        "circle" if translate || (unstroked && is_approx(m.a, m.d)) => ("cx", "cy", &["r"]),
+        "ellipse" if translate || unstroked => ("cx", "cy", &["rx", "ry"]),
+        _ => return,
+    };
+
+    let get = |name: &str| match elem.attributes.get(name) {
+        Some(v) => v.trim().trim_end_matches("px").parse::<f64>().ok(),
+        None => Some(0.0),
+    };
+    let (Some(old_x), Some(old_y)) = (get(x), get(y)) else {
+        return;
+    };
+    let Some(old_sizes) = sizes
+        .iter()
+        .map(|name| get(name))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return;
+    };
+
+    let opts = WriteOptions {
+        precision: Some(PRECISION),
+        ..Default::default()
+    };
+    let mut set = |name: &str, old: f64, new: f64| {
+        if elem.attributes.contains_key(name) || new != old {
+            elem.attributes
+                .insert(name.to_string(), path::format_number(new, &opts));
+        }
+    };
+    set(x, old_x, m.a * old_x + m.e);
+    set(y, old_y, m.d * old_y + m.f);
+    for (name, old) in sizes.iter().zip(old_sizes) {
+        let scale = if *name == "ry" { m.d } else { m.a };
+        set(name, old, old * scale);
+    }
+    elem.attributes.shift_remove("transform");
+}
+
+// Enough to not lose anything cleanupNumericValues keeps
+const PRECISION: usize = 6;
+
+// `<tspan x>` and the like are absolute, and a `<textPath>` follows a path
+// laid out in the text's user space
+fn has_positioned_children(elem: &Element) -> bool {
+    elem.children.iter().any(|child| match child {
+        Node::Element(child) => {
+            child.name == "textPath"
+                || ["x", "y"].iter().any(|a| child.attributes.contains_key(*a))
+                || has_positioned_children(child)
+        }
+        _ => false,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,7 +184,7 @@ mod tests {
     #[test]
     fn test_move_transform() {
         let input = "<svg><g transform=\"scale(2)\"><rect width=\"10\"/><circle/></g></svg>";
-        let expected = "<svg><g><rect width=\"10\" transform=\"scale(2)\"/><circle transform=\"scale(2)\"/></g></svg>";
+        let expected = "<svg><g><rect width=\"10\" transform=\"scale(2)\"/><circle/></g></svg>";
         // Note: CollapseGroups would later remove the empty <g>
 
         let mut doc = parser::parse(input).unwrap();
@@ -78,4 +201,34 @@ mod tests {
         MoveGroupAttrsToElems.apply(&mut doc);
         assert_eq!(printer::print(&doc), expected);
     }
+
+    #[test]
+    fn test_apply_to_attributes() {
+        let input = "<svg><g transform=\"translate(10 20)\"><rect x=\"1\" width=\"5\" height=\"5\"/><use href=\"#a\"/><text y=\"3\">a</text></g><g transform=\"translate(1) scale(2)\"><circle cx=\"3\" cy=\"4\" r=\"5\"/><circle r=\"5\" stroke=\"red\"/></g></svg>";
+        let expected = "<svg><g><rect x=\"11\" width=\"5\" height=\"5\" y=\"20\"/><use href=\"#a\" x=\"10\" y=\"20\"/><text y=\"23\" x=\"10\">a</text></g><g><circle cx=\"7\" cy=\"8\" r=\"10\"/><circle r=\"5\" stroke=\"red\" transform=\"translate(1) scale(2)\"/></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MoveGroupAttrsToElems.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_transform() {
+        let input = "<svg><style>.a { transform: scale(3) }</style><g transform=\"scale(2)\"><rect class=\"a\"/></g><g transform=\"scale(2)\" clip-path=\"url(#c)\"><rect/></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MoveGroupAttrsToElems.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+
+    #[test]
+    fn test_keep_text_path_transform() {
+        // The path is drawn in the text's user space, so x/y can't stand in
+        let input = "<svg><g transform=\"translate(20 30)\"><text x=\"5\"><textPath href=\"#p\">a</textPath></text></g></svg>";
+        let expected = "<svg><g><text x=\"5\" transform=\"translate(20 30)\"><textPath href=\"#p\">a</textPath></text></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        MoveGroupAttrsToElems.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
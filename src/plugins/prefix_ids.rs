@@ -0,0 +1,172 @@
+use crate::css;
+use crate::plugins::Plugin;
+use crate::references::{self, ReferenceIndex};
+use crate::tree::{Document, Node};
+use std::collections::HashSet;
+
+/// Prefixes IDs and class names so several SVGs can be inlined into one page
+/// without their references colliding.
+pub struct PrefixIds {
+    pub prefix: String,
+    pub delim: String,
+    pub prefix_ids: bool,
+    pub prefix_class_names: bool,
+}
+
+impl Default for PrefixIds {
+    fn default() -> Self {
+        Self {
+            prefix: "prefix".to_string(),
+            delim: "__".to_string(),
+            prefix_ids: true,
+            prefix_class_names: true,
+        }
+    }
+}
+
+impl PrefixIds {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Uses a prefix derived from a hash of `file_name`, so the same file
+    /// always gets the same prefix.
+    pub fn from_file_name(file_name: &str) -> Self {
+        Self::new(&prefix_from_file_name(file_name))
+    }
+}
+
+/// A short prefix derived from the FNV-1a hash of a file name. It always
+/// starts with a letter so that it's a valid CSS identifier.
+pub fn prefix_from_file_name(file_name: &str) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in file_name.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    format!("f{:08x}", hash)
+}
+
+impl Plugin for PrefixIds {
+    fn apply(&self, doc: &mut Document) {
+        let index = ReferenceIndex::build(doc);
+        // Rewriting a stylesheet we can't parse would leave its selectors
+        // pointing at the old names, so leave the whole document alone.
+        if index.is_opaque() {
+            return;
+        }
+
+        let ids = if self.prefix_ids {
+            index.ids().map(String::from).collect()
+        } else {
+            HashSet::new()
+        };
+
+        let rewriter = Rewriter { opts: self, ids };
+        rewriter.process_nodes(&mut doc.root);
+    }
+}
+
+struct Rewriter<'a> {
+    opts: &'a PrefixIds,
+    // Only references to IDs defined in this document are rewritten; anything
+    // else may point into the page the SVG is inlined into.
+    ids: HashSet<String>,
+}
+
+impl Rewriter<'_> {
+    fn prefixed(&self, name: &str) -> String {
+        format!("{}{}{}", self.opts.prefix, self.opts.delim, name)
+    }
+
+    fn id_ref(&self, id: &str) -> Option<String> {
+        self.ids.contains(id).then(|| self.prefixed(id))
+    }
+
+    fn class_name(&self, name: &str) -> Option<String> {
+        self.opts.prefix_class_names.then(|| self.prefixed(name))
+    }
+
+    fn process_nodes(&self, nodes: &mut [Node]) {
+        for node in nodes {
+            if let Node::Element(elem) = node {
+                if let Some(text) = css::style_text(elem) {
+                    let mut sheet = css::parse_stylesheet(&text).unwrap_or_default();
+                    references::rewrite_stylesheet(
+                        &mut sheet,
+                        &mut |id| self.id_ref(id),
+                        &mut |name| self.class_name(name),
+                    );
+                    let cdata = elem.children.iter().any(|c| matches!(c, Node::Cdata(_)));
+                    let text = css::stringify(&sheet);
+                    elem.children.clear();
+                    if !text.is_empty() {
+                        elem.children.push(if cdata {
+                            Node::Cdata(text)
+                        } else {
+                            Node::Text(text)
+                        });
+                    }
+                }
+
+                for (name, value) in elem.attributes.iter_mut() {
+                    let rewritten = match name.as_str() {
+                        "id" => self.id_ref(value),
+                        "class" if self.opts.prefix_class_names => Some(
+                            value
+                                .split_whitespace()
+                                .map(|c| self.prefixed(c))
+                                .collect::<Vec<_>>()
+                                .join(" "),
+                        ),
+                        _ => references::rewrite_attribute(name, value, &mut |id| self.id_ref(id)),
+                    };
+                    if let Some(rewritten) = rewritten {
+                        *value = rewritten;
+                    }
+                }
+
+                self.process_nodes(&mut elem.children);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_prefix_references() {
+        let input = "<svg><style>.cls-1 { fill: url(#a) } #a, rect[id=\"a\"], [class~=big], [xlink|href=\"#a\"] { opacity: .5 }</style><linearGradient id=\"a\"/><rect class=\"cls-1 big\" fill=\"url(#a)\" style=\"stroke:url('#a')\"/><use xlink:href=\"#a\"/><use href=\"#external\"/></svg>";
+        let expected = "<svg><style>.p__cls-1{fill:url(#p__a)}#p__a,rect[id=\"p__a\"],[class~=p__big],[xlink|href=\"#p__a\"]{opacity:.5}</style><linearGradient id=\"p__a\"/><rect class=\"p__cls-1 p__big\" fill=\"url(#p__a)\" style=\"stroke:url('#p__a')\"/><use xlink:href=\"#p__a\"/><use href=\"#external\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        PrefixIds::new("p").apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_prefix_smil_and_aria() {
+        let input = "<svg aria-labelledby=\"t other\"><title id=\"t\">x</title><rect id=\"r\"><animate id=\"anim\" begin=\"r.click; 2s\" end=\"anim.begin+1s\"/></rect></svg>";
+        let expected = "<svg aria-labelledby=\"p__t other\"><title id=\"p__t\">x</title><rect id=\"p__r\"><animate id=\"p__anim\" begin=\"p__r.click; 2s\" end=\"p__anim.begin+1s\"/></rect></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        PrefixIds::new("p").apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_prefix_from_file_name() {
+        let a = PrefixIds::from_file_name("icons/a.svg");
+        let b = PrefixIds::from_file_name("icons/b.svg");
+        assert_eq!(a.prefix, PrefixIds::from_file_name("icons/a.svg").prefix);
+        assert_ne!(a.prefix, b.prefix);
+        assert!(a.prefix.starts_with('f'));
+    }
+}
@@ -12,11 +12,9 @@ impl Plugin for RemoveDimensions {
 fn process_nodes(nodes: &mut Vec<Node>) {
     for node in nodes {
         if let Node::Element(elem) = node {
-            if elem.name == "svg" {
-                if elem.attributes.contains_key("viewBox") {
-                    elem.attributes.shift_remove("width");
-                    elem.attributes.shift_remove("height");
-                }
+            if elem.name == "svg" && elem.attributes.contains_key("viewBox") {
+                elem.attributes.shift_remove("width");
+                elem.attributes.shift_remove("height");
             }
             // Usually only on root svg, but recursively correct for nested SVGs too.
             process_nodes(&mut elem.children);
@@ -0,0 +1,231 @@
+use crate::css;
+use crate::plugins::Plugin;
+use crate::references::{self, ReferenceIndex};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashMap;
+
+/// Merges definitions that only differ by their `id` and points every
+/// reference at the copy that's kept.
+pub struct RemoveDuplicateDefs;
+
+const DEF_ELEMS: [&str; 8] = [
+    "linearGradient",
+    "radialGradient",
+    "pattern",
+    "clipPath",
+    "mask",
+    "filter",
+    "marker",
+    "symbol",
+];
+
+impl Plugin for RemoveDuplicateDefs {
+    fn apply(&self, doc: &mut Document) {
+        // Scripts may look definitions up by ID
+        if has_scripts(&doc.root) {
+            return;
+        }
+
+        // Merging can make definitions that refer to them identical too
+        // (gradients chained through href), so repeat until nothing changes.
+        loop {
+            let index = ReferenceIndex::build(doc);
+            if index.is_opaque() {
+                return;
+            }
+
+            let mut survivors = HashMap::new();
+            let mut renames = HashMap::new();
+            collect_duplicates(
+                &doc.root,
+                &index,
+                &mut Vec::new(),
+                &mut survivors,
+                &mut renames,
+            );
+            if renames.is_empty() {
+                break;
+            }
+
+            remove_duplicates(&mut doc.root, &index, &mut Vec::new(), &renames);
+            rewrite_references(&mut doc.root, &renames);
+        }
+    }
+}
+
+fn has_scripts(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => elem.name == "script" || has_scripts(&elem.children),
+        _ => false,
+    })
+}
+
+// Maps the ID of every duplicate to the ID of the first equal definition
+fn collect_duplicates(
+    nodes: &[Node],
+    index: &ReferenceIndex,
+    path: &mut Vec<usize>,
+    survivors: &mut HashMap<String, String>,
+    renames: &mut HashMap<String, String>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        let Node::Element(elem) = node else {
+            continue;
+        };
+        path.push(i);
+
+        if let Some(id) = elem.attributes.get("id") {
+            let is_definition = index.definition(id) == Some(path.as_slice());
+            // Stylesheets select by ID, so their targets must stay distinct
+            let styled = index.references(id).iter().any(|r| r.attr.is_none());
+            if DEF_ELEMS.contains(&elem.name.as_str()) && is_definition && !styled {
+                if let Some(key) = content_key(elem, index, true) {
+                    match survivors.get(&key) {
+                        Some(survivor) => {
+                            renames.insert(id.clone(), survivor.clone());
+                        }
+                        None => {
+                            survivors.insert(key, id.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        collect_duplicates(&elem.children, index, path, survivors, renames);
+        path.pop();
+    }
+}
+
+// Serialization with sorted attributes and without the element's own ID.
+// `None` if a descendant defines a referenced ID, since removing the
+// element would break that reference.
+fn content_key(elem: &Element, index: &ReferenceIndex, top: bool) -> Option<String> {
+    let mut attrs: Vec<(&String, &String)> = elem.attributes.iter().collect();
+    attrs.sort();
+
+    let mut key = format!("<{}", elem.name);
+    for (name, value) in attrs {
+        if name == "id" {
+            if !top && index.is_referenced(value) {
+                return None;
+            }
+            continue;
+        }
+        key.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    key.push('>');
+
+    for child in &elem.children {
+        match child {
+            Node::Element(child) => key.push_str(&content_key(child, index, false)?),
+            Node::Text(text) => key.push_str(text),
+            Node::Cdata(text) => key.push_str(&format!("<![CDATA[{}]]>", text)),
+            _ => {}
+        }
+    }
+    key.push_str(&format!("</{}>", elem.name));
+    Some(key)
+}
+
+fn remove_duplicates(
+    nodes: &mut Vec<Node>,
+    index: &ReferenceIndex,
+    path: &mut Vec<usize>,
+    renames: &HashMap<String, String>,
+) {
+    let mut keep = Vec::with_capacity(nodes.len());
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let mut retain = true;
+        if let Node::Element(elem) = node {
+            path.push(i);
+            let duplicate = elem.attributes.get("id").is_some_and(|id| {
+                renames.contains_key(id) && index.definition(id) == Some(path.as_slice())
+            });
+            if duplicate {
+                retain = false;
+            } else {
+                remove_duplicates(&mut elem.children, index, path, renames);
+            }
+            path.pop();
+        }
+        keep.push(retain);
+    }
+
+    let mut keep = keep.into_iter();
+    nodes.retain(|_| keep.next().unwrap_or(true));
+}
+
+fn rewrite_references(nodes: &mut [Node], renames: &HashMap<String, String>) {
+    for node in nodes {
+        if let Node::Element(elem) = node {
+            for (name, value) in elem.attributes.iter_mut() {
+                if let Some(value_new) =
+                    references::rewrite_attribute(name, value, &mut |id| renames.get(id).cloned())
+                {
+                    *value = value_new;
+                }
+            }
+
+            // <style> may still use url(#...) of a removed duplicate
+            if let Some(text) = css::style_text(elem) {
+                if let Some(mut sheet) = css::parse_stylesheet(&text) {
+                    let before = sheet.clone();
+                    references::rewrite_stylesheet(
+                        &mut sheet,
+                        &mut |id| renames.get(id).cloned(),
+                        &mut |_| None,
+                    );
+                    if sheet != before {
+                        let cdata = elem.children.iter().any(|c| matches!(c, Node::Cdata(_)));
+                        let text = css::stringify(&sheet);
+                        elem.children = vec![if cdata {
+                            Node::Cdata(text)
+                        } else {
+                            Node::Text(text)
+                        }];
+                    }
+                }
+            }
+
+            rewrite_references(&mut elem.children, renames);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_remove_duplicate_gradients() {
+        let input = "<svg><defs><linearGradient id=\"a\"><stop offset=\"0\"/><stop offset=\"1\" stop-color=\"red\"/></linearGradient><linearGradient id=\"b\"><stop offset=\"0\"/><stop stop-color=\"red\" offset=\"1\"/></linearGradient></defs><rect fill=\"url(#a)\"/><rect fill=\"url(#b)\"/></svg>";
+        let expected = "<svg><defs><linearGradient id=\"a\"><stop offset=\"0\"/><stop offset=\"1\" stop-color=\"red\"/></linearGradient></defs><rect fill=\"url(#a)\"/><rect fill=\"url(#a)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveDuplicateDefs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_chained_gradients() {
+        let input = "<svg><defs><linearGradient id=\"s1\"><stop/></linearGradient><linearGradient id=\"s2\"><stop/></linearGradient><linearGradient id=\"g1\" href=\"#s1\" x2=\"1\"/><linearGradient id=\"g2\" href=\"#s2\" x2=\"1\"/></defs><path fill=\"url(#g1)\"/><path style=\"fill:url(#g2)\"/></svg>";
+        let expected = "<svg><defs><linearGradient id=\"s1\"><stop/></linearGradient><linearGradient id=\"g1\" href=\"#s1\" x2=\"1\"/></defs><path fill=\"url(#g1)\"/><path style=\"fill:url(#g1)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveDuplicateDefs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_referenced_children() {
+        let input = "<svg><clipPath id=\"a\"><rect id=\"r\"/></clipPath><clipPath id=\"b\"><rect id=\"r2\"/></clipPath><clipPath id=\"c\"><rect id=\"r3\"/></clipPath><use href=\"#r\"/><g clip-path=\"url(#a)\"/><g clip-path=\"url(#b)\"/><g clip-path=\"url(#c)\"/></svg>";
+        let expected = "<svg><clipPath id=\"a\"><rect id=\"r\"/></clipPath><clipPath id=\"b\"><rect id=\"r2\"/></clipPath><use href=\"#r\"/><g clip-path=\"url(#a)\"/><g clip-path=\"url(#b)\"/><g clip-path=\"url(#b)\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveDuplicateDefs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+}
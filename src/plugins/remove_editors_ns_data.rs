@@ -24,8 +24,7 @@ impl Plugin for RemoveEditorsNSData {
             for node in nodes {
                 if let Node::Element(elem) = node {
                     for (k, v) in &elem.attributes {
-                        if k.starts_with("xmlns:") {
-                            let prefix = &k[6..];
+                        if let Some(prefix) = k.strip_prefix("xmlns:") {
                             if ns_list.contains(&v.as_str()) {
                                 prefixes.insert(prefix.to_string());
                             }
@@ -61,8 +60,7 @@ fn remove_ns_data(nodes: &mut Vec<Node>, prefixes: &HashSet<String>, ns_list: &[
             // Remove attributes
             elem.attributes.retain(|k, v| {
                 // Remove xmlns:prefix="EDITOR_URI"
-                if k.starts_with("xmlns:") {
-                    let prefix = &k[6..];
+                if let Some(prefix) = k.strip_prefix("xmlns:") {
                     if prefixes.contains(prefix) && ns_list.contains(&v.as_str()) {
                         return false;
                     }
@@ -1,59 +1,76 @@
 use crate::plugins::Plugin;
-use crate::tree::{Document, Node};
+use crate::references::{defines_referenced_id, ReferenceIndex};
+use crate::style::{self, StyledNode};
+use crate::tree::{Document, Element, Node};
 
 pub struct RemoveHiddenElems;
 
 impl Plugin for RemoveHiddenElems {
     fn apply(&self, doc: &mut Document) {
-        remove_hidden_elems_from_nodes(&mut doc.root);
+        let styles = style::resolve(doc);
+        // Referenced elements may still be rendered elsewhere (gradients, <use>...)
+        let index = ReferenceIndex::build(doc);
+
+        // The root <svg> stays even when hidden
+        for (node, styled) in doc.root.iter_mut().zip(&styles) {
+            if let Node::Element(elem) = node {
+                remove_hidden_elems_from_nodes(&mut elem.children, &styled.children, &index);
+            }
+        }
     }
 }
 
-fn remove_hidden_elems_from_nodes(nodes: &mut Vec<Node>) {
-    nodes.retain(|node| {
+fn remove_hidden_elems_from_nodes(
+    nodes: &mut Vec<Node>,
+    styles: &[StyledNode],
+    index: &ReferenceIndex,
+) {
+    let mut keep = Vec::with_capacity(nodes.len());
+
+    for (node, styled) in nodes.iter_mut().zip(styles) {
+        let mut retain = true;
         if let Node::Element(elem) = node {
-            // Check display="none"
-            if let Some(val) = elem.attributes.get("display") {
-                if val == "none" {
-                    return false;
-                }
-            }
-            // Check opacity="0"
-            if let Some(val) = elem.attributes.get("opacity") {
-                if val == "0" {
-                    return false;
-                }
-            }
-            // Check circle with r="0"
-            if elem.name == "circle" {
-                if let Some(r) = elem.attributes.get("r") {
-                    if r == "0" {
-                        return false;
-                    }
-                }
-            }
-            // Check rect with width="0" or height="0"
-            if elem.name == "rect" {
-                if let Some(w) = elem.attributes.get("width") {
-                    if w == "0" {
-                        return false;
-                    }
-                }
-                if let Some(h) = elem.attributes.get("height") {
-                    if h == "0" {
-                        return false;
-                    }
-                }
+            if is_hidden(elem, styled) && !defines_referenced_id(elem, index) {
+                retain = false;
+            } else {
+                remove_hidden_elems_from_nodes(&mut elem.children, &styled.children, index);
             }
         }
-        true
-    });
+        keep.push(retain);
+    }
 
-    for node in nodes {
-        if let Node::Element(elem) = node {
-            remove_hidden_elems_from_nodes(&mut elem.children);
-        }
+    let mut keep = keep.into_iter();
+    nodes.retain(|_| keep.next().unwrap_or(true));
+}
+
+fn is_hidden(elem: &Element, styled: &StyledNode) -> bool {
+    let style = &styled.style;
+    // display and opacity aren't inherited, but hide the whole subtree.
+    // `value` returns None for anything set by :hover, media queries etc.
+    if style.value("display") == Some("none") {
+        return true;
+    }
+    if style
+        .value("opacity")
+        .and_then(|v| v.parse::<f64>().ok())
+        .is_some_and(|v| v == 0.0)
+    {
+        return true;
+    }
+
+    // Check circle with r="0"
+    if elem.name == "circle" && elem.attributes.get("r").is_some_and(|r| r == "0") {
+        return true;
+    }
+    // Check rect with width="0" or height="0"
+    if elem.name == "rect"
+        && ["width", "height"]
+            .iter()
+            .any(|a| elem.attributes.get(*a).is_some_and(|v| v == "0"))
+    {
+        return true;
     }
+    false
 }
 
 #[cfg(test)]
@@ -84,4 +101,14 @@ mod tests {
         let output = printer::print(&doc);
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn test_hidden_by_stylesheet() {
+        let input = "<svg><style>.h { display: none } @media print { .p { display: none } }</style><rect class=\"h\"/><rect class=\"p\"/><g style=\"opacity:0\"><path id=\"a\"/></g><use href=\"#a\"/></svg>";
+        let expected = "<svg><style>.h { display: none } @media print { .p { display: none } }</style><rect class=\"p\"/><g style=\"opacity:0\"><path id=\"a\"/></g><use href=\"#a\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveHiddenElems.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
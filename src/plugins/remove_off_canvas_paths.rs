@@ -0,0 +1,286 @@
+use crate::bbox::{BBoxes, Rect};
+use crate::plugins::Plugin;
+use crate::references::{self, NodePath, ReferenceIndex};
+use crate::style::{ComputedStyle, Origin};
+use crate::transform::Matrix;
+use crate::tree::{Document, Element, Node};
+
+/// Removes elements whose painted area lies entirely outside what the root
+/// viewport shows.
+pub struct RemoveOffCanvasPaths;
+
+impl Plugin for RemoveOffCanvasPaths {
+    fn apply(&self, doc: &mut Document) {
+        // Scripts may move things into view
+        if has_scripts(&doc.root) {
+            return;
+        }
+        let Some(root_index) = doc
+            .root
+            .iter()
+            .position(|node| matches!(node, Node::Element(e) if e.name == "svg"))
+        else {
+            return;
+        };
+        let Some(Node::Element(root)) = doc.root.get(root_index) else {
+            return;
+        };
+        if root.attributes.contains_key("transform") {
+            return;
+        }
+        let Some(canvas) = canvas(root) else {
+            return;
+        };
+
+        let bboxes = BBoxes::new(doc, true);
+        let index = ReferenceIndex::build(doc);
+        // Content outside the viewBox shows when the root doesn't clip
+        let root_path = vec![root_index];
+        let Some(style) = bboxes.style(&root_path) else {
+            return;
+        };
+        if !clips(style) {
+            return;
+        }
+
+        let mut off_canvas = Vec::new();
+        let walker = Walker {
+            bboxes: &bboxes,
+            index: &index,
+            canvas,
+        };
+        walker.walk(
+            root,
+            &mut root_path.clone(),
+            &Matrix::identity(),
+            &mut off_canvas,
+        );
+
+        // Later paths first, so earlier ones stay valid
+        off_canvas.sort();
+        for path in off_canvas.iter().rev() {
+            remove_at(doc, path);
+        }
+    }
+}
+
+fn has_scripts(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => elem.name == "script" || has_scripts(&elem.children),
+        _ => false,
+    })
+}
+
+// Whether the root hides what's outside its viewport. A standalone SVG
+// always does unless told otherwise.
+pub(crate) fn clips(style: &ComputedStyle) -> bool {
+    style.origin("overflow") == Origin::Initial
+        || matches!(style.value("overflow"), Some("hidden" | "scroll"))
+}
+
+// The root viewBox, or the viewport when there's none
+pub(crate) fn view_box(root: &Element) -> Option<Rect> {
+    if let Some(view_box) = root.attributes.get("viewBox") {
+        return Rect::from_view_box(view_box);
+    }
+    Some(Rect::new(
+        0.0,
+        0.0,
+        length(root, "width")?,
+        length(root, "height")?,
+    ))
+}
+
+// The part of the root's user space that shows: the viewBox letterboxed
+// into the viewport, or cut down with `slice`, the way
+// `preserveAspectRatio` places it. `None` when that depends on a viewport
+// size we don't know.
+pub(crate) fn canvas(root: &Element) -> Option<Rect> {
+    let Some(view_box) = root.attributes.get("viewBox") else {
+        return view_box(root);
+    };
+    let view_box = Rect::from_view_box(view_box)?;
+    let mut parts = root
+        .attributes
+        .get("preserveAspectRatio")
+        .map_or("xMidYMid meet", |s| s.as_str())
+        .split_whitespace()
+        .skip_while(|&p| p == "defer");
+    let align = parts.next().unwrap_or("xMidYMid");
+    if align == "none" {
+        return Some(view_box);
+    }
+    let (width, height) = (length(root, "width")?, length(root, "height")?);
+    if width <= 0.0 || height <= 0.0 || align.len() != 8 {
+        return None;
+    }
+    let (sx, sy) = (width / view_box.width(), height / view_box.height());
+    let scale = if parts.next() == Some("slice") {
+        sx.max(sy)
+    } else {
+        sx.min(sy)
+    };
+    let fraction = |a: &str| match a {
+        "Min" => Some(0.0),
+        "Mid" => Some(0.5),
+        "Max" => Some(1.0),
+        _ => None,
+    };
+    let (w, h) = (width / scale, height / scale);
+    let x = view_box.min_x - (w - view_box.width()) * fraction(&align[1..4])?;
+    let y = view_box.min_y - (h - view_box.height()) * fraction(&align[5..8])?;
+    Some(Rect::new(x, y, w, h))
+}
+
+fn length(elem: &Element, name: &str) -> Option<f64> {
+    elem.attributes
+        .get(name)?
+        .trim()
+        .trim_end_matches("px")
+        .parse()
+        .ok()
+}
+
+struct Walker<'a> {
+    bboxes: &'a BBoxes<'a>,
+    index: &'a ReferenceIndex,
+    canvas: Rect,
+}
+
+impl Walker<'_> {
+    fn walk(&self, elem: &Element, path: &mut NodePath, m: &Matrix, out: &mut Vec<NodePath>) {
+        for (i, child) in elem.children.iter().enumerate() {
+            let Node::Element(child) = child else {
+                continue;
+            };
+            path.push(i);
+            self.visit(child, path, m, out);
+            path.pop();
+        }
+    }
+
+    fn visit(&self, elem: &Element, path: &mut NodePath, m: &Matrix, out: &mut Vec<NodePath>) {
+        if is_non_rendering(&elem.name) {
+            return;
+        }
+        // Referenced content is drawn elsewhere too (<use>) or needed by
+        // something else, and animations can move things into view
+        let referenced = elem
+            .attributes
+            .get("id")
+            .is_some_and(|id| self.index.is_referenced(id))
+            || references::defines_referenced_id(elem, self.index);
+        if referenced || has_animations(elem) {
+            return;
+        }
+        let Some(style) = self.bboxes.style(path) else {
+            return;
+        };
+        if style.value("filter") != Some("none") {
+            return;
+        }
+
+        match self.bboxes.element(path, m) {
+            Some(rect) if !rect.intersects(&self.canvas) => out.push(path.clone()),
+            // Partly visible or unknown: look at the parts
+            _ if matches!(elem.name.as_str(), "g" | "a") => {
+                if let Some(local) = self.bboxes.local_transform(path) {
+                    self.walk(elem, path, &m.multiply(&local), out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn is_non_rendering(name: &str) -> bool {
+    matches!(
+        name,
+        "defs"
+            | "title"
+            | "desc"
+            | "metadata"
+            | "style"
+            | "symbol"
+            | "linearGradient"
+            | "radialGradient"
+            | "pattern"
+            | "clipPath"
+            | "mask"
+            | "filter"
+            | "marker"
+    )
+}
+
+fn has_animations(elem: &Element) -> bool {
+    elem.children.iter().any(|child| match child {
+        Node::Element(child) => {
+            matches!(
+                child.name.as_str(),
+                "animate" | "animateMotion" | "animateTransform" | "set"
+            ) || has_animations(child)
+        }
+        _ => false,
+    })
+}
+
+fn remove_at(doc: &mut Document, path: &[usize]) {
+    let Some((last, parent)) = path.split_last() else {
+        return;
+    };
+    let siblings = match parent {
+        [] => &mut doc.root,
+        _ => match doc.node_at_mut(parent) {
+            Some(Node::Element(elem)) => &mut elem.children,
+            _ => return,
+        },
+    };
+    if *last < siblings.len() {
+        siblings.remove(*last);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_remove_off_canvas() {
+        let input = "<svg width=\"100\" height=\"100\" viewBox=\"0 0 100 100\"><rect x=\"200\" width=\"10\" height=\"10\"/><path d=\"M-50-50h10v10h-10z\"/><g transform=\"translate(-300 0)\"><circle cx=\"350\" cy=\"50\" r=\"5\"/><circle cx=\"50\" cy=\"50\" r=\"5\"/></g><path d=\"M-20 0C-20 50 50 50 50 50\"/></svg>";
+        let expected = "<svg width=\"100\" height=\"100\" viewBox=\"0 0 100 100\"><g transform=\"translate(-300 0)\"><circle cx=\"350\" cy=\"50\" r=\"5\"/></g><path d=\"M-20 0C-20 50 50 50 50 50\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveOffCanvasPaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_reachable_content() {
+        // Stroke reaching in, referenced content, markers and visible overflow
+        let input = "<svg width=\"100\" height=\"100\" viewBox=\"0 0 100 100\"><rect x=\"-12\" width=\"10\" height=\"10\" stroke=\"red\" stroke-width=\"6\"/><path id=\"p\" d=\"M200 0h10\"/><use href=\"#p\" x=\"-150\"/><path d=\"M-10 0h-5\" marker-end=\"url(#m)\"/><svg x=\"-50\" width=\"10\" height=\"10\" overflow=\"visible\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveOffCanvasPaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+
+    #[test]
+    fn test_letterboxed_canvas() {
+        // The viewport shows 50 units either side of a square viewBox, and
+        // without a size, who knows
+        let input = "<svg width=\"200\" height=\"100\" viewBox=\"0 0 100 100\"><rect x=\"-40\" width=\"10\" height=\"10\"/><rect x=\"-70\" width=\"10\" height=\"10\"/></svg>";
+        let expected = "<svg width=\"200\" height=\"100\" viewBox=\"0 0 100 100\"><rect x=\"-40\" width=\"10\" height=\"10\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveOffCanvasPaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+
+        let input =
+            "<svg viewBox=\"0 0 100 100\"><rect x=\"-40\" width=\"10\" height=\"10\"/></svg>";
+        let mut doc = parser::parse(input).unwrap();
+        RemoveOffCanvasPaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+}
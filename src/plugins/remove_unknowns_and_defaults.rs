@@ -1,4 +1,6 @@
+use crate::plugins::collections::INHERITABLE_ATTRS;
 use crate::plugins::Plugin;
+use crate::style::{self, ComputedStyle, StyledNode};
 use crate::tree::{Document, Node};
 use std::collections::HashMap;
 
@@ -40,12 +42,23 @@ impl Default for RemoveUnknownsAndDefaults {
 
 impl Plugin for RemoveUnknownsAndDefaults {
     fn apply(&self, doc: &mut Document) {
-        process_nodes(&mut doc.root, &self.default_attrs);
+        let styles = style::resolve(doc);
+        process_nodes(
+            &mut doc.root,
+            &styles,
+            &ComputedStyle::default(),
+            &self.default_attrs,
+        );
     }
 }
 
-fn process_nodes(nodes: &mut Vec<Node>, defaults: &HashMap<&str, &str>) {
-    for node in nodes {
+fn process_nodes(
+    nodes: &mut [Node],
+    styles: &[StyledNode],
+    parent: &ComputedStyle,
+    defaults: &HashMap<&str, &str>,
+) {
+    for (node, styled) in nodes.iter_mut().zip(styles) {
         if let Node::Element(elem) = node {
             // Remove defaults
             // We need to check if value matches default.
@@ -57,6 +70,14 @@ fn process_nodes(nodes: &mut Vec<Node>, defaults: &HashMap<&str, &str>) {
 
             elem.attributes.retain(|k, v| {
                 if let Some(def) = defaults.get(k.as_str()) {
+                    // An inherited property falls back to the parent's value,
+                    // not the initial one
+                    if INHERITABLE_ATTRS.contains(&k.as_str())
+                        && parent.value(k).is_none_or(|p| p != *def)
+                    {
+                        return true;
+                    }
+
                     if v == *def {
                         return false;
                     }
@@ -78,7 +99,12 @@ fn process_nodes(nodes: &mut Vec<Node>, defaults: &HashMap<&str, &str>) {
             // That's Namespace cleanup.
             // Let's stick to Defaults here.
 
-            process_nodes(&mut elem.children, defaults);
+            process_nodes(
+                &mut elem.children,
+                &styled.children,
+                &styled.style,
+                defaults,
+            );
         }
     }
 }
@@ -98,4 +124,14 @@ mod tests {
         RemoveUnknownsAndDefaults::default().apply(&mut doc);
         assert_eq!(printer::print(&doc), expected);
     }
+
+    #[test]
+    fn test_keep_overriding_inherited() {
+        let input = "<svg><g stroke-width=\"2\"><rect stroke-width=\"1\"/></g><style>.a { fill-opacity: .5 }</style><g class=\"a\"><rect fill-opacity=\"1\"/></g><rect fill-opacity=\"1\"/></svg>";
+        let expected = "<svg><g stroke-width=\"2\"><rect stroke-width=\"1\"/></g><style>.a { fill-opacity: .5 }</style><g class=\"a\"><rect fill-opacity=\"1\"/></g><rect/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveUnknownsAndDefaults::default().apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
@@ -49,8 +49,7 @@ fn process_root(nodes: &mut Vec<Node>, used: &HashSet<String>) {
             let mut to_remove = Vec::new();
 
             for key in elem.attributes.keys() {
-                if key.starts_with("xmlns:") {
-                    let prefix = &key[6..];
+                if let Some(prefix) = key.strip_prefix("xmlns:") {
                     if !used.contains(prefix) {
                         to_remove.push(key.clone());
                     }
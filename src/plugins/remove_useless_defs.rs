@@ -1,39 +1,28 @@
-use crate::plugins::collections::find_used_ids;
 use crate::plugins::Plugin;
+use crate::references::{defines_referenced_id, ReferenceIndex};
 use crate::tree::{Document, Node};
-use std::collections::HashSet;
 
 pub struct RemoveUselessDefs;
 
 impl Plugin for RemoveUselessDefs {
     fn apply(&self, doc: &mut Document) {
-        let mut used_ids = HashSet::new();
-        for node in &doc.root {
-            find_used_ids(node, &mut used_ids);
-        }
-
-        remove_useless_defs_in_nodes(&mut doc.root, &used_ids);
+        let index = ReferenceIndex::build(doc);
+        remove_useless_defs_in_nodes(&mut doc.root, &index);
     }
 }
 
-fn remove_useless_defs_in_nodes(nodes: &mut Vec<Node>, used_ids: &HashSet<String>) {
+fn remove_useless_defs_in_nodes(nodes: &mut Vec<Node>, index: &ReferenceIndex) {
     // 1. Recurse first (to clean nested defs)
     for node in nodes.iter_mut() {
         if let Node::Element(elem) = node {
-            remove_useless_defs_in_nodes(&mut elem.children, used_ids);
+            remove_useless_defs_in_nodes(&mut elem.children, index);
 
             // If there is ANY defs element, filter its children now (mutable access)
             if elem.name == "defs" {
                 elem.children.retain(|child| {
                     if let Node::Element(child_elem) = child {
-                        // Keep if it has an ID and that ID is used
-                        if let Some(id) = child_elem.attributes.get("id") {
-                            if used_ids.contains(id) {
-                                return true;
-                            }
-                        }
-                        // Remove unused definitions
-                        return false;
+                        // Keep if it (or a wrapper's descendant) has a used ID
+                        return defines_referenced_id(child_elem, index);
                     }
                     // Remove non-element nodes in defs (text, comments)
                     false
@@ -82,4 +71,15 @@ mod tests {
         let output = printer::print(&doc);
         assert_eq!(output, expected);
     }
+
+    #[test]
+    fn test_color_is_not_reference() {
+        let input = "<svg><defs><rect id=\"f00\"/><g><path id=\"p\"/></g></defs><rect fill=\"#f00\"/><use href=\"#p\"/></svg>";
+        let expected =
+            "<svg><defs><g><path id=\"p\"/></g></defs><rect fill=\"#f00\"/><use href=\"#p\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        RemoveUselessDefs.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
 }
@@ -1,86 +1,135 @@
 use crate::plugins::Plugin;
-use crate::tree::{Document, Node};
+use crate::style::{self, ComputedStyle, Origin, StyledNode};
+use crate::tree::{Document, Element, Node};
 
 pub struct RemoveUselessStrokeAndFill;
 
 impl Plugin for RemoveUselessStrokeAndFill {
     fn apply(&self, doc: &mut Document) {
-        process_nodes(&mut doc.root);
+        let styles = style::resolve(doc);
+        process_nodes(&mut doc.root, &styles, &ComputedStyle::default());
     }
 }
 
-fn process_nodes(nodes: &mut Vec<Node>) {
-    nodes.retain(|node| {
+const STROKE_ATTRS: [&str; 8] = [
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+];
+
+const FILL_ATTRS: [&str; 3] = ["fill", "fill-opacity", "fill-rule"];
+
+fn process_nodes(nodes: &mut Vec<Node>, styles: &[StyledNode], parent: &ComputedStyle) {
+    let mut keep = Vec::with_capacity(nodes.len());
+
+    for (node, styled) in nodes.iter_mut().zip(styles) {
+        let mut retain = true;
         if let Node::Element(elem) = node {
-            // Check for invisible elements
-            // If it's a shape (rect, circle, etc or path) and has no visible stroke/fill
-            // Default fill is black! So fill="none" must be explicit.
-
-            let has_stroke = if let Some(s) = elem.attributes.get("stroke") {
-                s != "none"
-            } else {
-                false
-            };
-
-            let has_fill = if let Some(f) = elem.attributes.get("fill") {
-                f != "none"
-            } else {
-                true
-            }; // Default is black
-
-            // If stroke is present but stroke-width is 0, it's invisible stroke.
-            let visible_stroke = if has_stroke {
-                if let Some(w) = elem.attributes.get("stroke-width") {
-                    w != "0" && w != "0px"
-                } else {
-                    true
-                } // Default 1
-            } else {
-                false
-            };
-
-            // If no visible stroke and explicit fill="none", it's invisible.
-            // But we must separate "removing attribute" from "removing element".
-
-            // Keep element if it defines something? (defs are handled elsewhere).
-            // Only remove distinct shapes.
+            let style = &styled.style;
+            // Only remove distinct shapes; containers pass paint on to their children.
             let is_shape = matches!(
                 elem.name.as_str(),
                 "rect" | "circle" | "ellipse" | "line" | "polygon" | "polyline" | "path"
             );
 
-            if is_shape && !visible_stroke && !has_fill {
-                // Element is invisible
-                // check for id? If it has ID it might be referenced.
-                if !elem.attributes.contains_key("id") {
-                    return false;
+            if is_shape && !is_paint_dynamic(style) {
+                let visible_stroke = is_stroke_visible(style);
+                // Default is black! So fill="none" must be explicit.
+                let visible_fill =
+                    style.value("fill") != Some("none") && !is_zero(style.value("fill-opacity"));
+                let has_markers = ["marker-start", "marker-mid", "marker-end"]
+                    .iter()
+                    .any(|m| style.value(m) != Some("none"));
+
+                if !visible_stroke && !visible_fill && !has_markers {
+                    // Element is invisible
+                    // check for id? If it has ID it might be referenced.
+                    if !elem.attributes.contains_key("id") {
+                        retain = false;
+                    }
                 }
-            }
-        }
-        true
-    });
 
-    for node in nodes {
-        if let Node::Element(elem) = node {
-            // Attribute cleanup
-            if let Some(s) = elem.attributes.get("stroke") {
-                if s == "none" {
-                    elem.attributes.shift_remove("stroke");
+                if retain && !visible_stroke {
+                    remove_paint_attrs(elem, style, parent, &STROKE_ATTRS);
                 }
-            }
-            if let Some(w) = elem.attributes.get("stroke-width") {
-                if w == "0" || w == "0px" {
-                    elem.attributes.shift_remove("stroke"); // Remove stroke definition if width 0
-                    elem.attributes.shift_remove("stroke-width");
+                if retain && !visible_fill && style.value("fill") == Some("none") {
+                    remove_paint_attrs(elem, style, parent, &FILL_ATTRS);
                 }
             }
 
-            // If fill is none? We usually keep fill="none" because default is black.
-            // removing fill="none" makes it black!
+            process_nodes(&mut elem.children, &styled.children, style);
+        }
+        keep.push(retain);
+    }
+
+    let mut keep = keep.into_iter();
+    nodes.retain(|_| keep.next().unwrap_or(true));
+}
+
+fn is_paint_dynamic(style: &ComputedStyle) -> bool {
+    [
+        "fill",
+        "fill-opacity",
+        "stroke",
+        "stroke-width",
+        "stroke-opacity",
+        "marker-start",
+        "marker-mid",
+        "marker-end",
+    ]
+    .iter()
+    .any(|p| style.is_dynamic(p))
+}
+
+fn is_stroke_visible(style: &ComputedStyle) -> bool {
+    style.value("stroke") != Some("none")
+        && !is_zero(style.value("stroke-width"))
+        && !is_zero(style.value("stroke-opacity"))
+}
+
+fn is_zero(value: Option<&str>) -> bool {
+    value
+        .map(|v| v.trim_end_matches("px"))
+        .and_then(|v| v.parse::<f64>().ok())
+        .is_some_and(|n| n == 0.0)
+}
 
-            process_nodes(&mut elem.children);
+// Drop attributes of an invisible paint. Without them the element inherits
+// from its parent, so that's only safe when the parent paints nothing either.
+fn remove_paint_attrs(
+    elem: &mut Element,
+    style: &ComputedStyle,
+    parent: &ComputedStyle,
+    attrs: &[&str],
+) {
+    let paint = attrs[0];
+    let owned = attrs
+        .iter()
+        .any(|a| elem.attributes.contains_key(*a) && style.origin(a) == Origin::Attribute);
+    if !owned {
+        return;
+    }
+
+    let parent_paints = match paint {
+        "stroke" => is_stroke_visible(parent) || parent.is_dynamic("stroke"),
+        _ => parent.value("fill") != Some("none"),
+    };
+
+    for attr in attrs {
+        if (*attr != paint || !parent_paints) && style.origin(attr) == Origin::Attribute {
+            elem.attributes.shift_remove(*attr);
         }
     }
+    if parent_paints {
+        // Keeps the position of an existing attribute
+        elem.attributes
+            .insert(paint.to_string(), "none".to_string());
+    }
 }
 
 #[cfg(test)]
@@ -122,4 +171,23 @@ mod tests {
         // stroke and stroke-width removed.
         assert_eq!(printer::print(&doc), expected);
     }
+
+    #[test]
+    fn test_inherited_paint() {
+        // fill="none" comes from the group, so the path paints nothing
+        let input = "<svg><g fill=\"none\"><path d=\"M0 0\"/><path d=\"M1 1\" stroke=\"red\" stroke-width=\"0\"/></g><g stroke=\"red\"><rect stroke-width=\"0\"/></g></svg>";
+        let expected = "<svg><g fill=\"none\"/><g stroke=\"red\"><rect stroke=\"none\"/></g></svg>";
+        let mut doc = parser::parse(input).unwrap();
+        RemoveUselessStrokeAndFill.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_keep_dynamic_paint() {
+        let input =
+            "<svg><style>path:hover { stroke: red }</style><path d=\"M0 0\" fill=\"none\"/></svg>";
+        let mut doc = parser::parse(input).unwrap();
+        RemoveUselessStrokeAndFill.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
 }
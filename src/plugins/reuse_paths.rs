@@ -0,0 +1,287 @@
+use crate::path::{self, PathData, Segment, WriteOptions};
+use crate::plugins::Plugin;
+use crate::references::{NodePath, ReferenceIndex};
+use crate::tree::{Document, Element, Node};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+
+/// Replaces repeated paths that only differ by position with `<use>`
+/// elements pointing at a single copy in `<defs>`.
+pub struct ReusePaths;
+
+struct Candidate {
+    path: NodePath,
+    start: (f64, f64),
+}
+
+impl Plugin for ReusePaths {
+    fn apply(&self, doc: &mut Document) {
+        // Selectors like `path { ... }` would stop matching the <use> copies
+        if has_style_or_script(&doc.root) {
+            return;
+        }
+        let index = ReferenceIndex::build(doc);
+        let Some(root) = doc.root_element() else {
+            return;
+        };
+        let href_attr = if root.attributes.contains_key("xmlns:xlink") {
+            "xlink:href"
+        } else {
+            "href"
+        };
+
+        // Group paths by their shape, with the start point factored out
+        let mut groups: IndexMap<String, Vec<Candidate>> = IndexMap::new();
+        collect_candidates(&doc.root, &index, &mut Vec::new(), &mut groups);
+
+        // A new <defs> costs `<defs></defs>`
+        let mut defs_cost = if has_defs(root) { 0 } else { 13 };
+        let mut taken: HashSet<String> = index.ids().map(String::from).collect();
+        let mut counter = 0;
+        let mut defs_paths = Vec::new();
+
+        for candidates in groups.values() {
+            if candidates.len() < 2 {
+                continue;
+            }
+            let Some(Node::Element(first)) = doc.node_at(&candidates[0].path) else {
+                continue;
+            };
+            let mut id = format!("reuse-{}", counter);
+            while taken.contains(&id) {
+                counter += 1;
+                id = format!("reuse-{}", counter);
+            }
+
+            let mut def = Element::new("path");
+            def.attributes.insert("id".to_string(), id.clone());
+            for name in ["d", "pathLength"] {
+                if let Some(value) = first.attributes.get(name) {
+                    def.attributes.insert(name.to_string(), value.clone());
+                }
+            }
+
+            let uses: Vec<Element> = candidates
+                .iter()
+                .filter_map(|c| match doc.node_at(&c.path) {
+                    Some(Node::Element(elem)) => {
+                        Some(make_use(elem, &id, href_attr, c.start, candidates[0].start))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            // Only worth it when the markup gets smaller
+            let before: usize = candidates
+                .iter()
+                .filter_map(|c| match doc.node_at(&c.path) {
+                    Some(Node::Element(elem)) => Some(element_len(elem)),
+                    _ => None,
+                })
+                .sum();
+            let after = defs_cost + element_len(&def) + uses.iter().map(element_len).sum::<usize>();
+            if after >= before {
+                continue;
+            }
+
+            for (candidate, use_elem) in candidates.iter().zip(uses) {
+                if let Some(node) = doc.node_at_mut(&candidate.path) {
+                    *node = Node::Element(use_elem);
+                }
+            }
+            taken.insert(id);
+            counter += 1;
+            defs_cost = 0;
+            defs_paths.push(Node::Element(def));
+        }
+
+        if !defs_paths.is_empty() {
+            insert_into_defs(doc, defs_paths);
+        }
+    }
+}
+
+fn has_style_or_script(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Element(elem) => {
+            elem.name == "style" || elem.name == "script" || has_style_or_script(&elem.children)
+        }
+        _ => false,
+    })
+}
+
+fn collect_candidates(
+    nodes: &[Node],
+    index: &ReferenceIndex,
+    path: &mut NodePath,
+    groups: &mut IndexMap<String, Vec<Candidate>>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        let Node::Element(elem) = node else {
+            continue;
+        };
+        path.push(i);
+
+        if elem.name == "path" {
+            // Referenced paths may be needed as paths (textPath, mpath), and
+            // animations of `d` would end up on the <use>
+            let referenced = elem
+                .attributes
+                .get("id")
+                .is_some_and(|id| index.is_referenced(id));
+            let animated = elem.children.iter().any(|child| {
+                matches!(child, Node::Element(child) if matches!(
+                    child.name.as_str(),
+                    "animate" | "animateMotion" | "animateTransform" | "set"
+                ))
+            });
+            if !referenced && !animated {
+                if let Some((key, start)) = elem.attributes.get("d").and_then(|d| shape_key(d)) {
+                    let key = match elem.attributes.get("pathLength") {
+                        Some(len) => format!("{} {}", key, len),
+                        None => key,
+                    };
+                    groups.entry(key).or_default().push(Candidate {
+                        path: path.clone(),
+                        start,
+                    });
+                }
+            }
+        } else if elem.name != "clipPath" {
+            // Clip paths only allow <use> of basic shapes, don't bother
+            collect_candidates(&elem.children, index, path, groups);
+        }
+
+        path.pop();
+    }
+}
+
+// The path data translated so that it starts at the origin, plus its start
+fn shape_key(d: &str) -> Option<(String, (f64, f64))> {
+    let path = PathData::parse(d);
+    let Some(Segment::MoveTo { x: x0, y: y0 }) = path.segments.first().copied() else {
+        return None;
+    };
+    let normalized = PathData {
+        segments: path
+            .segments
+            .iter()
+            .map(|segment| segment.translate(-x0, -y0))
+            .collect(),
+    };
+    // Rounding away float noise from subtracting the start point
+    let key = normalized.write(&WriteOptions {
+        precision: Some(6),
+        ..WriteOptions::default()
+    });
+    Some((key, (x0, y0)))
+}
+
+// Rounds away float noise from subtracting start points
+fn fmt(n: f64) -> String {
+    path::format_number(
+        n,
+        &WriteOptions {
+            precision: Some(6),
+            ..WriteOptions::default()
+        },
+    )
+}
+
+fn make_use(
+    path: &Element,
+    id: &str,
+    href_attr: &str,
+    start: (f64, f64),
+    origin: (f64, f64),
+) -> Element {
+    let mut use_elem = Element::new("use");
+    use_elem
+        .attributes
+        .insert(href_attr.to_string(), format!("#{}", id));
+    let (dx, dy) = (fmt(start.0 - origin.0), fmt(start.1 - origin.1));
+    if dx != "0" {
+        use_elem.attributes.insert("x".to_string(), dx);
+    }
+    if dy != "0" {
+        use_elem.attributes.insert("y".to_string(), dy);
+    }
+    // Everything else applies to the <use> just like to the path: presentation
+    // attributes are inherited, transform/clip-path/opacity act on the whole.
+    for (name, value) in &path.attributes {
+        if name != "d" && name != "pathLength" {
+            use_elem.attributes.insert(name.clone(), value.clone());
+        }
+    }
+    use_elem.children = path.children.clone();
+    use_elem
+}
+
+// Serialized size of an element without children, as the printer writes it
+fn element_len(elem: &Element) -> usize {
+    let attrs: usize = elem
+        .attributes
+        .iter()
+        .map(|(k, v)| k.len() + v.len() + 4)
+        .sum();
+    elem.name.len() + attrs + 3
+}
+
+fn has_defs(root: &Element) -> bool {
+    root.children
+        .iter()
+        .any(|node| matches!(node, Node::Element(elem) if elem.name == "defs"))
+}
+
+fn insert_into_defs(doc: &mut Document, paths: Vec<Node>) {
+    let Some(root) = doc.root_element_mut() else {
+        return;
+    };
+    let defs = root.children.iter_mut().find_map(|node| match node {
+        Node::Element(elem) if elem.name == "defs" => Some(elem),
+        _ => None,
+    });
+    match defs {
+        Some(defs) => defs.children.extend(paths),
+        None => {
+            let mut defs = Element::new("defs");
+            defs.children = paths;
+            root.children.insert(0, Node::Element(defs));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_reuse_translated_paths() {
+        let input = "<svg><path d=\"M10 10c5.5 0 10 4.5 10 10s-4.5 10-10 10S0 25.5 0 20h20v20h-20z\" fill=\"red\"/><path d=\"M50 10C55.5 10 60 14.5 60 20s-4.5 10-10 10S40 25.5 40 20H60V40H40z\" fill=\"blue\"/><g transform=\"scale(2)\"><path d=\"M10 60c5.5 0 10 4.5 10 10s-4.5 10-10 10S0 75.5 0 70h20v20h-20z\"/></g></svg>";
+        let expected = "<svg><defs><path id=\"reuse-0\" d=\"M10 10c5.5 0 10 4.5 10 10s-4.5 10-10 10S0 25.5 0 20h20v20h-20z\"/></defs><use href=\"#reuse-0\" fill=\"red\"/><use href=\"#reuse-0\" x=\"40\" fill=\"blue\"/><g transform=\"scale(2)\"><use href=\"#reuse-0\" y=\"50\"/></g></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ReusePaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), expected);
+    }
+
+    #[test]
+    fn test_skip_when_not_smaller() {
+        let input = "<svg><path d=\"M0 0h1\"/><path d=\"M5 5h1\"/><path d=\"M5 5h2\" id=\"p\"/><textPath href=\"#p\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ReusePaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+
+    #[test]
+    fn test_skip_animated_paths() {
+        let input = "<svg><path d=\"M10 10c5.5 0 10 4.5 10 10s-4.5 10-10 10S0 25.5 0 20h20v20h-20z\"><animate attributeName=\"d\" to=\"M0 0h20v20z\"/></path><path d=\"M50 10c5.5 0 10 4.5 10 10s-4.5 10-10 10S40 25.5 40 20h20v20h-20z\"/><path d=\"M90 10c5.5 0 10 4.5 10 10s-4.5 10-10 10S80 25.5 80 20h20v20h-20z\"/></svg>";
+
+        let mut doc = parser::parse(input).unwrap();
+        ReusePaths.apply(&mut doc);
+        assert_eq!(printer::print(&doc), input);
+    }
+}
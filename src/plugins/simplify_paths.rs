@@ -0,0 +1,717 @@
+use crate::path::{self, CoordMode, PathData, Point, Segment, WriteOptions};
+use crate::plugins::remove_off_canvas_paths::view_box;
+use crate::plugins::Plugin;
+use crate::precision::{self, ErrorBudget, ErrorNode};
+use crate::style::{self, StyledNode};
+use crate::transform::{self, Matrix};
+use crate::tree::{Document, Element, Node};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Lossy: drops points from polylines, and optionally refits them with
+/// cubic curves, as long as the outline moves less than the tolerance.
+pub struct SimplifyPaths {
+    pub tolerance: Tolerance,
+    pub algorithm: Simplification,
+    /// Replace polylines with smooth curves where that takes fewer points
+    pub fit_curves: bool,
+    pub float_precision: usize,
+    /// Round each element to what its rendered size needs instead of
+    /// `float_precision`
+    pub error_budget: Option<ErrorBudget>,
+    // Points in the paths looked at, before and after
+    points: Cell<(usize, usize)>,
+}
+
+/// How far the outline may move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tolerance {
+    /// In user units of the root element
+    Units(f64),
+    /// A fraction of the larger side of the root viewBox
+    ViewBox(f64),
+}
+
+/// Which points go.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Simplification {
+    /// Ramer–Douglas–Peucker: keeps the points further than the tolerance
+    /// from the line between the points kept around them
+    #[default]
+    RamerDouglasPeucker,
+    /// Visvalingam–Whyatt: drops the points whose triangle with their
+    /// neighbours is smaller than the tolerance squared
+    Visvalingam,
+}
+
+impl Default for SimplifyPaths {
+    fn default() -> Self {
+        Self::new(Tolerance::ViewBox(0.001))
+    }
+}
+
+impl SimplifyPaths {
+    pub fn new(tolerance: Tolerance) -> Self {
+        Self {
+            tolerance,
+            algorithm: Simplification::default(),
+            fit_curves: false,
+            float_precision: 3,
+            error_budget: None,
+            points: Cell::new((0, 0)),
+        }
+    }
+
+    fn count(&self, before: usize, after: usize) {
+        let (b, a) = self.points.get();
+        self.points.set((b + before, a + after));
+    }
+}
+
+impl Plugin for SimplifyPaths {
+    fn apply(&self, doc: &mut Document) {
+        self.points.set((0, 0));
+        let tolerance = match self.tolerance {
+            Tolerance::Units(t) => t,
+            Tolerance::ViewBox(f) => match doc.root_element().and_then(view_box) {
+                Some(rect) => f * rect.width().max(rect.height()),
+                None => return,
+            },
+        };
+        if tolerance <= 0.0 {
+            return;
+        }
+        let styles = style::resolve(doc);
+        let errors = self.error_budget.map(|b| b.resolve(doc, &styles));
+        self.process(
+            &mut doc.root,
+            &styles,
+            errors.as_deref(),
+            &Matrix::identity(),
+            tolerance,
+            true,
+        );
+    }
+
+    fn report(&self) -> Option<String> {
+        let (before, after) = self.points.get();
+        Some(format!("simplifyPaths: {} -> {} points", before, after))
+    }
+}
+
+impl SimplifyPaths {
+    // Only content drawn directly: whatever sits in <defs>, <symbol>, a
+    // nested <svg viewBox> and the like is drawn in coordinates we don't
+    // know here
+    fn process(
+        &self,
+        nodes: &mut [Node],
+        styles: &[StyledNode],
+        errors: Option<&[ErrorNode]>,
+        ctm: &Matrix,
+        tolerance: f64,
+        top_level: bool,
+    ) {
+        for (i, (node, styled)) in nodes.iter_mut().zip(styles).enumerate() {
+            let Node::Element(elem) = node else {
+                continue;
+            };
+            let error = errors.and_then(|e| e.get(i));
+            let Some(local) = transform::element_transform(elem, &styled.style) else {
+                continue;
+            };
+            let m = ctm.multiply(&local);
+            match elem.name.as_str() {
+                "svg" if !top_level && elem.attributes.contains_key("viewBox") => {}
+                "svg" | "g" | "a" | "switch" => self.process(
+                    &mut elem.children,
+                    &styled.children,
+                    error.map(|e| e.children.as_slice()),
+                    &m,
+                    tolerance,
+                    false,
+                ),
+                "path" | "polyline" | "polygon" => {
+                    // Markers sit on the vertices
+                    let has_markers = ["marker-start", "marker-mid", "marker-end"]
+                        .iter()
+                        .any(|p| styled.style.value(p) != Some("none"));
+                    let scale = m.max_scale();
+                    if !has_markers && scale > 0.0 {
+                        let precision = error
+                            .and_then(|e| e.error)
+                            .map_or(self.float_precision, precision::decimals);
+                        self.simplify_element(elem, tolerance / scale, precision);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn simplify_element(&self, elem: &mut Element, tolerance: f64, precision: usize) {
+        let opts = WriteOptions {
+            precision: Some(precision),
+            remove_leading_zero: false,
+            mode: CoordMode::Shortest,
+        };
+        if elem.name == "path" {
+            let Some(d) = elem.attributes.get_mut("d") else {
+                return;
+            };
+            let path = PathData::parse(d);
+            let segments = self.simplify_path(&path, tolerance);
+            let (before, after) = (count_points(&path.segments), count_points(&segments));
+            self.count(before, after);
+            if after < before {
+                *d = PathData { segments }.write(&opts);
+            }
+            return;
+        }
+
+        let Some(points) = elem.attributes.get_mut("points") else {
+            return;
+        };
+        let nums: Vec<f64> = points
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map_while(|s| s.parse().ok())
+            .collect();
+        let dense: Vec<Point> = nums
+            .chunks_exact(2)
+            .map(|xy| Point::new(xy[0], xy[1]))
+            .collect();
+        // Curves would need the element to become a <path>
+        let kept = simplify_polyline(&dense, elem.name == "polygon", self.algorithm, tolerance);
+        self.count(dense.len(), kept.len());
+        if kept.len() < dense.len() {
+            *points = kept
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{},{}",
+                        path::format_number(p.x, &opts),
+                        path::format_number(p.y, &opts)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+    }
+
+    // Subpaths made of lines get simplified; any with curves stay as they are
+    fn simplify_path(&self, path: &PathData, tolerance: f64) -> Vec<Segment> {
+        let steps: Vec<_> = path.iter().collect();
+        let mut out = Vec::with_capacity(steps.len());
+        let mut start = 0;
+        while start < steps.len() {
+            let end = steps[start + 1..]
+                .iter()
+                .position(|s| matches!(s.segment, Segment::MoveTo { .. }))
+                .map_or(steps.len(), |i| start + 1 + i);
+            let subpath = &steps[start..end];
+            start = end;
+
+            let closed = matches!(subpath.last().map(|s| s.segment), Some(Segment::Close));
+            let lines = &subpath[..subpath.len() - closed as usize];
+            let is_polyline = lines.len() >= 3
+                && matches!(lines[0].segment, Segment::MoveTo { .. })
+                && lines[1..].iter().all(|s| {
+                    matches!(
+                        s.segment,
+                        Segment::LineTo { .. }
+                            | Segment::HorizontalTo { .. }
+                            | Segment::VerticalTo { .. }
+                    )
+                });
+            if !is_polyline {
+                out.extend(subpath.iter().map(|s| s.segment));
+                continue;
+            }
+
+            let mut dense: Vec<Point> = lines.iter().map(|s| s.to).collect();
+            // `Z` draws the last line itself
+            if closed && dense.len() > 1 && dense.last() == dense.first() {
+                dense.pop();
+            }
+            let kept = simplify_polyline(&dense, closed, self.algorithm, tolerance);
+            let mut segments = polyline_segments(&kept, closed);
+            if self.fit_curves {
+                let fitted = fit_curves(&dense, closed, tolerance);
+                if count_points(&fitted) < count_points(&segments) {
+                    segments = fitted;
+                }
+            }
+            out.extend(segments);
+        }
+        out
+    }
+}
+
+fn count_points(segments: &[Segment]) -> usize {
+    segments
+        .iter()
+        .map(|s| match s {
+            Segment::Close => 0,
+            Segment::CubicTo { .. } => 3,
+            Segment::SmoothCubicTo { .. } | Segment::QuadTo { .. } => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn polyline_segments(points: &[Point], closed: bool) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| match i {
+            0 => Segment::MoveTo { x: p.x, y: p.y },
+            _ => Segment::LineTo { x: p.x, y: p.y },
+        })
+        .collect();
+    if closed {
+        segments.push(Segment::Close);
+    }
+    segments
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    Point::new(a.x - b.x, a.y - b.y)
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let ab = sub(b, a);
+    let len2 = dot(ab, ab);
+    if len2 == 0.0 {
+        return distance(p, a);
+    }
+    let t = (dot(sub(p, a), ab) / len2).clamp(0.0, 1.0);
+    distance(p, Point::new(a.x + t * ab.x, a.y + t * ab.y))
+}
+
+fn simplify_polyline(
+    points: &[Point],
+    closed: bool,
+    algorithm: Simplification,
+    tolerance: f64,
+) -> Vec<Point> {
+    let simplify = |points: &[Point]| match algorithm {
+        Simplification::RamerDouglasPeucker => ramer_douglas_peucker(points, tolerance),
+        Simplification::Visvalingam => visvalingam(points, tolerance * tolerance),
+    };
+    if !closed {
+        return simplify(points);
+    }
+    if points.len() < 4 {
+        return points.to_vec();
+    }
+    // A ring is two chains between its start and the point furthest away
+    let far = (1..points.len())
+        .max_by(|&i, &j| distance(points[0], points[i]).total_cmp(&distance(points[0], points[j])))
+        .unwrap_or(1);
+    let mut kept = simplify(&points[..=far]);
+    let mut back = points[far..].to_vec();
+    back.push(points[0]);
+    let back = simplify(&back);
+    kept.extend_from_slice(&back[1..back.len() - 1]);
+    if kept.len() < 3 {
+        return points.to_vec();
+    }
+    kept
+}
+
+fn ramer_douglas_peucker(points: &[Point], tolerance: f64) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let furthest = (first + 1..last)
+            .map(|i| (i, segment_distance(points[i], points[first], points[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, d)) = furthest {
+            if d > tolerance {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect()
+}
+
+// A point that could go next, smallest area first
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .area
+            .total_cmp(&self.area)
+            .then(other.index.cmp(&self.index))
+    }
+}
+
+fn visvalingam(points: &[Point], min_area: f64) -> Vec<Point> {
+    let n = points.len();
+    if n < 3 {
+        return points.to_vec();
+    }
+    let mut prev: Vec<usize> = (0..n).map(|i| i.saturating_sub(1)).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1).min(n - 1)).collect();
+    let mut area = vec![f64::INFINITY; n];
+    let mut removed = vec![false; n];
+    let triangle = |a: Point, b: Point, c: Point| {
+        ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0
+    };
+
+    let mut heap = BinaryHeap::new();
+    for i in 1..n - 1 {
+        area[i] = triangle(points[i - 1], points[i], points[i + 1]);
+        heap.push(Candidate {
+            area: area[i],
+            index: i,
+        });
+    }
+    // The area a point's removal is charged with never drops below that of
+    // points removed before it, so the order stays meaningful
+    let mut floor: f64 = 0.0;
+    while let Some(Candidate { area: a, index: i }) = heap.pop() {
+        if removed[i] || a != area[i] {
+            continue;
+        }
+        if a >= min_area {
+            break;
+        }
+        floor = floor.max(a);
+        removed[i] = true;
+        let (p, q) = (prev[i], next[i]);
+        next[p] = q;
+        prev[q] = p;
+        for j in [p, q] {
+            if j == 0 || j == n - 1 {
+                continue;
+            }
+            area[j] = triangle(points[prev[j]], points[j], points[next[j]]).max(floor);
+            heap.push(Candidate {
+                area: area[j],
+                index: j,
+            });
+        }
+    }
+    points
+        .iter()
+        .zip(removed)
+        .filter_map(|(p, removed)| (!removed).then_some(*p))
+        .collect()
+}
+
+// Turns sharper than this start a new curve
+const CORNER_ANGLE: f64 = std::f64::consts::FRAC_PI_4;
+
+fn normalize(v: Point) -> Point {
+    let len = v.x.hypot(v.y);
+    if len == 0.0 {
+        v
+    } else {
+        Point::new(v.x / len, v.y / len)
+    }
+}
+
+// The direction leaving `a` towards `b` and `c`, from the parabola through
+// them: the first chord alone is off by half the turn at `b`
+fn end_tangent(a: Point, b: Point, c: Point) -> Point {
+    normalize(Point::new(
+        4.0 * b.x - 3.0 * a.x - c.x,
+        4.0 * b.y - 3.0 * a.y - c.y,
+    ))
+}
+
+// Fits cubics to the polyline (Schneider's algorithm from Graphics Gems),
+// keeping sharp corners as they are
+fn fit_curves(points: &[Point], closed: bool, tolerance: f64) -> Vec<Segment> {
+    let mut dense = points.to_vec();
+    if closed {
+        dense.push(points[0]);
+    }
+    dense.dedup();
+    let mut out = vec![Segment::MoveTo {
+        x: dense[0].x,
+        y: dense[0].y,
+    }];
+    let mut start = 0;
+    for i in 1..dense.len() {
+        let is_corner = i == dense.len() - 1 || {
+            let (a, b) = (sub(dense[i], dense[i - 1]), sub(dense[i + 1], dense[i]));
+            let turn = (a.x * b.y - a.y * b.x).atan2(dot(a, b)).abs();
+            turn > CORNER_ANGLE
+        };
+        if is_corner {
+            let run = &dense[start..=i];
+            if run.len() < 3 {
+                out.push(Segment::LineTo {
+                    x: dense[i].x,
+                    y: dense[i].y,
+                });
+            } else {
+                let t1 = end_tangent(run[0], run[1], run[2]);
+                let n = run.len();
+                let t2 = end_tangent(run[n - 1], run[n - 2], run[n - 3]);
+                fit_cubic(run, t1, t2, tolerance, &mut out);
+            }
+            start = i;
+        }
+    }
+    if closed {
+        out.push(Segment::Close);
+    }
+    out
+}
+
+type Cubic = [Point; 4];
+
+fn cubic_at(c: &Cubic, t: f64) -> Point {
+    let mt = 1.0 - t;
+    let (a, b, d, e) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+    Point::new(
+        a * c[0].x + b * c[1].x + d * c[2].x + e * c[3].x,
+        a * c[0].y + b * c[1].y + d * c[2].y + e * c[3].y,
+    )
+}
+
+fn fit_cubic(points: &[Point], t1: Point, t2: Point, tolerance: f64, out: &mut Vec<Segment>) {
+    let last = points.len() - 1;
+    if last == 1 {
+        out.push(Segment::LineTo {
+            x: points[1].x,
+            y: points[1].y,
+        });
+        return;
+    }
+    // Chord length parameters
+    let mut u = vec![0.0; points.len()];
+    for i in 1..=last {
+        u[i] = u[i - 1] + distance(points[i], points[i - 1]);
+    }
+    let total = u[last];
+    u.iter_mut().for_each(|t| *t /= total);
+
+    let mut cubic = generate_cubic(points, &u, t1, t2);
+    let (mut error, mut split) = max_error(points, &cubic, &u);
+    // Chord lengths are only a first guess at where each point sits
+    for _ in 0..4 {
+        if error <= tolerance {
+            break;
+        }
+        for (t, p) in u.iter_mut().zip(points) {
+            *t = newton_step(&cubic, *p, *t);
+        }
+        cubic = generate_cubic(points, &u, t1, t2);
+        (error, split) = max_error(points, &cubic, &u);
+    }
+    if error <= tolerance {
+        out.push(Segment::CubicTo {
+            x1: cubic[1].x,
+            y1: cubic[1].y,
+            x2: cubic[2].x,
+            y2: cubic[2].y,
+            x: cubic[3].x,
+            y: cubic[3].y,
+        });
+        return;
+    }
+    let split = split.clamp(1, last - 1);
+    let center = normalize(sub(points[split - 1], points[split + 1]));
+    fit_cubic(&points[..=split], t1, center, tolerance, out);
+    let opposite = Point::new(-center.x, -center.y);
+    fit_cubic(&points[split..], opposite, t2, tolerance, out);
+}
+
+// Least squares control points along the end tangents
+fn generate_cubic(points: &[Point], u: &[f64], t1: Point, t2: Point) -> Cubic {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut c = [[0.0; 2]; 2];
+    let mut x = [0.0; 2];
+    for (p, &t) in points.iter().zip(u) {
+        let mt = 1.0 - t;
+        let (b0, b1, b2, b3) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+        let a0 = Point::new(t1.x * b1, t1.y * b1);
+        let a1 = Point::new(t2.x * b2, t2.y * b2);
+        c[0][0] += dot(a0, a0);
+        c[0][1] += dot(a0, a1);
+        c[1][1] += dot(a1, a1);
+        let rest = Point::new(
+            p.x - (first.x * (b0 + b1) + last.x * (b2 + b3)),
+            p.y - (first.y * (b0 + b1) + last.y * (b2 + b3)),
+        );
+        x[0] += dot(a0, rest);
+        x[1] += dot(a1, rest);
+    }
+    c[1][0] = c[0][1];
+    let det = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let (mut alpha1, mut alpha2) = if det.abs() > 1e-12 {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+    // Degenerate fits fall back to a third of the chord
+    let chord = distance(first, last);
+    if alpha1 < 1e-6 * chord || alpha2 < 1e-6 * chord {
+        alpha1 = chord / 3.0;
+        alpha2 = chord / 3.0;
+    }
+    [
+        first,
+        Point::new(first.x + t1.x * alpha1, first.y + t1.y * alpha1),
+        Point::new(last.x + t2.x * alpha2, last.y + t2.y * alpha2),
+        last,
+    ]
+}
+
+fn max_error(points: &[Point], cubic: &Cubic, u: &[f64]) -> (f64, usize) {
+    points
+        .iter()
+        .zip(u)
+        .enumerate()
+        .map(|(i, (p, &t))| (distance(cubic_at(cubic, t), *p), i))
+        .fold(
+            (0.0, points.len() / 2),
+            |best, e| {
+                if e.0 > best.0 {
+                    e
+                } else {
+                    best
+                }
+            },
+        )
+}
+
+// Moves `t` closer to the point on the curve nearest to `p`
+fn newton_step(c: &Cubic, p: Point, t: f64) -> f64 {
+    let q = cubic_at(c, t);
+    let mt = 1.0 - t;
+    let d1 = |i: usize| sub(c[i + 1], c[i]);
+    let (a, b, e) = (d1(0), d1(1), d1(2));
+    let q1 = Point::new(
+        3.0 * (mt * mt * a.x + 2.0 * mt * t * b.x + t * t * e.x),
+        3.0 * (mt * mt * a.y + 2.0 * mt * t * b.y + t * t * e.y),
+    );
+    let q2 = Point::new(
+        6.0 * (mt * (b.x - a.x) + t * (e.x - b.x)),
+        6.0 * (mt * (b.y - a.y) + t * (e.y - b.y)),
+    );
+    let diff = sub(q, p);
+    let denominator = dot(q1, q1) + dot(diff, q2);
+    if denominator.abs() < 1e-12 {
+        return t;
+    }
+    (t - dot(diff, q1) / denominator).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    fn run(plugin: &SimplifyPaths, input: &str) -> String {
+        let mut doc = parser::parse(input).unwrap();
+        plugin.apply(&mut doc);
+        printer::print(&doc)
+    }
+
+    #[test]
+    fn test_ramer_douglas_peucker() {
+        let plugin = SimplifyPaths::new(Tolerance::Units(0.5));
+        let input = "<svg><path d=\"M0 0L5 0.1L10 0L10 10L5 9.9L0 10Z\"/><polyline points=\"0,0 1,0.2 2,0 3,3\"/></svg>";
+        let expected = "<svg><path d=\"M0 0H10V10H0z\"/><polyline points=\"0,0 2,0 3,3\"/></svg>";
+        // The report covers the last run only
+        run(&plugin, input);
+        assert_eq!(run(&plugin, input), expected);
+        assert_eq!(
+            plugin.report().as_deref(),
+            Some("simplifyPaths: 10 -> 7 points")
+        );
+    }
+
+    #[test]
+    fn test_visvalingam_and_view_box_tolerance() {
+        // 1% of 100 is a unit, which the scaled group shrinks to a tenth;
+        // the nested viewBox scales by an amount left alone here
+        let mut plugin = SimplifyPaths::new(Tolerance::ViewBox(0.01));
+        plugin.algorithm = Simplification::Visvalingam;
+        let input = "<svg viewBox=\"0 0 100 100\"><path d=\"M0 0L5 0.1L10 0L10 10\"/><g transform=\"scale(10)\"><path d=\"M0 0L5 0.2L10 0\"/></g><svg width=\"100\" height=\"100\" viewBox=\"0 0 1 1\"><path d=\"M0 0L5 0.1L10 0\"/></svg></svg>";
+        let expected = "<svg viewBox=\"0 0 100 100\"><path d=\"M0 0H10V10\"/><g transform=\"scale(10)\"><path d=\"M0 0L5 0.2L10 0\"/></g><svg width=\"100\" height=\"100\" viewBox=\"0 0 1 1\"><path d=\"M0 0L5 0.1L10 0\"/></svg></svg>";
+        assert_eq!(run(&plugin, input), expected);
+    }
+
+    #[test]
+    fn test_fit_curves() {
+        // A dense half circle becomes a few cubics
+        let d: String = (0..=32)
+            .map(|i| {
+                let a = std::f64::consts::PI * i as f64 / 32.0;
+                let cmd = if i == 0 { 'M' } else { 'L' };
+                format!("{}{:.4} {:.4}", cmd, 50.0 - 50.0 * a.cos(), -50.0 * a.sin())
+            })
+            .collect();
+        let mut plugin = SimplifyPaths::new(Tolerance::Units(0.1));
+        plugin.fit_curves = true;
+        let out = run(&plugin, &format!("<svg><path d=\"{}\"/></svg>", d));
+        let (before, after) = plugin.points.get();
+        assert_eq!(before, 33);
+        assert!(after <= 10, "{} points: {}", after, out);
+        assert!(out.contains('C') || out.contains('c'), "{}", out);
+    }
+
+    #[test]
+    fn test_error_budget() {
+        // 0.01px across 1000px of a one unit viewBox needs five decimals
+        let mut plugin = SimplifyPaths::new(Tolerance::Units(0.001));
+        plugin.error_budget = Some(ErrorBudget {
+            error: 0.01,
+            size: None,
+        });
+        let input = "<svg viewBox=\"0 0 1 1\" width=\"1000\"><path d=\"M0.123456 0.123456L0.3 0.1234561L0.5 0.123456L0.5 0.5\"/></svg>";
+        let expected =
+            "<svg viewBox=\"0 0 1 1\" width=\"1000\"><path d=\"M0.12346 0.12346H0.5V0.5\"/></svg>";
+        assert_eq!(run(&plugin, input), expected);
+    }
+}
@@ -0,0 +1,184 @@
+//! Rounding budgets derived from how big the SVG ends up on screen.
+//!
+//! A fixed number of decimals wastes bytes on a 10000-unit map and destroys
+//! detail on a `0 0 1 1` icon. An [`ErrorBudget`] instead says how far, in
+//! rendered pixels, rounding may move anything; [`ErrorBudget::resolve`]
+//! turns that into an error in each element's own user units, taking the
+//! root viewBox and the transforms above the element into account.
+
+use crate::bbox::Rect;
+use crate::style::StyledNode;
+use crate::transform::{self, Matrix};
+use crate::tree::{Document, Element, Node};
+
+/// Most decimals ever asked for: more is below what `f32` renderers keep.
+pub const MAX_DECIMALS: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorBudget {
+    /// How far rounding may move things, in pixels
+    pub error: f64,
+    /// Rendered size of the larger side of the root viewBox in pixels;
+    /// `None` for the root's own `width`/`height`
+    pub size: Option<f64>,
+}
+
+/// The error an element's coordinates may take, laid out like the
+/// document: `children[i]` belongs to the i-th child node.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorNode {
+    /// In the element's user units, after its own transform. `None` where
+    /// that isn't known, e.g. under a CSS transform.
+    pub error: Option<f64>,
+    pub children: Vec<ErrorNode>,
+}
+
+impl ErrorBudget {
+    /// The error as a fraction of the larger side of the root viewBox.
+    /// Rotating or scaling content by that much moves it about as far as
+    /// the budget allows. `None` when the document has no size.
+    pub fn relative(&self, doc: &Document) -> Option<f64> {
+        let root = doc.root_element()?;
+        let (view_box, viewport) = root_sizes(root);
+        let pixels = self.size.or(viewport).or(view_box)?;
+        (pixels > 0.0 && self.error > 0.0).then(|| self.error / pixels)
+    }
+
+    /// The budget with the rendered size taken from `doc` now, so plugins
+    /// that drop `width`/`height` later don't change it.
+    pub fn pinned(&self, doc: &Document) -> Self {
+        let viewport = doc.root_element().and_then(|root| root_sizes(root).1);
+        Self {
+            size: self.size.or(viewport),
+            ..*self
+        }
+    }
+
+    /// The error in the root's user units, before any transform.
+    pub fn root_error(&self, doc: &Document) -> Option<f64> {
+        let (view_box, viewport) = root_sizes(doc.root_element()?);
+        Some(self.relative(doc)? * view_box.or(viewport)?)
+    }
+
+    pub fn resolve(&self, doc: &Document, styles: &[StyledNode]) -> Vec<ErrorNode> {
+        let root_error = self.root_error(doc);
+        resolve_nodes(
+            &doc.root,
+            styles,
+            root_error.map(|_| Matrix::identity()),
+            root_error,
+            true,
+        )
+    }
+}
+
+fn resolve_nodes(
+    nodes: &[Node],
+    styles: &[StyledNode],
+    ctm: Option<Matrix>,
+    root_error: Option<f64>,
+    top_level: bool,
+) -> Vec<ErrorNode> {
+    nodes
+        .iter()
+        .zip(styles)
+        .map(|(node, styled)| {
+            let Node::Element(elem) = node else {
+                return ErrorNode::default();
+            };
+            let ctm = ctm.and_then(|m| {
+                Some(m.multiply(&transform::element_transform(elem, &styled.style)?))
+            });
+            let scale = ctm.map(|m| m.max_scale());
+            // The root viewBox is what the budget is relative to
+            let inner_ctm = ctm.filter(|_| top_level || !has_own_units(elem));
+            ErrorNode {
+                error: root_error
+                    .zip(scale)
+                    .and_then(|(e, s)| (s > 0.0).then(|| e / s)),
+                children: resolve_nodes(
+                    &elem.children,
+                    &styled.children,
+                    inner_ctm,
+                    root_error,
+                    false,
+                ),
+            }
+        })
+        .collect()
+}
+
+// Whether an element's content is drawn in units other than its parent's:
+// a bounding box, wherever it's used, or its own viewBox
+fn has_own_units(elem: &Element) -> bool {
+    let units = |name: &str| elem.attributes.get(name).map(|v| v.trim());
+    match elem.name.as_str() {
+        "clipPath" => units("clipPathUnits") == Some("objectBoundingBox"),
+        "mask" => units("maskContentUnits") == Some("objectBoundingBox"),
+        "marker" | "pattern" | "symbol" => true,
+        "svg" => elem.attributes.contains_key("viewBox"),
+        _ => false,
+    }
+}
+
+// The larger side of the root viewBox and of its viewport, in user units
+// and pixels
+fn root_sizes(root: &Element) -> (Option<f64>, Option<f64>) {
+    let view_box = root
+        .attributes
+        .get("viewBox")
+        .and_then(|v| Rect::from_view_box(v))
+        .map(|r| r.width().max(r.height()));
+    let length = |name: &str| -> Option<f64> {
+        root.attributes
+            .get(name)?
+            .trim()
+            .trim_end_matches("px")
+            .parse()
+            .ok()
+    };
+    let viewport = match (length("width"), length("height")) {
+        (Some(w), Some(h)) => Some(w.max(h)),
+        (w, h) => w.or(h),
+    };
+    (view_box, viewport)
+}
+
+/// Decimals needed so that rounding moves a number by at most half of
+/// `error`, leaving the other half for whatever else a plugin changes.
+pub fn decimals(error: f64) -> usize {
+    if error.is_nan() || error <= 0.0 {
+        return MAX_DECIMALS;
+    }
+    (-error.log10()).ceil().clamp(0.0, MAX_DECIMALS as f64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::style;
+
+    #[test]
+    fn test_resolve() {
+        // 0.1px at 24px on a 2400 unit viewBox is 10 units
+        let doc = parser::parse(
+            "<svg viewBox=\"0 0 2400 1200\"><g transform=\"scale(100)\"><path/></g><g style=\"transform:scale(2)\"><path/></g><clipPath clipPathUnits=\"objectBoundingBox\"><path/></clipPath></svg>",
+        )
+        .unwrap();
+        let budget = ErrorBudget {
+            error: 0.1,
+            size: Some(24.0),
+        };
+        let errors = budget.resolve(&doc, &style::resolve(&doc));
+        let root = &errors[0];
+        assert_eq!(root.error, Some(10.0));
+        assert_eq!(root.children[0].children[0].error, Some(0.1));
+        assert_eq!(root.children[1].error, None);
+        assert_eq!(root.children[2].error, Some(10.0));
+        assert_eq!(root.children[2].children[0].error, None);
+        assert_eq!(decimals(10.0), 0);
+        assert_eq!(decimals(0.1), 1);
+        assert_eq!(decimals(0.004), 3);
+    }
+}
@@ -58,7 +58,7 @@ fn print_node(node: &Node, out: &mut String) {
         Node::Doctype(text) => {
             out.push_str("<!DOCTYPE ");
             out.push_str(text);
-            out.push_str(">");
+            out.push('>');
         }
     }
 }
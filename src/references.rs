@@ -0,0 +1,518 @@
+use crate::css::{self, AtRuleBody, CssItem, Declaration, Stylesheet};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashMap;
+
+// Index of ID definitions and the places referring to them.
+//
+// References are only recognised where SVG allows them: `href`/`xlink:href`,
+// `url(#id)` in paint/clip/mask/filter/marker properties (as attributes, in
+// `style` and in <style>), SMIL timing values such as `begin="btn.click"`,
+// and ARIA ID lists. A value like `fill="#f00"` is a color, not a reference.
+
+/// Indices of the nodes leading from `Document::root` to an element.
+pub type NodePath = Vec<usize>;
+
+/// How an attribute may refer to IDs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefKind {
+    /// `#id` (`href`, `xlink:href`)
+    Href,
+    /// `url(#id)` anywhere in the value
+    Url,
+    /// SMIL timing list: `id.begin+1s; id.click`
+    Timing,
+    /// Whitespace-separated IDs (ARIA relations)
+    IdList,
+}
+
+const URL_ATTRS: [&str; 14] = [
+    "fill",
+    "stroke",
+    "clip-path",
+    "mask",
+    "filter",
+    "marker-start",
+    "marker-mid",
+    "marker-end",
+    "cursor",
+    "style",
+    // Animation values, e.g. <set attributeName="fill" to="url(#g)">
+    "from",
+    "to",
+    "by",
+    "values",
+];
+
+const IDLIST_ATTRS: [&str; 8] = [
+    "aria-activedescendant",
+    "aria-controls",
+    "aria-describedby",
+    "aria-details",
+    "aria-errormessage",
+    "aria-flowto",
+    "aria-labelledby",
+    "aria-owns",
+];
+
+/// The kind of ID references the attribute `name` can hold, if any.
+pub fn ref_kind(name: &str) -> Option<RefKind> {
+    match name {
+        "href" | "xlink:href" => Some(RefKind::Href),
+        "begin" | "end" => Some(RefKind::Timing),
+        _ if URL_ATTRS.contains(&name) => Some(RefKind::Url),
+        _ if IDLIST_ATTRS.contains(&name) => Some(RefKind::IdList),
+        _ => None,
+    }
+}
+
+/// Where an ID is referenced from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub element: NodePath,
+    /// The referencing attribute, `None` for the contents of a <style> element
+    pub attr: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    definitions: HashMap<String, NodePath>,
+    references: HashMap<String, Vec<Reference>>,
+    // Set when a <style> element couldn't be parsed: any ID may be used
+    opaque: bool,
+}
+
+impl ReferenceIndex {
+    pub fn build(doc: &Document) -> Self {
+        let mut index = Self::default();
+        index.scan(&doc.root, &mut Vec::new());
+        index
+    }
+
+    /// The element defining `id`. With duplicate IDs the first one wins, like
+    /// `getElementById`.
+    pub fn definition(&self, id: &str) -> Option<&[usize]> {
+        self.definitions.get(id).map(|p| p.as_slice())
+    }
+
+    /// All IDs defined in the document.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.definitions.keys().map(|k| k.as_str())
+    }
+
+    pub fn references(&self, id: &str) -> &[Reference] {
+        self.references.get(id).map_or(&[], |r| r.as_slice())
+    }
+
+    pub fn is_referenced(&self, id: &str) -> bool {
+        self.opaque || self.references.contains_key(id)
+    }
+
+    /// Whether the document has styles whose references couldn't be read.
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
+
+    fn scan(&mut self, nodes: &[Node], path: &mut NodePath) {
+        for (i, node) in nodes.iter().enumerate() {
+            let Node::Element(elem) = node else {
+                continue;
+            };
+            path.push(i);
+
+            if let Some(id) = elem.attributes.get("id") {
+                self.definitions
+                    .entry(id.clone())
+                    .or_insert_with(|| path.clone());
+            }
+
+            for (name, value) in &elem.attributes {
+                for id in attribute_refs(name, value) {
+                    self.add_ref(id, path, Some(name));
+                }
+            }
+
+            if let Some(text) = css::style_text(elem) {
+                match css::parse_stylesheet(&text) {
+                    Some(sheet) => {
+                        for id in stylesheet_refs(&sheet) {
+                            self.add_ref(id, path, None);
+                        }
+                    }
+                    None => self.opaque = true,
+                }
+            }
+
+            self.scan(&elem.children, path);
+            path.pop();
+        }
+    }
+
+    fn add_ref(&mut self, id: String, path: &[usize], attr: Option<&String>) {
+        self.references.entry(id).or_default().push(Reference {
+            element: path.to_vec(),
+            attr: attr.cloned(),
+        });
+    }
+}
+
+/// IDs referenced by the attribute `name="value"`.
+pub fn attribute_refs(name: &str, value: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    rewrite_attribute(name, value, &mut |id| {
+        ids.push(id.to_string());
+        None
+    });
+    ids
+}
+
+/// IDs referenced by a stylesheet, through `#id` selectors or `url(#id)`.
+pub fn stylesheet_refs(sheet: &Stylesheet) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut sheet = sheet.clone();
+    rewrite_stylesheet(
+        &mut sheet,
+        &mut |id| {
+            ids.push(id.to_string());
+            None
+        },
+        &mut |_| None,
+    );
+    ids
+}
+
+/// Replaces the IDs referenced by an attribute value with `f(id)` where it
+/// returns `Some`. Returns `None` if nothing changed.
+pub fn rewrite_attribute(
+    name: &str,
+    value: &str,
+    f: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    match ref_kind(name)? {
+        RefKind::Href => value
+            .strip_prefix('#')
+            .and_then(&mut *f)
+            .map(|id| format!("#{}", id)),
+        RefKind::Url => rewrite_urls(value, f),
+        RefKind::Timing => rewrite_timing(value, f),
+        RefKind::IdList => {
+            let mut changed = false;
+            let ids: Vec<String> = value
+                .split_whitespace()
+                .map(|id| match f(id) {
+                    Some(new_id) => {
+                        changed = true;
+                        new_id
+                    }
+                    None => id.to_string(),
+                })
+                .collect();
+            changed.then(|| ids.join(" "))
+        }
+    }
+}
+
+/// Rewrites `#id` selectors and `url(#id)` values with `ids`, and `.class`
+/// selectors with `classes`. Keyframe selectors are left alone.
+pub fn rewrite_stylesheet(
+    sheet: &mut Stylesheet,
+    ids: &mut dyn FnMut(&str) -> Option<String>,
+    classes: &mut dyn FnMut(&str) -> Option<String>,
+) {
+    rewrite_items(&mut sheet.items, ids, classes);
+}
+
+fn rewrite_items(
+    items: &mut [CssItem],
+    ids: &mut dyn FnMut(&str) -> Option<String>,
+    classes: &mut dyn FnMut(&str) -> Option<String>,
+) {
+    for item in items {
+        match item {
+            CssItem::Rule(rule) => {
+                for selector in &mut rule.selectors {
+                    *selector = rewrite_selector(selector, ids, classes);
+                }
+                rewrite_declarations(&mut rule.declarations, ids);
+            }
+            CssItem::AtRule(at_rule) => match &mut at_rule.body {
+                // Keyframe selectors are percentages, not element selectors
+                AtRuleBody::Rules(rules) if at_rule.name.ends_with("keyframes") => {
+                    for item in rules {
+                        if let CssItem::Rule(rule) = item {
+                            rewrite_declarations(&mut rule.declarations, ids);
+                        }
+                    }
+                }
+                AtRuleBody::Rules(rules) => rewrite_items(rules, ids, classes),
+                AtRuleBody::Declarations(decls) => rewrite_declarations(decls, ids),
+                AtRuleBody::None => {}
+            },
+        }
+    }
+}
+
+fn rewrite_declarations(decls: &mut [Declaration], ids: &mut dyn FnMut(&str) -> Option<String>) {
+    for decl in decls {
+        if let Some(value) = rewrite_urls(&decl.value, ids) {
+            decl.value = value;
+        }
+    }
+}
+
+// Rewrites `#id` and `.class` in a selector, and the exact-match attribute
+// selectors that select by them, leaving strings alone.
+fn rewrite_selector(
+    selector: &str,
+    ids: &mut dyn FnMut(&str) -> Option<String>,
+    classes: &mut dyn FnMut(&str) -> Option<String>,
+) -> String {
+    let chars: Vec<char> = selector.chars().collect();
+    let mut out = String::with_capacity(selector.len());
+    let mut quote = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            out.push(c);
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '[' => {
+                let start = i;
+                let mut in_quote = None;
+                while i < chars.len() {
+                    match (in_quote, chars[i]) {
+                        (Some(q), c) if c == q => in_quote = None,
+                        (Some(_), _) => {}
+                        (None, q @ ('"' | '\'')) => in_quote = Some(q),
+                        (None, ']') => break,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                let inner: String = chars[start..i].iter().collect();
+                out.push('[');
+                out.push_str(&rewrite_attribute_selector(&inner, ids, classes).unwrap_or(inner));
+                continue;
+            }
+            '#' | '.' => {
+                let start = i;
+                while i < chars.len() && is_ident_char(chars[i]) {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                let name: String = chars[start..i.min(chars.len())].iter().collect();
+                let rewritten = match c {
+                    _ if name.is_empty() => None,
+                    '#' => ids(&name),
+                    _ => classes(&name),
+                };
+                out.push(c);
+                out.push_str(rewritten.as_deref().unwrap_or(&name));
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Rewrites the value of `[id=a]`, `[class~=a]`, `[class="a b"]` and
+// `[href="#a"]`. Substring matches can't be mapped to new names and are
+// left alone, as are other attributes.
+fn rewrite_attribute_selector(
+    inner: &str,
+    ids: &mut dyn FnMut(&str) -> Option<String>,
+    classes: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    let eq = inner.find('=')?;
+    let (name, op) = match inner[..eq].strip_suffix('~') {
+        Some(name) => (name, "~="),
+        None if inner[..eq].ends_with(|c| "|^$*".contains(c)) => return None,
+        None => (&inner[..eq], "="),
+    };
+    // Drop any namespace, e.g. `xlink|href`
+    let local = name.trim().rsplit('|').next()?;
+    let rest = &inner[eq + 1..];
+    let trimmed = rest.trim_start();
+    let lead = &rest[..rest.len() - trimmed.len()];
+    let (quote, value, tail) = match trimmed.chars().next()? {
+        q @ ('"' | '\'') => {
+            let end = trimmed[1..].find(q)? + 1;
+            (Some(q), &trimmed[1..end], &trimmed[end + 1..])
+        }
+        _ => {
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            (None, &trimmed[..end], &trimmed[end..])
+        }
+    };
+
+    let rewritten = match (local, op) {
+        ("id", _) => ids(value)?,
+        ("class", "~=") => classes(value)?,
+        ("class", _) => {
+            let mut changed = false;
+            let names: Vec<String> = value
+                .split_whitespace()
+                .map(|name| match classes(name) {
+                    Some(new_name) => {
+                        changed = true;
+                        new_name
+                    }
+                    None => name.to_string(),
+                })
+                .collect();
+            changed.then(|| names.join(" "))?
+        }
+        ("href", "=") => format!("#{}", ids(value.strip_prefix('#')?)?),
+        _ => return None,
+    };
+    let quote = quote.map(String::from).unwrap_or_default();
+    Some(format!(
+        "{}{}{}{}{}{}{}",
+        name, op, lead, quote, rewritten, quote, tail
+    ))
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || c == '\\' || !c.is_ascii()
+}
+
+// Rewrites `url(#id)`, keeping any quotes
+fn rewrite_urls(value: &str, f: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    if !value.contains("url(") {
+        return None;
+    }
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut changed = false;
+
+    while let Some(pos) = rest.find("url(") {
+        let (before, after) = rest.split_at(pos + 4);
+        out.push_str(before);
+        let Some(end) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let inner = &after[..end];
+        let trimmed = inner.trim();
+        let (quote, target) = match trimmed.chars().next() {
+            Some(q @ ('"' | '\'')) if trimmed.len() >= 2 && trimmed.ends_with(q) => {
+                (Some(q), &trimmed[1..trimmed.len() - 1])
+            }
+            _ => (None, trimmed),
+        };
+        match target.strip_prefix('#').and_then(&mut *f) {
+            Some(id) => {
+                let q = quote.map(String::from).unwrap_or_default();
+                out.push_str(&format!("{q}#{id}{q}"));
+                changed = true;
+            }
+            None => out.push_str(inner),
+        }
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    changed.then_some(out)
+}
+
+// SMIL `begin`/`end`: a `;`-separated list where syncbase and event values
+// start with an element ID, e.g. `a.end+1s` or `button.click`.
+fn rewrite_timing(value: &str, f: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut changed = false;
+    let parts: Vec<String> = value
+        .split(';')
+        .map(|part| {
+            let trimmed = part.trim_start();
+            let lead = &part[..part.len() - trimmed.len()];
+            let id = trimmed.find('.').map(|dot| &trimmed[..dot]);
+            // Skip offsets like `2.5s` and `wallclock(...)`
+            let is_id = id.is_some_and(|id| {
+                id.chars()
+                    .next()
+                    .is_some_and(|c| c.is_alphabetic() || c == '_')
+                    && id.chars().all(is_ident_char)
+            });
+            if let (true, Some(id)) = (is_id, id) {
+                if let Some(new_id) = f(id) {
+                    changed = true;
+                    return format!("{}{}{}", lead, new_id, &trimmed[id.len()..]);
+                }
+            }
+            part.to_string()
+        })
+        .collect();
+    changed.then(|| parts.join(";"))
+}
+
+/// Whether `elem` or any of its descendants defines a referenced ID.
+pub fn defines_referenced_id(elem: &Element, index: &ReferenceIndex) -> bool {
+    elem.attributes
+        .get("id")
+        .is_some_and(|id| index.is_referenced(id))
+        || elem
+            .children
+            .iter()
+            .any(|c| matches!(c, Node::Element(e) if defines_referenced_id(e, index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_index_references() {
+        let input = "<svg><style>#s { fill: url(#g) }</style><linearGradient id=\"g\"/><rect id=\"f00\" fill=\"#f00\"/><g id=\"btn\" aria-labelledby=\"t x\"/><title id=\"t\"/><animate begin=\"btn.click; 1.5s\"/><use xlink:href=\"#s\" style=\"stroke:url('#g')\"/></svg>";
+        let doc = parser::parse(input).unwrap();
+        let index = ReferenceIndex::build(&doc);
+
+        assert!(!index.is_referenced("f00"));
+        assert_eq!(index.definition("g"), Some(&[0, 1][..]));
+        assert_eq!(
+            index.references("g"),
+            &[
+                Reference {
+                    element: vec![0, 0],
+                    attr: None
+                },
+                Reference {
+                    element: vec![0, 6],
+                    attr: Some("style".to_string())
+                },
+            ]
+        );
+        assert!(index.is_referenced("btn"));
+        assert!(index.is_referenced("t"));
+        assert!(index.is_referenced("s"));
+        assert!(!index.is_referenced("1"));
+    }
+
+    #[test]
+    fn test_rewrite_attribute() {
+        let mut f = |id: &str| (id == "a").then(|| "b".to_string());
+        assert_eq!(
+            rewrite_attribute("href", "#a", &mut f),
+            Some("#b".to_string())
+        );
+        assert_eq!(
+            rewrite_attribute("fill", "url(\"#a\") red", &mut f),
+            Some("url(\"#b\") red".to_string())
+        );
+        assert_eq!(
+            rewrite_attribute("end", "a.end+1s;c.click", &mut f),
+            Some("b.end+1s;c.click".to_string())
+        );
+        assert_eq!(rewrite_attribute("fill", "#a", &mut f), None);
+        assert_eq!(rewrite_attribute("id", "a", &mut f), None);
+    }
+}
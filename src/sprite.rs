@@ -0,0 +1,348 @@
+//! Combining standalone icons into a `<symbol>` sprite sheet, and splitting
+//! one back up.
+
+use crate::plugins::{Plugin, PrefixIds, RemoveDuplicateDefs};
+use crate::references::{NodePath, ReferenceIndex};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashSet;
+
+/// A symbol in a sprite, as listed in the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteEntry {
+    pub id: String,
+    pub view_box: Option<String>,
+}
+
+// Attributes that only make sense on a standalone <svg> root
+const ROOT_ONLY_ATTRS: [&str; 10] = [
+    "id",
+    "xmlns",
+    "version",
+    "baseProfile",
+    "x",
+    "y",
+    "width",
+    "height",
+    "viewBox",
+    "enable-background",
+];
+
+/// Turns a file stem into a symbol ID: anything that's not a letter, digit,
+/// `-` or `_` becomes `-`, and an ID can't start with a digit.
+pub fn symbol_id(stem: &str) -> String {
+    let id: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    match id.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => id,
+        _ => format!("i{}", id),
+    }
+}
+
+/// Builds a sprite from already optimized icons, given as `(symbol ID,
+/// document)` pairs. Each icon becomes a `<symbol>` with its `viewBox`, its
+/// internal IDs are prefixed with the symbol ID, and the contents of its
+/// top-level `<defs>` move into one shared `<defs>`, where identical
+/// definitions are merged.
+pub fn build(icons: Vec<(String, Document)>) -> (Document, Vec<SpriteEntry>) {
+    let mut root = Element::new("svg");
+    root.attributes.insert(
+        "xmlns".to_string(),
+        "http://www.w3.org/2000/svg".to_string(),
+    );
+    let mut defs = Element::new("defs");
+    let mut symbols = Vec::new();
+    let mut entries = Vec::new();
+    let mut taken = HashSet::new();
+
+    for (id, mut doc) in icons {
+        // Two files may map to the same ID ("a b.svg" and "a-b.svg")
+        let mut unique = id.clone();
+        let mut n = 2;
+        while !taken.insert(unique.clone()) {
+            unique = format!("{}-{}", id, n);
+            n += 1;
+        }
+        let id = unique;
+
+        PrefixIds::new(&id).apply(&mut doc);
+        let Some(svg) = doc.root_element_mut() else {
+            continue;
+        };
+        let svg = std::mem::replace(svg, Element::new("svg"));
+
+        let view_box = svg.attributes.get("viewBox").cloned().or_else(|| {
+            let width = number(svg.attributes.get("width")?)?;
+            let height = number(svg.attributes.get("height")?)?;
+            Some(format!("0 0 {} {}", width, height))
+        });
+
+        let mut symbol = Element::new("symbol");
+        symbol.attributes.insert("id".to_string(), id.clone());
+        if let Some(view_box) = &view_box {
+            symbol
+                .attributes
+                .insert("viewBox".to_string(), view_box.clone());
+        }
+        for (name, value) in svg.attributes {
+            if name.starts_with("xmlns:") {
+                root.attributes.entry(name).or_insert(value);
+            } else if !ROOT_ONLY_ATTRS.contains(&name.as_str()) {
+                symbol.attributes.insert(name, value);
+            }
+        }
+
+        for child in svg.children {
+            match child {
+                Node::Element(elem) if elem.name == "defs" => defs.children.extend(elem.children),
+                Node::Element(_) | Node::Text(_) | Node::Cdata(_) => symbol.children.push(child),
+                _ => {}
+            }
+        }
+
+        symbols.push(Node::Element(symbol));
+        entries.push(SpriteEntry { id, view_box });
+    }
+
+    if !defs.children.is_empty() {
+        root.children.push(Node::Element(defs));
+    }
+    root.children.extend(symbols);
+
+    let mut doc = Document::new();
+    doc.root.push(Node::Element(root));
+    RemoveDuplicateDefs.apply(&mut doc);
+    (doc, entries)
+}
+
+fn number(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches("px");
+    value.parse::<f64>().ok().map(|_| value.to_string())
+}
+
+/// Turns every `<symbol>` with an ID into a standalone document, returned
+/// with that ID. Definitions outside the symbol are copied into a `<defs>`
+/// only if the symbol (directly or through other definitions) references
+/// them. `<style>` elements are copied as they are.
+pub fn split(doc: &Document) -> Vec<(String, Document)> {
+    let index = ReferenceIndex::build(doc);
+    let mut symbols = Vec::new();
+    let mut styles = Vec::new();
+    find_symbols(&doc.root, &mut Vec::new(), &mut symbols, &mut styles);
+
+    let namespaces: Vec<(String, String)> = doc
+        .root_element()
+        .map(|root| {
+            root.attributes
+                .iter()
+                .filter(|(name, _)| *name == "xmlns" || name.starts_with("xmlns:"))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut docs = Vec::new();
+    for symbol_path in symbols {
+        let Some(Node::Element(symbol)) = doc.node_at(&symbol_path) else {
+            continue;
+        };
+        let Some(id) = symbol.attributes.get("id") else {
+            continue;
+        };
+        let styles: Vec<NodePath> = styles
+            .iter()
+            .filter(|path| !path.starts_with(&symbol_path))
+            .cloned()
+            .collect();
+
+        let mut svg = Element::new("svg");
+        svg.attributes.insert(
+            "xmlns".to_string(),
+            "http://www.w3.org/2000/svg".to_string(),
+        );
+        for (name, value) in &namespaces {
+            svg.attributes.insert(name.clone(), value.clone());
+        }
+        for (name, value) in &symbol.attributes {
+            if name != "id" {
+                svg.attributes.insert(name.clone(), value.clone());
+            }
+        }
+
+        let mut defs = Element::new("defs");
+        for path in required_defs(&index, &symbol_path, &styles) {
+            if let Some(node) = doc.node_at(&path) {
+                defs.children.push(node.clone());
+            }
+        }
+        for path in &styles {
+            if let Some(node) = doc.node_at(path) {
+                svg.children.push(node.clone());
+            }
+        }
+        if !defs.children.is_empty() {
+            svg.children.push(Node::Element(defs));
+        }
+        svg.children.extend(symbol.children.iter().cloned());
+
+        let mut out = Document::new();
+        out.root.push(Node::Element(svg));
+        docs.push((id.clone(), out));
+    }
+    docs
+}
+
+fn find_symbols(
+    nodes: &[Node],
+    path: &mut NodePath,
+    symbols: &mut Vec<NodePath>,
+    styles: &mut Vec<NodePath>,
+) {
+    for (i, node) in nodes.iter().enumerate() {
+        let Node::Element(elem) = node else {
+            continue;
+        };
+        path.push(i);
+        match elem.name.as_str() {
+            "symbol" => symbols.push(path.clone()),
+            "style" => styles.push(path.clone()),
+            _ => {}
+        }
+        find_symbols(&elem.children, path, symbols, styles);
+        path.pop();
+    }
+}
+
+// Outermost definitions referenced from the symbol or the copied styles,
+// following references between definitions, in document order
+fn required_defs(index: &ReferenceIndex, symbol: &[usize], styles: &[NodePath]) -> Vec<NodePath> {
+    let mut included: Vec<NodePath> = vec![symbol.to_vec()];
+    included.extend(styles.iter().cloned());
+    let mut defs: Vec<NodePath> = Vec::new();
+
+    loop {
+        let mut added = false;
+        for id in index.ids() {
+            let Some(def) = index.definition(id) else {
+                continue;
+            };
+            if included.iter().any(|p| def.starts_with(p)) {
+                continue;
+            }
+            let used = index
+                .references(id)
+                .iter()
+                .any(|r| included.iter().any(|p| r.element.starts_with(p)));
+            if used {
+                // A definition nested in an already copied one comes along
+                // with it; one that contains copied ones replaces them
+                defs.retain(|p| !p.starts_with(def));
+                defs.push(def.to_vec());
+                included.push(def.to_vec());
+                added = true;
+            }
+        }
+        if !added {
+            break;
+        }
+    }
+
+    defs.sort();
+    defs
+}
+
+/// A JSON array describing the symbols of a sprite.
+pub fn manifest_json(entries: &[SpriteEntry]) -> String {
+    let items: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let view_box = match &entry.view_box {
+                Some(view_box) => json_string(view_box),
+                None => "null".to_string(),
+            };
+            format!(
+                "  {{\"id\": {}, \"viewBox\": {}}}",
+                json_string(&entry.id),
+                view_box
+            )
+        })
+        .collect();
+    if items.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn test_build_sprite() {
+        let a = "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 24 24\" fill=\"none\"><defs><linearGradient id=\"g\"><stop/></linearGradient></defs><path fill=\"url(#g)\" d=\"M0 0h24\"/></svg>";
+        let b = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"16\" height=\"16\"><defs><linearGradient id=\"g\"><stop/></linearGradient></defs><rect fill=\"url(#g)\"/></svg>";
+        let icons = vec![
+            ("a".to_string(), parser::parse(a).unwrap()),
+            ("b".to_string(), parser::parse(b).unwrap()),
+        ];
+        let expected = "<svg xmlns=\"http://www.w3.org/2000/svg\"><defs><linearGradient id=\"a__g\"><stop/></linearGradient></defs><symbol id=\"a\" viewBox=\"0 0 24 24\" fill=\"none\"><path fill=\"url(#a__g)\" d=\"M0 0h24\"/></symbol><symbol id=\"b\" viewBox=\"0 0 16 16\"><rect fill=\"url(#a__g)\"/></symbol></svg>";
+
+        let (doc, entries) = build(icons);
+        assert_eq!(printer::print(&doc), expected);
+        assert_eq!(
+            manifest_json(&entries),
+            "[\n  {\"id\": \"a\", \"viewBox\": \"0 0 24 24\"},\n  {\"id\": \"b\", \"viewBox\": \"0 0 16 16\"}\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_split_sprite() {
+        let input = "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\"><defs><linearGradient id=\"base\"><stop/></linearGradient><linearGradient id=\"g\" xlink:href=\"#base\"/><clipPath id=\"c\"><rect width=\"5\"/></clipPath></defs><symbol id=\"a\" viewBox=\"0 0 24 24\"><path fill=\"url(#g)\"/></symbol><symbol id=\"b\" viewBox=\"0 0 16 16\" fill=\"red\"><rect/></symbol></svg>";
+
+        let docs = split(&parser::parse(input).unwrap());
+        let printed: Vec<(String, String)> = docs
+            .iter()
+            .map(|(id, doc)| (id.clone(), printer::print(doc)))
+            .collect();
+        assert_eq!(
+            printed,
+            vec![
+                ("a".to_string(), "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"0 0 24 24\"><defs><linearGradient id=\"base\"><stop/></linearGradient><linearGradient id=\"g\" xlink:href=\"#base\"/></defs><path fill=\"url(#g)\"/></svg>".to_string()),
+                ("b".to_string(), "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"0 0 16 16\" fill=\"red\"><rect/></svg>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbol_id() {
+        assert_eq!(symbol_id("arrow left"), "arrow-left");
+        assert_eq!(symbol_id("24px"), "i24px");
+    }
+}
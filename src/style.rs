@@ -0,0 +1,365 @@
+use crate::css::{self, Declaration, Selector};
+use crate::plugins::collections::{INHERITABLE_ATTRS, PRESENTATION_ATTRS};
+use crate::tree::{Document, Element, Node};
+use std::collections::HashMap;
+
+// Computed-style resolver shared by plugins.
+//
+// For every element it combines presentation attributes, the `style`
+// attribute, <style> rules, inheritance and initial values. Values that depend
+// on state we can't see (`:hover`, media queries, selectors we don't
+// understand) are reported as `StyleValue::Dynamic` so plugins can stay away.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StyleValue {
+    Static(String),
+    Dynamic,
+}
+
+/// Where the computed value of a property comes from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Initial,
+    Inherited,
+    Attribute,
+    Stylesheet,
+    StyleAttribute,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Computed {
+    pub value: StyleValue,
+    pub origin: Origin,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComputedStyle {
+    props: HashMap<String, Computed>,
+    // Set when a stylesheet couldn't be parsed: nothing can be trusted
+    unresolved: bool,
+}
+
+impl ComputedStyle {
+    /// The specified or inherited value of `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Computed> {
+        self.props.get(name)
+    }
+
+    /// The computed value of `name`, falling back to its initial value.
+    /// `None` when the value is dynamic or unknown.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        if self.unresolved {
+            return None;
+        }
+        match self.props.get(name) {
+            Some(Computed {
+                value: StyleValue::Static(v),
+                ..
+            }) => Some(v),
+            Some(_) => None,
+            None => initial_value(name),
+        }
+    }
+
+    pub fn origin(&self, name: &str) -> Origin {
+        self.props.get(name).map_or(Origin::Initial, |c| c.origin)
+    }
+
+    pub fn is_dynamic(&self, name: &str) -> bool {
+        self.unresolved
+            || matches!(
+                self.props.get(name),
+                Some(Computed {
+                    value: StyleValue::Dynamic,
+                    ..
+                })
+            )
+    }
+
+    /// Whether any property of the element can't be resolved statically.
+    pub fn has_dynamic(&self) -> bool {
+        self.unresolved || self.props.values().any(|c| c.value == StyleValue::Dynamic)
+    }
+}
+
+/// Computed styles laid out like the document: `children[i]` belongs to the
+/// i-th child node. Non-element nodes get an empty style.
+#[derive(Debug, Clone, Default)]
+pub struct StyledNode {
+    pub style: ComputedStyle,
+    pub children: Vec<StyledNode>,
+}
+
+pub fn initial_value(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "fill" | "stop-color" | "flood-color" => "black",
+        "stroke" | "clip-path" | "mask" | "filter" | "marker-start" | "marker-mid"
+        | "marker-end" | "stroke-dasharray" | "transform" => "none",
+        "fill-opacity" | "stroke-opacity" | "opacity" | "stop-opacity" | "flood-opacity"
+        | "stroke-width" => "1",
+        "stroke-dashoffset" => "0",
+        "stroke-linecap" => "butt",
+        "stroke-linejoin" => "miter",
+        "stroke-miterlimit" => "4",
+        "fill-rule" | "clip-rule" => "nonzero",
+        "display" => "inline",
+        "visibility" => "visible",
+        "overflow" => "visible",
+        "letter-spacing" | "word-spacing" => "normal",
+        "text-anchor" => "start",
+        "font-size" => "medium",
+        _ => return None,
+    })
+}
+
+struct SheetRule {
+    // `None` for selectors we can't evaluate; they may match anything
+    selector: Option<Selector>,
+    specificity: (u32, u32, u32),
+    declarations: Vec<Declaration>,
+    dynamic: bool,
+}
+
+/// Resolves the computed style of every element in the document.
+pub fn resolve(doc: &Document) -> Vec<StyledNode> {
+    let rules = collect_rules(&doc.root);
+    let root_style = ComputedStyle {
+        props: HashMap::new(),
+        unresolved: rules.is_none(),
+    };
+    let rules = rules.unwrap_or_default();
+    resolve_nodes(&doc.root, &rules, &root_style, &mut Vec::new())
+}
+
+fn collect_rules(nodes: &[Node]) -> Option<Vec<SheetRule>> {
+    let mut rules = Vec::new();
+    collect_rules_into(nodes, &mut rules)?;
+    Some(rules)
+}
+
+fn collect_rules_into(nodes: &[Node], rules: &mut Vec<SheetRule>) -> Option<()> {
+    for node in nodes {
+        if let Node::Element(elem) = node {
+            if let Some(text) = css::style_text(elem) {
+                let sheet = css::parse_stylesheet(&text)?;
+                // <style media="..."> only applies under some conditions
+                let media = elem.attributes.get("media").is_some_and(|m| m != "all");
+                for (rule, conditional) in sheet.rules() {
+                    for selector in &rule.selectors {
+                        let parsed = Selector::parse(selector);
+                        rules.push(SheetRule {
+                            specificity: parsed.as_ref().map_or((0, 0, 0), |s| s.specificity()),
+                            dynamic: media
+                                || conditional
+                                || parsed.as_ref().is_none_or(|s| s.has_pseudo()),
+                            selector: parsed,
+                            declarations: rule.declarations.clone(),
+                        });
+                    }
+                }
+            }
+            collect_rules_into(&elem.children, rules)?;
+        }
+    }
+    Some(())
+}
+
+fn resolve_nodes<'a>(
+    nodes: &'a [Node],
+    rules: &[SheetRule],
+    parent: &ComputedStyle,
+    ancestors: &mut Vec<&'a Element>,
+) -> Vec<StyledNode> {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Element(elem) => {
+                let style = compute_element(elem, rules, parent, ancestors);
+                ancestors.push(elem);
+                let children = resolve_nodes(&elem.children, rules, &style, ancestors);
+                ancestors.pop();
+                StyledNode { style, children }
+            }
+            _ => StyledNode::default(),
+        })
+        .collect()
+}
+
+// Cascade precedence, lowest first
+#[derive(PartialEq, PartialOrd)]
+struct Precedence {
+    important: bool,
+    level: u8, // 0 = attribute, 1 = stylesheet, 2 = style attribute
+    specificity: (u32, u32, u32),
+    order: usize,
+}
+
+fn compute_element(
+    elem: &Element,
+    rules: &[SheetRule],
+    parent: &ComputedStyle,
+    ancestors: &[&Element],
+) -> ComputedStyle {
+    // Winning declaration for each property: (precedence, value, origin)
+    let mut winners: HashMap<String, (Precedence, StyleValue, Origin)> = HashMap::new();
+    let mut order = 0;
+    let mut declare = |name: &str, value: StyleValue, origin: Origin, precedence: Precedence| {
+        if winners.get(name).is_none_or(|(p, _, _)| *p <= precedence) {
+            winners.insert(name.to_string(), (precedence, value, origin));
+        }
+    };
+
+    for (name, value) in &elem.attributes {
+        if PRESENTATION_ATTRS.contains(&name.as_str())
+            || matches!(
+                name.as_str(),
+                "transform" | "transform-origin" | "transform-box"
+            )
+        {
+            order += 1;
+            let precedence = Precedence {
+                important: false,
+                level: 0,
+                specificity: (0, 0, 0),
+                order,
+            };
+            declare(
+                name,
+                StyleValue::Static(value.trim().to_string()),
+                Origin::Attribute,
+                precedence,
+            );
+        }
+    }
+
+    for rule in rules {
+        let matches = rule
+            .selector
+            .as_ref()
+            .is_none_or(|s| s.matches(elem, ancestors));
+        if !matches {
+            continue;
+        }
+        for decl in &rule.declarations {
+            order += 1;
+            let value = if rule.dynamic {
+                StyleValue::Dynamic
+            } else {
+                StyleValue::Static(decl.value.clone())
+            };
+            let precedence = Precedence {
+                important: decl.important,
+                level: 1,
+                specificity: rule.specificity,
+                order,
+            };
+            declare(&decl.name, value, Origin::Stylesheet, precedence);
+        }
+    }
+
+    if let Some(style) = elem.attributes.get("style") {
+        for decl in css::parse_declarations(style) {
+            order += 1;
+            let precedence = Precedence {
+                important: decl.important,
+                level: 2,
+                specificity: (0, 0, 0),
+                order,
+            };
+            declare(
+                &decl.name,
+                StyleValue::Static(decl.value),
+                Origin::StyleAttribute,
+                precedence,
+            );
+        }
+    }
+
+    // Start from the inherited properties of the parent
+    let mut props: HashMap<String, Computed> = parent
+        .props
+        .iter()
+        .filter(|(name, _)| INHERITABLE_ATTRS.contains(&name.as_str()))
+        .map(|(name, computed)| {
+            let value = Computed {
+                value: computed.value.clone(),
+                origin: Origin::Inherited,
+            };
+            (name.clone(), value)
+        })
+        .collect();
+
+    for (name, (_, value, origin)) in winners {
+        let keyword = match &value {
+            StyleValue::Static(v) => v.to_ascii_lowercase(),
+            StyleValue::Dynamic => String::new(),
+        };
+        let inherited = INHERITABLE_ATTRS.contains(&name.as_str());
+        match keyword.as_str() {
+            "inherit" => {
+                let value = parent.props.get(&name).map_or_else(
+                    || StyleValue::Static(initial_value(&name).unwrap_or_default().to_string()),
+                    |c| c.value.clone(),
+                );
+                props.insert(name, Computed { value, origin });
+            }
+            "initial" => {
+                props.remove(&name);
+            }
+            "unset" => {
+                if !inherited {
+                    props.remove(&name);
+                }
+            }
+            _ => {
+                props.insert(name, Computed { value, origin });
+            }
+        }
+    }
+
+    ComputedStyle {
+        props,
+        unresolved: parent.unresolved,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_inheritance_and_cascade() {
+        let input = "<svg><style>.a { stroke: red } #b { stroke: blue }</style><g fill=\"none\" stroke-width=\"2\"><rect class=\"a\" id=\"b\" style=\"stroke-width: 3\"/></g></svg>";
+        let doc = parser::parse(input).unwrap();
+        let styles = resolve(&doc);
+        let rect = &styles[0].children[1].children[0].style;
+
+        assert_eq!(rect.value("fill"), Some("none"));
+        assert_eq!(rect.origin("fill"), Origin::Inherited);
+        assert_eq!(rect.value("stroke"), Some("blue"));
+        assert_eq!(rect.value("stroke-width"), Some("3"));
+        assert_eq!(rect.value("opacity"), Some("1"));
+    }
+
+    #[test]
+    fn test_presentation_attribute_loses_to_stylesheet() {
+        let input = "<svg><style>rect { display: none }</style><rect display=\"inline\"/></svg>";
+        let doc = parser::parse(input).unwrap();
+        let styles = resolve(&doc);
+        assert_eq!(styles[0].children[1].style.value("display"), Some("none"));
+    }
+
+    #[test]
+    fn test_dynamic_styles() {
+        let input = "<svg><style>g:hover { fill: red } @media print { rect { stroke: blue } }</style><g><rect/></g></svg>";
+        let doc = parser::parse(input).unwrap();
+        let styles = resolve(&doc);
+        let rect = &styles[0].children[1].children[0].style;
+
+        assert!(rect.is_dynamic("fill"));
+        assert!(rect.is_dynamic("stroke"));
+        assert!(!rect.is_dynamic("opacity"));
+        assert_eq!(rect.value("fill"), None);
+    }
+}
@@ -0,0 +1,354 @@
+//! Affine transforms as used by the `transform` attribute.
+
+use crate::path::Point;
+use crate::style::{ComputedStyle, Origin};
+use crate::tree::Element;
+use std::f64::consts::PI;
+
+/// A 2D affine matrix, laid out like SVG's `matrix(a b c d e f)`:
+///
+/// ```text
+/// [ a c e ]
+/// [ b d f ]
+/// [ 0 0 1 ]
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 0.0,
+            f: 0.0,
+        }
+    }
+
+    pub fn translate(tx: f64, ty: f64) -> Self {
+        Self {
+            e: tx,
+            f: ty,
+            ..Self::identity()
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            a: sx,
+            d: sy,
+            ..Self::identity()
+        }
+    }
+
+    /// `self * other`: `other` is applied first, like in
+    /// `transform="self other"`.
+    pub fn multiply(&self, other: &Matrix) -> Matrix {
+        Matrix {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+
+    pub fn is_identity(&self) -> bool {
+        is_approx(self.a, 1.0)
+            && is_approx(self.b, 0.0)
+            && is_approx(self.c, 0.0)
+            && is_approx(self.d, 1.0)
+            && is_approx(self.e, 0.0)
+            && is_approx(self.f, 0.0)
+    }
+
+    /// This transform applied around `origin` instead of `0 0`.
+    pub fn around(&self, origin: Point) -> Matrix {
+        Matrix::translate(origin.x, origin.y)
+            .multiply(self)
+            .multiply(&Matrix::translate(-origin.x, -origin.y))
+    }
+
+    /// The transform undoing this one, `None` when it's degenerate.
+    pub fn inverse(&self) -> Option<Matrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        Some(Matrix {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    /// The most a length can grow under this transform (the largest
+    /// singular value of the linear part).
+    pub fn max_scale(&self) -> f64 {
+        let p = self.a * self.a + self.b * self.b + self.c * self.c + self.d * self.d;
+        let det = self.a * self.d - self.b * self.c;
+        let q = (p * p - 4.0 * det * det).max(0.0).sqrt();
+        ((p + q) / 2.0).sqrt()
+    }
+}
+
+pub(crate) fn is_approx(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-10
+}
+
+/// Parses a `transform-origin` given in user units. Keywords and
+/// percentages are relative to a reference box and give `None`, as does a
+/// single value (the other one defaults to `center`).
+pub fn parse_origin(s: &str) -> Option<Point> {
+    let nums: Vec<f64> = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches("px").parse().ok())
+        .collect::<Option<_>>()?;
+    match nums[..] {
+        [x, y] => Some(Point::new(x, y)),
+        [x, y, 0.0] => Some(Point::new(x, y)),
+        _ => None,
+    }
+}
+
+/// The transform an element's attributes give it, `transform-origin`
+/// included. `None` when it's set in a way that isn't understood: CSS
+/// transforms may use units, and origins may be relative to a box.
+pub fn element_transform(elem: &Element, style: &ComputedStyle) -> Option<Matrix> {
+    if matches!(
+        style.origin("transform"),
+        Origin::Stylesheet | Origin::StyleAttribute
+    ) || ["transform", "transform-origin", "transform-box"]
+        .iter()
+        .any(|p| style.is_dynamic(p))
+        || style.origin("transform-box") != Origin::Initial
+    {
+        return None;
+    }
+    let m = match elem.attributes.get("transform") {
+        Some(t) => parse(t),
+        None => Matrix::identity(),
+    };
+    match style.origin("transform-origin") {
+        Origin::Initial => Some(m),
+        Origin::Attribute => Some(m.around(parse_origin(style.value("transform-origin")?)?)),
+        _ => None,
+    }
+}
+
+/// Parses a transform list into one matrix. Unknown or malformed functions
+/// are skipped.
+pub fn parse(s: &str) -> Matrix {
+    parse_list(s)
+        .iter()
+        .fold(Matrix::identity(), |acc, m| acc.multiply(m))
+}
+
+/// Parses a transform list into its individual functions.
+pub fn parse_list(s: &str) -> Vec<Matrix> {
+    let mut matrices = Vec::new();
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if c.is_ascii_alphabetic() {
+            // Read name
+            let mut name = String::new();
+            name.push(c);
+            while let Some((_, nc)) = chars.peek() {
+                if nc.is_ascii_alphabetic() {
+                    name.push(*nc);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            // Skip to (
+            while let Some((_, nc)) = chars.peek() {
+                if *nc == '(' {
+                    chars.next();
+                    break;
+                }
+                chars.next();
+            }
+
+            // Read args
+            let mut args = Vec::new();
+            let mut cur_num = String::new();
+            while let Some((_, nc)) = chars.peek() {
+                if *nc == ')' {
+                    chars.next();
+                    break;
+                }
+                let cc = *nc;
+                if cc.is_numeric() || cc == '.' || cc == '-' || cc == 'e' || cc == 'E' {
+                    cur_num.push(cc);
+                    chars.next();
+                } else {
+                    if !cur_num.is_empty() {
+                        if let Ok(n) = cur_num.parse::<f64>() {
+                            args.push(n);
+                        }
+                        cur_num.clear();
+                    }
+                    chars.next(); // Skip separator
+                }
+            }
+            if !cur_num.is_empty() {
+                if let Ok(n) = cur_num.parse::<f64>() {
+                    args.push(n);
+                }
+            }
+
+            match name.as_str() {
+                "translate" => {
+                    let tx = *args.first().unwrap_or(&0.0);
+                    let ty = *args.get(1).unwrap_or(&0.0);
+                    matrices.push(Matrix {
+                        a: 1.0,
+                        b: 0.0,
+                        c: 0.0,
+                        d: 1.0,
+                        e: tx,
+                        f: ty,
+                    });
+                }
+                "scale" => {
+                    let sx = *args.first().unwrap_or(&1.0);
+                    let sy = *args.get(1).unwrap_or(&sx); // if 1 arg, scale(s, s)
+                    matrices.push(Matrix {
+                        a: sx,
+                        b: 0.0,
+                        c: 0.0,
+                        d: sy,
+                        e: 0.0,
+                        f: 0.0,
+                    });
+                }
+                "rotate" => {
+                    let angle = *args.first().unwrap_or(&0.0);
+                    // cx, cy optional
+                    let cx = *args.get(1).unwrap_or(&0.0);
+                    let cy = *args.get(2).unwrap_or(&0.0);
+
+                    let rad = angle * PI / 180.0;
+                    let c = rad.cos();
+                    let s = rad.sin();
+
+                    // define rotate(a, cx, cy) as translate(cx, cy) rotate(a) translate(-cx, -cy)
+                    let mut m = Matrix::identity();
+                    if cx != 0.0 || cy != 0.0 {
+                        m = m.multiply(&Matrix {
+                            a: 1.0,
+                            b: 0.0,
+                            c: 0.0,
+                            d: 1.0,
+                            e: cx,
+                            f: cy,
+                        });
+                    }
+                    m = m.multiply(&Matrix {
+                        a: c,
+                        b: s,
+                        c: -s,
+                        d: c,
+                        e: 0.0,
+                        f: 0.0,
+                    });
+                    if cx != 0.0 || cy != 0.0 {
+                        m = m.multiply(&Matrix {
+                            a: 1.0,
+                            b: 0.0,
+                            c: 0.0,
+                            d: 1.0,
+                            e: -cx,
+                            f: -cy,
+                        });
+                    }
+                    matrices.push(m);
+                }
+                "skewX" => {
+                    let a = *args.first().unwrap_or(&0.0);
+                    let rad = a * PI / 180.0;
+                    matrices.push(Matrix {
+                        a: 1.0,
+                        b: 0.0,
+                        c: rad.tan(),
+                        d: 1.0,
+                        e: 0.0,
+                        f: 0.0,
+                    });
+                }
+                "skewY" => {
+                    let a = *args.first().unwrap_or(&0.0);
+                    let rad = a * PI / 180.0;
+                    matrices.push(Matrix {
+                        a: 1.0,
+                        b: rad.tan(),
+                        c: 0.0,
+                        d: 1.0,
+                        e: 0.0,
+                        f: 0.0,
+                    });
+                }
+                "matrix" if args.len() == 6 => {
+                    matrices.push(Matrix {
+                        a: args[0],
+                        b: args[1],
+                        c: args[2],
+                        d: args[3],
+                        e: args[4],
+                        f: args[5],
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    matrices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rotate_around_point() {
+        let m = parse("rotate(90 10 10)");
+        let p = m.apply(Point::new(20.0, 10.0));
+        assert!(is_approx(p.x, 10.0) && is_approx(p.y, 20.0), "{:?}", p);
+    }
+
+    #[test]
+    fn test_max_scale() {
+        assert!(is_approx(parse("scale(2 3)").max_scale(), 3.0));
+        assert!(is_approx(parse("rotate(30) scale(2)").max_scale(), 2.0));
+    }
+}
@@ -31,8 +31,54 @@ pub struct Document {
     pub root: Vec<Node>, // Usually contains one root Element, but can have comments/doctype before it
 }
 
+impl Default for Document {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Document {
     pub fn new() -> Self {
         Self { root: Vec::new() }
     }
+
+    /// The node reached by following child indices from `root`.
+    pub fn node_at(&self, path: &[usize]) -> Option<&Node> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.root.get(*first)?;
+        for i in rest {
+            match node {
+                Node::Element(elem) => node = elem.children.get(*i)?,
+                _ => return None,
+            }
+        }
+        Some(node)
+    }
+
+    pub fn node_at_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+        let (first, rest) = path.split_first()?;
+        let mut node = self.root.get_mut(*first)?;
+        for i in rest {
+            match node {
+                Node::Element(elem) => node = elem.children.get_mut(*i)?,
+                _ => return None,
+            }
+        }
+        Some(node)
+    }
+
+    /// The outermost `<svg>` element.
+    pub fn root_element(&self) -> Option<&Element> {
+        self.root.iter().find_map(|node| match node {
+            Node::Element(elem) if elem.name == "svg" => Some(elem),
+            _ => None,
+        })
+    }
+
+    pub fn root_element_mut(&mut self) -> Option<&mut Element> {
+        self.root.iter_mut().find_map(|node| match node {
+            Node::Element(elem) if elem.name == "svg" => Some(elem),
+            _ => None,
+        })
+    }
 }